@@ -1,6 +1,9 @@
 //! oligarchy-ai-mcp — read-only MCP server for the local AI/voice aspect.
 //! See `docs/mcp-servers-roadmap.md` §4.5.
 
+mod ollama;
+mod probe;
+
 use oligarchy_mcp_core::audit;
 use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
 use oligarchy_mcp_core::runner_mcp;
@@ -21,18 +24,22 @@ impl Server {
             .unwrap_or_else(|e| format!("[error] {e}"))
     }
 
-    #[tool(description = "List installed Ollama models (read-only).")]
+    #[tool(description = "Installed Ollama models as JSON: name, digest, size, modified, plus family, parameter count, quantization, context length and capabilities from `ollama show`.")]
     fn ollama_models(&self) -> String {
         audit::tool(ASPECT, "ollama_models", "");
-        runner::run(ASPECT, "ollama", &["list"], QUICK_TIMEOUT)
-            .unwrap_or_else(|e| format!("[error] {e}"))
+        ollama::models()
     }
 
-    #[tool(description = "Show currently-running Ollama processes (ollama ps).")]
+    #[tool(description = "Loaded Ollama models (`ollama ps`) as JSON: size, GPU/CPU split as VRAM and RAM bytes, context, and seconds until unload; with host MemTotal/MemAvailable for fit checks.")]
     fn ollama_running(&self) -> String {
         audit::tool(ASPECT, "ollama_running", "");
-        runner::run(ASPECT, "ollama", &["ps"], QUICK_TIMEOUT)
-            .unwrap_or_else(|e| format!("[error] {e}"))
+        ollama::running()
+    }
+
+    #[tool(description = "Local inference health probe: runs a fixed one-line prompt through `ollama run --verbose` on an installed model (never pulls) and reports load time, time to first token, tokens/sec, and from `ollama ps` whether the model fell back to CPU. Loads the model into memory; takes up to 3 minutes on a cold load.")]
+    fn ai_probe(&self, #[tool(param)] model: String) -> String {
+        audit::tool(ASPECT, "ai_probe", &model);
+        probe::run(&model)
     }
 
     #[tool(description = "Blipply Assistant user service status (read-only).")]
@@ -75,7 +82,7 @@ impl ServerHandler for Server {
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt().init();
     tracing::info!(aspect = ASPECT, "starting");
-    runner_mcp::serve(Server::default()).await
+    runner_mcp::serve(Server).await
 }
 
 #[cfg(test)]
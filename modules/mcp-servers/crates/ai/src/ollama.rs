@@ -0,0 +1,343 @@
+//! Typed views of `ollama list`, `ollama ps` and `ollama show`.
+//!
+//! The CLI prints fixed-width tables whose cells may contain single spaces
+//! (`2.0 GB`, `4 minutes from now`), so rows are split on runs of two or more
+//! spaces and matched to the header by name — newer releases insert a
+//! `CONTEXT` column into `ps`, older ones don't. Sizes are Ollama's decimal
+//! units (1 GB = 10⁹ bytes). Output without a `NAME` header is not a table
+//! (`Error: could not connect to ollama app…`) and is surfaced as `[error]`
+//! rather than read as an empty one.
+
+use std::collections::BTreeMap;
+
+use oligarchy_mcp_core::report;
+use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
+use serde::Serialize;
+
+use crate::ASPECT;
+
+/// `ollama show` is one exec per model; stop there on a very large library.
+const MAX_SHOWN: usize = 50;
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Model {
+    pub name: String,
+    /// The short digest `ollama list` prints (first 12 hex of the manifest).
+    pub digest: String,
+    pub size_bytes: Option<u64>,
+    pub modified: String,
+    pub family: Option<String>,
+    /// As printed: `3.2B`, `7.6B`.
+    pub parameters: Option<String>,
+    pub parameter_count: Option<u64>,
+    pub quantization: Option<String>,
+    pub context_length: Option<u64>,
+    pub embedding_length: Option<u64>,
+    pub capabilities: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Running {
+    pub name: String,
+    pub digest: String,
+    pub size_bytes: Option<u64>,
+    /// `100% GPU`, `48%/52% CPU/GPU`, `100% CPU`.
+    pub processor: String,
+    pub gpu_fraction: Option<f64>,
+    pub vram_bytes: Option<u64>,
+    pub ram_bytes: Option<u64>,
+    /// The context window the runner was loaded with, when `ps` shows it.
+    pub context: Option<u64>,
+    pub until: String,
+    /// Seconds until unload; `None` for `Forever` or an unparsable value.
+    pub expires_in_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct HostMemory {
+    total_bytes: Option<u64>,
+    available_bytes: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct ModelsReport {
+    models: Vec<Model>,
+    total_bytes: u64,
+    notes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct RunningReport {
+    running: Vec<Running>,
+    vram_bytes: u64,
+    ram_bytes: u64,
+    host_memory: HostMemory,
+}
+
+pub fn models() -> String {
+    let mut models = match runner::run(ASPECT, "ollama", &["list"], QUICK_TIMEOUT) {
+        Ok(o) => match parse_list(&o) {
+            Ok(m) => m,
+            Err(e) => return e,
+        },
+        Err(e) => return format!("[error] {e}"),
+    };
+    let mut notes = Vec::new();
+    for m in models.iter_mut().take(MAX_SHOWN) {
+        match runner::run(ASPECT, "ollama", &["show", &m.name], QUICK_TIMEOUT) {
+            Ok(show) => apply_show(m, &show),
+            Err(e) => notes.push(format!("ollama show {}: {e}", m.name)),
+        }
+    }
+    if models.len() > MAX_SHOWN {
+        notes.push(format!("metadata only for the first {MAX_SHOWN} of {} models", models.len()));
+    }
+    let total_bytes = models.iter().filter_map(|m| m.size_bytes).sum();
+    report::json(&ModelsReport { models, total_bytes, notes })
+}
+
+pub fn running() -> String {
+    let running = match runner::run(ASPECT, "ollama", &["ps"], QUICK_TIMEOUT) {
+        Ok(o) => match parse_ps(&o) {
+            Ok(r) => r,
+            Err(e) => return e,
+        },
+        Err(e) => return format!("[error] {e}"),
+    };
+    report::json(&RunningReport {
+        vram_bytes: running.iter().filter_map(|r| r.vram_bytes).sum(),
+        ram_bytes: running.iter().filter_map(|r| r.ram_bytes).sum(),
+        running,
+        host_memory: std::fs::read_to_string("/proc/meminfo").map(|m| meminfo(&m)).unwrap_or_default(),
+    })
+}
+
+/// Header-keyed rows of a CLI table: one map per data line. `Err` carries
+/// the output as `[error] …` when the first line is not a `NAME …` header.
+fn table(out: &str) -> Result<Vec<BTreeMap<String, String>>, String> {
+    let mut lines = out.lines().filter(|l| !l.trim().is_empty());
+    let header = lines.next().map(cells).unwrap_or_default();
+    if !header.first().is_some_and(|h| h.eq_ignore_ascii_case("NAME")) {
+        return Err(format!("[error] ollama: {}", out.trim()));
+    }
+    Ok(lines
+        .map(|line| header.iter().map(|h| h.to_ascii_uppercase()).zip(cells(line)).collect())
+        .collect())
+}
+
+fn cells(line: &str) -> Vec<String> {
+    line.split("  ").map(str::trim).filter(|c| !c.is_empty()).map(str::to_string).collect()
+}
+
+pub fn parse_list(out: &str) -> Result<Vec<Model>, String> {
+    Ok(table(out)?
+        .into_iter()
+        .map(|mut row| Model {
+            name: row.remove("NAME").unwrap_or_default(),
+            digest: row.remove("ID").unwrap_or_default(),
+            size_bytes: row.get("SIZE").and_then(|s| bytes(s)),
+            modified: row.remove("MODIFIED").unwrap_or_default(),
+            ..Default::default()
+        })
+        .filter(|m| !m.name.is_empty())
+        .collect())
+}
+
+pub fn parse_ps(out: &str) -> Result<Vec<Running>, String> {
+    Ok(table(out)?
+        .into_iter()
+        .map(|mut row| {
+            let size_bytes = row.get("SIZE").and_then(|s| bytes(s));
+            let processor = row.remove("PROCESSOR").unwrap_or_default();
+            let gpu_fraction = gpu_fraction(&processor);
+            let until = row.remove("UNTIL").unwrap_or_default();
+            Running {
+                name: row.remove("NAME").unwrap_or_default(),
+                digest: row.remove("ID").unwrap_or_default(),
+                size_bytes,
+                vram_bytes: size_bytes.zip(gpu_fraction).map(|(s, g)| (s as f64 * g).round() as u64),
+                ram_bytes: size_bytes.zip(gpu_fraction).map(|(s, g)| (s as f64 * (1.0 - g)).round() as u64),
+                processor,
+                gpu_fraction,
+                context: row.get("CONTEXT").and_then(|c| c.parse().ok()),
+                expires_in_secs: expires_in(&until),
+                until,
+            }
+        })
+        .filter(|r| !r.name.is_empty())
+        .collect())
+}
+
+/// `2.0 GB`, `934 MB`, `4.7GB` → bytes, decimal units as Ollama prints them.
+fn bytes(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let n: f64 = s[..split].trim().parse().ok()?;
+    let scale = match s[split..].trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1.0,
+        "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        _ => return None,
+    };
+    Some((n * scale).round() as u64)
+}
+
+/// Share of the model on the GPU, 0–1.
+fn gpu_fraction(processor: &str) -> Option<f64> {
+    let (pcts, units) = processor.split_once(' ')?;
+    let pcts: Vec<f64> = pcts.split('/').map(|p| p.trim_end_matches('%').parse::<f64>().ok()).collect::<Option<_>>()?;
+    let units: Vec<&str> = units.trim().split('/').collect();
+    if pcts.len() != units.len() {
+        return None;
+    }
+    Some(units.iter().zip(&pcts).filter(|(u, _)| u.eq_ignore_ascii_case("gpu")).map(|(_, p)| p / 100.0).sum())
+}
+
+/// `4 minutes from now`, `About a minute from now`, `59 seconds from now`.
+fn expires_in(until: &str) -> Option<u64> {
+    let until = until.to_ascii_lowercase();
+    let rest = until.strip_suffix(" from now")?;
+    let mut words = rest.split_whitespace();
+    let first = words.next()?;
+    let (n, unit) = match first {
+        "about" | "a" | "an" => {
+            let unit = if first == "about" { words.nth(1)? } else { words.next()? };
+            (1, unit)
+        }
+        _ => (first.parse().ok()?, words.next()?),
+    };
+    let secs = match unit.trim_end_matches('s') {
+        "second" => 1,
+        "minute" => 60,
+        "hour" => 3_600,
+        "day" => 86_400,
+        "week" => 604_800,
+        _ => return None,
+    };
+    Some(n * secs)
+}
+
+/// Fills metadata from `ollama show`: indented `key  value` rows under
+/// section headings (`Model`, `Capabilities`, `Parameters`, …).
+pub fn apply_show(m: &mut Model, show: &str) {
+    let mut section = String::new();
+    for line in show.lines() {
+        let indent = line.len() - line.trim_start().len();
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+        if indent <= 2 {
+            section = text.to_ascii_lowercase();
+            continue;
+        }
+        if section == "capabilities" {
+            m.capabilities.push(text.to_string());
+            continue;
+        }
+        if section != "model" {
+            continue;
+        }
+        let row = cells(text);
+        let [key, value] = row.as_slice() else { continue };
+        match key.to_ascii_lowercase().as_str() {
+            "architecture" => m.family = Some(value.clone()),
+            "parameters" => {
+                m.parameter_count = parameter_count(value);
+                m.parameters = Some(value.clone());
+            }
+            "quantization" => m.quantization = Some(value.clone()),
+            "context length" => m.context_length = value.parse().ok(),
+            "embedding length" => m.embedding_length = value.parse().ok(),
+            _ => {}
+        }
+    }
+}
+
+/// `3.2B`, `770M`, `1.5T` → count.
+fn parameter_count(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (n, scale) = match s.chars().last()?.to_ascii_uppercase() {
+        'K' => (&s[..s.len() - 1], 1e3),
+        'M' => (&s[..s.len() - 1], 1e6),
+        'B' => (&s[..s.len() - 1], 1e9),
+        'T' => (&s[..s.len() - 1], 1e12),
+        _ => (s, 1.0),
+    };
+    Some((n.parse::<f64>().ok()? * scale).round() as u64)
+}
+
+fn meminfo(text: &str) -> HostMemory {
+    let kb = |key: &str| {
+        text.lines()
+            .find_map(|l| l.strip_prefix(key))
+            .and_then(|v| v.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+            .map(|k| k * 1024)
+    };
+    HostMemory { total_bytes: kb("MemTotal:"), available_bytes: kb("MemAvailable:") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_list_and_show() {
+        let list = "\
+NAME                       ID              SIZE      MODIFIED
+llama3.2:latest            a80c4f17acd5    2.0 GB    3 weeks ago
+qwen2.5-coder:7b           2b0496514337    4.7 GB    2 days ago
+";
+        let mut models = parse_list(list).unwrap();
+        assert_eq!(models.len(), 2);
+        assert_eq!((models[0].digest.as_str(), models[0].size_bytes), ("a80c4f17acd5", Some(2_000_000_000)));
+        assert_eq!(models[1].modified, "2 days ago");
+
+        let show = "  Model
+    architecture        llama
+    parameters          3.2B
+    context length      131072
+    embedding length    3072
+    quantization        Q4_K_M
+
+  Capabilities
+    completion
+    tools
+
+  Parameters
+    stop    \"<|eot_id|>\"
+";
+        apply_show(&mut models[0], show);
+        let m = &models[0];
+        assert_eq!((m.family.as_deref(), m.quantization.as_deref()), (Some("llama"), Some("Q4_K_M")));
+        assert_eq!((m.parameter_count, m.context_length), (Some(3_200_000_000), Some(131_072)));
+        assert_eq!(m.capabilities, ["completion", "tools"]);
+    }
+
+    #[test]
+    fn parses_ps_split_and_expiry() {
+        let ps = "\
+NAME              ID              SIZE      PROCESSOR          CONTEXT    UNTIL
+llama3.2:3b       a80c4f17acd5    4.0 GB    100% GPU           4096       4 minutes from now
+mistral:latest    f974a74358d6    5.0 GB    40%/60% CPU/GPU    8192       Forever
+";
+        let r = parse_ps(ps).unwrap();
+        assert_eq!((r[0].vram_bytes, r[0].ram_bytes), (Some(4_000_000_000), Some(0)));
+        assert_eq!((r[0].context, r[0].expires_in_secs), (Some(4096), Some(240)));
+        assert_eq!((r[1].vram_bytes, r[1].ram_bytes), (Some(3_000_000_000), Some(2_000_000_000)));
+        assert_eq!((r[1].until.as_str(), r[1].expires_in_secs), ("Forever", None));
+
+        let old = "NAME    ID    SIZE    PROCESSOR    UNTIL\nphi3:mini    4f2222    2.6 GB    100% CPU    About a minute from now\n";
+        let r = parse_ps(old).unwrap();
+        assert_eq!((r[0].gpu_fraction, r[0].context, r[0].expires_in_secs), (Some(0.0), None, Some(60)));
+        assert!(parse_ps("NAME    ID    SIZE    PROCESSOR    UNTIL\n").unwrap().is_empty());
+        // A down daemon is an error, not an empty table.
+        let down = "Error: could not connect to ollama app, is it running?";
+        assert_eq!(parse_ps(down), Err(format!("[error] ollama: {down}")));
+        assert!(parse_list("").is_err());
+        let mem = meminfo("MemTotal:       32000000 kB\nMemAvailable:   16000000 kB\n");
+        assert_eq!(mem.available_bytes, Some(16_384_000_000));
+    }
+}
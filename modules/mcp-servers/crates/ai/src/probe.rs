@@ -0,0 +1,213 @@
+//! `ai_probe`: a fixed one-line prompt through `ollama run --verbose`, timed.
+//!
+//! `--verbose` appends Ollama's timing block (load, prompt eval, eval) after
+//! the response; time to first token is load plus prompt evaluation. Where
+//! the weights ended up is read back from `ollama ps` right after the run, so
+//! a driver update that silently drops offload shows as `cpu_fallback`.
+//!
+//! The model must already be in `ollama list`: `ollama run` on an unknown
+//! name would pull it from the network, and an exact match against installed
+//! names also keeps arbitrary strings off the command line.
+
+use std::time::Duration;
+
+use oligarchy_mcp_core::{audit, report};
+use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
+use serde::Serialize;
+
+use crate::{ollama, ASPECT};
+
+/// Short, deterministic, and cheap on every model family.
+const PROMPT: &str = "Reply with exactly one word: ready";
+/// A cold load of a large model from disk dominates; generation is tiny.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(180);
+const MAX_RESPONSE_CHARS: usize = 500;
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct Timings {
+    total_secs: Option<f64>,
+    load_secs: Option<f64>,
+    prompt_tokens: Option<u64>,
+    prompt_eval_secs: Option<f64>,
+    prompt_tokens_per_sec: Option<f64>,
+    eval_tokens: Option<u64>,
+    eval_secs: Option<f64>,
+    tokens_per_sec: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct Probe {
+    model: String,
+    prompt: &'static str,
+    response: String,
+    /// Load plus prompt evaluation: when the first generated token appears.
+    ttft_secs: Option<f64>,
+    timings: Timings,
+    processor: Option<String>,
+    gpu_fraction: Option<f64>,
+    vram_bytes: Option<u64>,
+    /// Any share of the model on the CPU. `None` when `ollama ps` no longer
+    /// listed it (keep-alive of 0, or evicted).
+    cpu_fallback: Option<bool>,
+    notes: Vec<String>,
+}
+
+pub fn run(model: &str) -> String {
+    let installed = match runner::run(ASPECT, "ollama", &["list"], QUICK_TIMEOUT) {
+        Ok(o) => match ollama::parse_list(&o) {
+            Ok(m) => m,
+            Err(e) => return e,
+        },
+        Err(e) => return format!("[error] {e}"),
+    };
+    let Some(name) = installed.iter().map(|m| m.name.as_str()).find(|n| same_model(n, model)) else {
+        let names: Vec<&str> = installed.iter().map(|m| m.name.as_str()).collect();
+        let reason = format!(
+            "{model} is not installed (ai_probe never pulls); installed: {}",
+            if names.is_empty() { "none".into() } else { names.join(", ") }
+        );
+        audit::log(ASPECT, "DENIED", &format!("ai_probe: {reason}"), "");
+        return format!("[denied] {reason}");
+    };
+    // A failed run (out of memory, runner crash) exits non-zero with the
+    // reason on stderr; `run` would hand that back as the model's response.
+    let out = match runner::run_checked(ASPECT, "ollama", &["run", name, "--verbose", PROMPT], PROBE_TIMEOUT) {
+        Ok(o) => o,
+        Err(e) => return format!("[error] {e}"),
+    };
+    let (response, timings) = parse_verbose(&out);
+    let mut notes = Vec::new();
+    if timings == Timings::default() {
+        notes.push("no --verbose timing block in the output".into());
+    }
+    let loaded = runner::run(ASPECT, "ollama", &["ps"], QUICK_TIMEOUT)
+        .ok()
+        .and_then(|o| ollama::parse_ps(&o).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .find(|r| same_model(&r.name, name));
+    let cpu_fallback = loaded.as_ref().and_then(|r| r.gpu_fraction).map(|g| g < 1.0);
+    match cpu_fallback {
+        Some(true) => notes.push(format!(
+            "{} is not fully on the GPU; check the driver and VRAM headroom",
+            loaded.as_ref().map_or("", |r| r.processor.as_str())
+        )),
+        None => notes.push(format!("{name} not in `ollama ps` after the run; placement unknown")),
+        Some(false) => {}
+    }
+    report::json(&Probe {
+        model: name.to_string(),
+        prompt: PROMPT,
+        response: response.chars().take(MAX_RESPONSE_CHARS).collect(),
+        ttft_secs: timings.load_secs.zip(timings.prompt_eval_secs).map(|(l, p)| l + p),
+        timings,
+        processor: loaded.as_ref().map(|r| r.processor.clone()),
+        gpu_fraction: loaded.as_ref().and_then(|r| r.gpu_fraction),
+        vram_bytes: loaded.and_then(|r| r.vram_bytes),
+        cpu_fallback,
+        notes,
+    })
+}
+
+/// `llama3.2` and `llama3.2:latest` are the same model.
+fn same_model(a: &str, b: &str) -> bool {
+    let norm = |s: &str| if s.contains(':') { s.to_string() } else { format!("{s}:latest") };
+    norm(a) == norm(b)
+}
+
+/// Splits the response from the timing block and parses the latter.
+fn parse_verbose(out: &str) -> (String, Timings) {
+    let mut t = Timings::default();
+    let mut response = Vec::new();
+    for line in out.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            response.push(line);
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "total duration" => t.total_secs = duration(value),
+            "load duration" => t.load_secs = duration(value),
+            "prompt eval count" => t.prompt_tokens = count(value),
+            "prompt eval duration" => t.prompt_eval_secs = duration(value),
+            "prompt eval rate" => t.prompt_tokens_per_sec = rate(value),
+            "eval count" => t.eval_tokens = count(value),
+            "eval duration" => t.eval_secs = duration(value),
+            "eval rate" => t.tokens_per_sec = rate(value),
+            _ => response.push(line),
+        }
+    }
+    (response.join("\n").trim().to_string(), t)
+}
+
+/// A Go `time.Duration` string: `1.5s`, `812.3ms`, `45µs`, `1m2.5s`.
+fn duration(s: &str) -> Option<f64> {
+    let mut total = 0.0;
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let split = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+        let n: f64 = rest[..split].parse().ok()?;
+        let unit_end = rest[split..].find(|c: char| c.is_ascii_digit()).map_or(rest.len(), |i| split + i);
+        let scale = match &rest[split..unit_end] {
+            "h" => 3_600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 1e-3,
+            "µs" | "us" => 1e-6,
+            "ns" => 1e-9,
+            _ => return None,
+        };
+        total += n * scale;
+        rest = &rest[unit_end..];
+    }
+    Some(total)
+}
+
+/// `26 token(s)`.
+fn count(s: &str) -> Option<u64> {
+    s.split_whitespace().next()?.parse().ok()
+}
+
+/// `51.28 tokens/s`.
+fn rate(s: &str) -> Option<f64> {
+    s.split_whitespace().next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_verbose_block() {
+        let out = "\
+Ready
+
+total duration:       1.912345s
+load duration:        1.2s
+prompt eval count:    26 token(s)
+prompt eval duration: 123.5ms
+prompt eval rate:     210.53 tokens/s
+eval count:           3 token(s)
+eval duration:        58ms
+eval rate:            51.72 tokens/s";
+        let (response, t) = parse_verbose(out);
+        assert_eq!(response, "Ready");
+        assert_eq!((t.load_secs, t.prompt_tokens, t.eval_tokens), (Some(1.2), Some(26), Some(3)));
+        assert_eq!((t.tokens_per_sec, t.prompt_tokens_per_sec), (Some(51.72), Some(210.53)));
+        assert!((t.prompt_eval_secs.unwrap() - 0.1235).abs() < 1e-9);
+        assert_eq!(parse_verbose("Note: ready").0, "Note: ready");
+    }
+
+    #[test]
+    fn parses_go_durations_and_names() {
+        assert_eq!(duration("1m2.5s"), Some(62.5));
+        assert!((duration("45µs").unwrap() - 45e-6).abs() < 1e-12);
+        assert_eq!(duration("2h0m3s"), Some(7_203.0));
+        assert_eq!(duration("fast"), None);
+        assert!(same_model("llama3.2:latest", "llama3.2"));
+        assert!(!same_model("llama3.2:3b", "llama3.2"));
+    }
+}
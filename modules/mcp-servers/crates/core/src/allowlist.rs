@@ -19,6 +19,10 @@ pub const SYSTEM: &[&str] = &[
     "journalctl",
     "nixos-rebuild",
     "nix",
+    // `nix-store --gc --print-roots` / `--print-dead` only — both report
+    // without deleting. A bare `--gc` collects; [`args_allowed`] refuses
+    // every other argument list.
+    "nix-store",
     "nixfmt",
     "read",
     "fd",
@@ -31,6 +35,9 @@ pub const NET: &[&str] = &[
     "nft",
     "resolvectl",
     "ip",
+    // `journalctl _TRANSPORT=kernel --grep=STRICT-EGRESS-` only, for the
+    // strict-egress drop history.
+    "journalctl",
     "demod-ip-blocker",
     // `oligarchy-blocklist status` / `test <ip>` only — both read-only. The
     // `update` and `panic` verbs mutate, so they are never passed from here.
@@ -86,6 +93,9 @@ pub const AI: &[&str] = &[
 pub const SECRETS: &[&str] = &[
     "sops-blackbox-ls",
     "age",
+    // `age-keygen -y <file>` only: identity file in, recipients out. Enforced
+    // by [`args_allowed`]; `-o` would write a new key.
+    "age-keygen",
 ];
 
 /// CLIs the `vm` aspect may invoke.
@@ -143,6 +153,16 @@ pub fn is_allowed(aspect: &str, prog: &str) -> bool {
     list_for(aspect).is_some_and(|list| list.contains(&prog))
 }
 
+/// Argument lists for programs that are only safe in one form, checked by
+/// `runner` before spawning; every other program takes any arguments.
+pub fn args_allowed(prog: &str, args: &[&str]) -> bool {
+    match prog {
+        "nix-store" => matches!(args, ["--gc", "--print-roots" | "--print-dead"]),
+        "age-keygen" => matches!(args, ["-y", file] if !file.starts_with('-')),
+        _ => true,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -162,6 +182,24 @@ mod tests {
         assert!(list_for("bogus").is_none());
     }
 
+    #[test]
+    fn pins_age_keygen_to_recipient_derivation() {
+        assert!(args_allowed("age-keygen", &["-y", "/home/u/.config/sops/age/keys.txt"]));
+        for args in [&["-o", "keys.txt"][..], &[], &["-y"], &["-y", "-o"], &["-y", "a", "b"], &["-pq"]] {
+            assert!(!args_allowed("age-keygen", args), "{args:?}");
+        }
+        assert!(args_allowed("systemctl", &["--failed"]));
+    }
+
+    #[test]
+    fn pins_nix_store_to_reporting_gc() {
+        assert!(args_allowed("nix-store", &["--gc", "--print-roots"]));
+        assert!(args_allowed("nix-store", &["--gc", "--print-dead"]));
+        for args in [&["--gc"][..], &["--delete", "/nix/store/aaa"], &["--gc", "--print-dead", "--max-freed", "1"]] {
+            assert!(!args_allowed("nix-store", args), "{args:?}");
+        }
+    }
+
     #[test]
     fn dcf_does_not_reference_phantom_binaries() {
         // These were never installed on the host — they are docker wrappers
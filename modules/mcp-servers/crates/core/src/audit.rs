@@ -17,8 +17,20 @@ use time::OffsetDateTime;
 
 /// Returns the audit log path for `aspect`.
 pub fn audit_path(aspect: &str) -> PathBuf {
+    state_dir(aspect).join("audit.log")
+}
+
+/// Returns the per-aspect state directory: the audit log's parent, and the
+/// place for anything else an aspect keeps between calls (cached snapshots,
+/// baselines). Same env/XDG/home fallbacks as the module docs describe; the
+/// directory is not created here. A leading `~/` in
+/// `OLIGARCHY_MCP_STATE_DIR` (the NixOS module's default) is expanded.
+pub fn state_dir(aspect: &str) -> PathBuf {
     let base = std::env::var("OLIGARCHY_MCP_STATE_DIR")
-        .map(PathBuf::from)
+        .map(|dir| match (dir.strip_prefix("~/"), std::env::var("HOME")) {
+            (Some(rest), Ok(home)) => PathBuf::from(home).join(rest),
+            _ => PathBuf::from(dir),
+        })
         .unwrap_or_else(|_| {
             let xdg = std::env::var("XDG_STATE_HOME").map(PathBuf::from).unwrap_or_else(|_| {
                 let home = std::env::var("HOME").map(PathBuf::from).unwrap_or_else(|_| PathBuf::from("/tmp"));
@@ -26,7 +38,7 @@ pub fn audit_path(aspect: &str) -> PathBuf {
             });
             xdg.join("oligarchy-mcp")
         });
-    base.join(aspect).join("audit.log")
+    base.join(aspect)
 }
 
 /// Appends one audit line. `detail` is free-form; `cli` is the invoked
@@ -104,6 +116,17 @@ mod tests {
         std::env::remove_var("OLIGARCHY_MCP_STATE_DIR");
     }
 
+    #[test]
+    fn state_dir_expands_home() {
+        let _guard = lock_env();
+        std::env::set_var("OLIGARCHY_MCP_STATE_DIR", "~/.local/state/oligarchy-mcp");
+        let home = std::env::var("HOME").unwrap_or_default();
+        if !home.is_empty() {
+            assert_eq!(state_dir("net"), PathBuf::from(&home).join(".local/state/oligarchy-mcp/net"));
+        }
+        std::env::remove_var("OLIGARCHY_MCP_STATE_DIR");
+    }
+
     #[test]
     fn log_writes_under_tempfile_state_dir() {
         let _guard = lock_env();
@@ -1,6 +1,7 @@
 //! Static table of well-known local API endpoints the project exposes.
 //!
-//! Source of truth for both `egress_coverage` and `local_api_scan`. Built by
+//! Source of truth for `ports-sec`'s `egress_coverage` and `local_api_scan`,
+//! and for the port checks in `hydramesh`'s `node_config` lint. Built by
 //! hand from a one-time scan of the repo. **Add to this table when you add a
 //! new API to the project** — `egress_coverage` will then know to expect it
 //! and `local_api_scan` will probe it.
@@ -17,7 +18,7 @@
 
 use serde::Serialize;
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub enum Proto {
     Tcp,
     Udp,
@@ -3,14 +3,28 @@
 //! - [`audit`] — per-aspect audit log; never panics.
 //! - [`allowlist`] — compile-time-checked CLI allowlists per aspect.
 //! - [`sandbox`] — sandboxed reads confined to the flake directory.
+//! - [`known_endpoints`] — the project's own listeners and their expected remotes.
+//! - [`nft`] — typed nftables ruleset from `nft -j` and a packet evaluator.
+//! - [`procnet`] — sockets from `/proc/net` and their owning processes.
+//! - [`sops`] — sops metadata of an encrypted file, read without decrypting.
 //! - [`runner`] — stdio runner wrapping the MCP server entry point.
+//! - [`report`] — JSON rendering for tools that return structured reports.
+//! - [`users`] — uid ↔ user name from `/etc/passwd`.
+//! - [`validate`] — typed, schema-carrying tool parameters (hosts, IPs, units).
 //! - [`error`] — shared error types.
 
 pub mod allowlist;
 pub mod audit;
 pub mod error;
+pub mod known_endpoints;
+pub mod nft;
+pub mod procnet;
+pub mod report;
 pub mod runner;
 pub mod runner_mcp;
 pub mod sandbox;
+pub mod sops;
+pub mod users;
+pub mod validate;
 
 pub use error::{Error, Result};
\ No newline at end of file
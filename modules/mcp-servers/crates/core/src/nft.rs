@@ -0,0 +1,1111 @@
+//! Typed nftables ruleset, parsed from `nft -j list …`, plus a small
+//! evaluator that walks a packet description through it.
+//!
+//! Shared by the `net` and `ports-sec` aspects. Parsing is tolerant: JSON
+//! objects this model does not know (flowtables, maps, quotas, unusual
+//! expressions) are kept as [`Expr::Other`] or skipped rather than failing
+//! the whole ruleset.
+//!
+//! Evaluation is tri-state. A packet description only carries what the
+//! caller knows (addresses, protocol, ports, optionally interface, uid and
+//! conntrack state); a match on anything else is [`Tri::Unknown`], and the
+//! trace says so instead of guessing. A rule only "matches" when every one of
+//! its matches is definitely true. A `limit` depends on the live packet rate,
+//! so a rule gated by one is undecided too.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::net::IpAddr;
+
+use serde::Serialize;
+use serde_json::Value as Json;
+
+use crate::runner::{self, QUICK_TIMEOUT};
+
+/// Jump/goto nesting limit; the kernel allows 16.
+const MAX_JUMP_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Ruleset {
+    pub tables: Vec<Table>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Table {
+    pub family: String,
+    pub name: String,
+    pub handle: Option<u64>,
+    pub chains: Vec<Chain>,
+    pub sets: Vec<Set>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Chain {
+    pub name: String,
+    pub handle: Option<u64>,
+    /// `filter`, `nat` or `route`; `None` for regular (non-base) chains.
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub hook: Option<String>,
+    pub prio: Option<i64>,
+    pub policy: Option<String>,
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Rule {
+    pub handle: Option<u64>,
+    /// The rule rendered back to nft-like text, e.g. `ip daddr @egress_dyn4 accept`.
+    pub text: String,
+    pub verdict: Option<Verdict>,
+    pub comment: Option<String>,
+    #[serde(skip)]
+    pub exprs: Vec<Expr>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Set {
+    pub name: String,
+    pub handle: Option<u64>,
+    /// Element type, e.g. `ipv4_addr` (concatenations joined with ` . `).
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub flags: Vec<String>,
+    /// Default element timeout in seconds.
+    pub timeout: Option<u64>,
+    pub element_count: usize,
+    /// Elements carrying their own timeout (the resolver-fed dynamic sets).
+    pub elements_with_timeout: usize,
+    /// Seconds until the first element expires.
+    pub soonest_expiry: Option<u64>,
+    #[serde(skip)]
+    pub elements: Vec<Element>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Element {
+    pub value: Value,
+    pub timeout: Option<u64>,
+    pub expires: Option<u64>,
+}
+
+/// A right-hand side: a literal, a set reference, or a composite.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Value {
+    Num(u64),
+    Str(String),
+    /// `@name` — a named set in the same table.
+    SetRef(String),
+    Prefix { addr: String, len: u8 },
+    Range(Box<Value>, Box<Value>),
+    /// An anonymous set `{ a, b }`, or the flag list of `ct state a,b`.
+    Set(Vec<Value>),
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Accept,
+    Drop,
+    Reject,
+    Return,
+    Continue,
+    Queue,
+    Jump(String),
+    Goto(String),
+}
+
+/// What a match looks at.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Key {
+    Meta(String),
+    Ct(String),
+    Payload { protocol: String, field: String },
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Match { left: Key, op: String, right: Value },
+    Verdict(Verdict),
+    Counter,
+    Log { prefix: Option<String> },
+    Limit(String),
+    Other(String),
+}
+
+// ── Parsing ─────────────────────────────────────────────────────────────────
+
+/// Runs `nft -j list ruleset` as `aspect` and parses it.
+pub fn load(aspect: &str) -> Result<Ruleset, String> {
+    let out = runner::run(aspect, "nft", &["-j", "list", "ruleset"], QUICK_TIMEOUT).map_err(|e| e.to_string())?;
+    parse(&out)
+}
+
+/// Runs `nft -j list table <family> <name>` as `aspect` and parses it. The
+/// result holds just that table (or none, if the JSON had no such table).
+pub fn load_table(aspect: &str, family: &str, name: &str) -> Result<Ruleset, String> {
+    let out = runner::run(aspect, "nft", &["-j", "list", "table", family, name], QUICK_TIMEOUT)
+        .map_err(|e| e.to_string())?;
+    parse(&out)
+}
+
+/// Parses `nft -j list ruleset` (or `list table …`) output. `runner::run`
+/// folds stderr into the text, so the JSON object is sliced out first.
+pub fn parse(text: &str) -> Result<Ruleset, String> {
+    let (Some(start), Some(end)) = (text.find('{'), text.rfind('}')) else {
+        return Err("no JSON object in nft output".into());
+    };
+    let root: Json = serde_json::from_str(&text[start..=end]).map_err(|e| format!("parsing nft JSON: {e}"))?;
+    let items = root
+        .get("nftables")
+        .and_then(Json::as_array)
+        .ok_or("nft JSON has no `nftables` array")?;
+
+    let mut tables: BTreeMap<(String, String), Table> = BTreeMap::new();
+    let mut order = Vec::new();
+    let table_for = |obj: &Json| -> (String, String) {
+        let family = str_of(obj, "family").unwrap_or_default();
+        let name = str_of(obj, "table").or_else(|| str_of(obj, "name")).unwrap_or_default();
+        (family, name)
+    };
+    let mut rules = Vec::new();
+    for item in items {
+        if let Some(t) = item.get("table") {
+            let key = table_for(t);
+            order.push(key.clone());
+            tables.insert(
+                key.clone(),
+                Table {
+                    family: key.0,
+                    name: key.1,
+                    handle: t.get("handle").and_then(Json::as_u64),
+                    chains: Vec::new(),
+                    sets: Vec::new(),
+                },
+            );
+        } else if let Some(c) = item.get("chain") {
+            let chain = Chain {
+                name: str_of(c, "name").unwrap_or_default(),
+                handle: c.get("handle").and_then(Json::as_u64),
+                kind: str_of(c, "type"),
+                hook: str_of(c, "hook"),
+                prio: c.get("prio").and_then(Json::as_i64),
+                policy: str_of(c, "policy"),
+                rules: Vec::new(),
+            };
+            if let Some(t) = tables.get_mut(&table_for(c)) {
+                t.chains.push(chain);
+            }
+        } else if let Some(s) = item.get("set") {
+            if let Some(t) = tables.get_mut(&table_for(s)) {
+                t.sets.push(parse_set(s));
+            }
+        } else if let Some(r) = item.get("rule") {
+            // Rules may precede their chain in hand-written fixtures; attach
+            // them once everything else is known.
+            rules.push(r);
+        }
+    }
+    for r in rules {
+        let chain_name = str_of(r, "chain").unwrap_or_default();
+        if let Some(chain) = tables
+            .get_mut(&table_for(r))
+            .and_then(|t| t.chains.iter_mut().find(|c| c.name == chain_name))
+        {
+            chain.rules.push(parse_rule(r));
+        }
+    }
+    Ok(Ruleset { tables: order.into_iter().filter_map(|k| tables.remove(&k)).collect() })
+}
+
+fn str_of(obj: &Json, key: &str) -> Option<String> {
+    obj.get(key).and_then(Json::as_str).map(String::from)
+}
+
+fn parse_set(s: &Json) -> Set {
+    let kind = match s.get("type") {
+        Some(Json::String(t)) => t.clone(),
+        Some(Json::Array(parts)) => parts.iter().filter_map(Json::as_str).collect::<Vec<_>>().join(" . "),
+        _ => String::new(),
+    };
+    let flags = match s.get("flags") {
+        Some(Json::Array(f)) => f.iter().filter_map(Json::as_str).map(String::from).collect(),
+        Some(Json::String(f)) => vec![f.clone()],
+        _ => Vec::new(),
+    };
+    let elements: Vec<Element> = s
+        .get("elem")
+        .and_then(Json::as_array)
+        .map(|e| e.iter().map(parse_element).collect())
+        .unwrap_or_default();
+    Set {
+        name: str_of(s, "name").unwrap_or_default(),
+        handle: s.get("handle").and_then(Json::as_u64),
+        kind,
+        flags,
+        timeout: s.get("timeout").and_then(Json::as_u64),
+        element_count: elements.len(),
+        elements_with_timeout: elements.iter().filter(|e| e.timeout.is_some()).count(),
+        soonest_expiry: elements.iter().filter_map(|e| e.expires).min(),
+        elements,
+    }
+}
+
+/// A set element: a bare value, or `{"elem": {"val": …, "timeout": …, "expires": …}}`.
+fn parse_element(e: &Json) -> Element {
+    match e.get("elem") {
+        Some(inner) => Element {
+            value: inner.get("val").map(parse_value).unwrap_or(Value::Other(inner.to_string())),
+            timeout: inner.get("timeout").and_then(Json::as_u64),
+            expires: inner.get("expires").and_then(Json::as_u64),
+        },
+        None => Element { value: parse_value(e), timeout: None, expires: None },
+    }
+}
+
+fn parse_value(v: &Json) -> Value {
+    match v {
+        Json::Number(n) => n.as_u64().map(Value::Num).unwrap_or(Value::Other(n.to_string())),
+        Json::String(s) => match s.strip_prefix('@') {
+            Some(name) => Value::SetRef(name.to_string()),
+            None => Value::Str(s.clone()),
+        },
+        Json::Array(items) => Value::Set(items.iter().map(parse_value).collect()),
+        Json::Object(o) => {
+            if let Some(p) = o.get("prefix") {
+                Value::Prefix {
+                    addr: str_of(p, "addr").unwrap_or_default(),
+                    len: p.get("len").and_then(Json::as_u64).unwrap_or(0) as u8,
+                }
+            } else if let Some(Json::Array(r)) = o.get("range") {
+                match r.as_slice() {
+                    [lo, hi] => Value::Range(Box::new(parse_value(lo)), Box::new(parse_value(hi))),
+                    _ => Value::Other(v.to_string()),
+                }
+            } else if let Some(Json::Array(items)) = o.get("set") {
+                Value::Set(items.iter().map(parse_value).collect())
+            } else if let Some(inner) = o.get("elem") {
+                parse_element(&serde_json::json!({ "elem": inner })).value
+            } else {
+                Value::Other(v.to_string())
+            }
+        }
+        _ => Value::Other(v.to_string()),
+    }
+}
+
+fn parse_key(v: &Json) -> Key {
+    if let Some(m) = v.get("meta") {
+        Key::Meta(str_of(m, "key").unwrap_or_default())
+    } else if let Some(c) = v.get("ct") {
+        Key::Ct(str_of(c, "key").unwrap_or_default())
+    } else if let Some(p) = v.get("payload") {
+        Key::Payload {
+            protocol: str_of(p, "protocol").unwrap_or_default(),
+            field: str_of(p, "field").unwrap_or_default(),
+        }
+    } else {
+        Key::Other(v.to_string())
+    }
+}
+
+fn parse_rule(r: &Json) -> Rule {
+    let exprs: Vec<Expr> = r
+        .get("expr")
+        .and_then(Json::as_array)
+        .map(|e| e.iter().map(parse_expr).collect())
+        .unwrap_or_default();
+    let verdict = exprs.iter().rev().find_map(|e| match e {
+        Expr::Verdict(v) => Some(v.clone()),
+        _ => None,
+    });
+    Rule {
+        handle: r.get("handle").and_then(Json::as_u64),
+        text: exprs.iter().map(ToString::to_string).collect::<Vec<_>>().join(" "),
+        verdict,
+        comment: str_of(r, "comment"),
+        exprs,
+    }
+}
+
+fn parse_expr(e: &Json) -> Expr {
+    let Some((name, body)) = e.as_object().and_then(|o| o.iter().next()) else {
+        return Expr::Other(e.to_string());
+    };
+    let target = || body.get("target").and_then(Json::as_str).unwrap_or_default().to_string();
+    match name.as_str() {
+        "match" => Expr::Match {
+            left: body.get("left").map(parse_key).unwrap_or(Key::Other(String::new())),
+            op: str_of(body, "op").unwrap_or_else(|| "==".into()),
+            right: body.get("right").map(parse_value).unwrap_or(Value::Other(String::new())),
+        },
+        "accept" => Expr::Verdict(Verdict::Accept),
+        "drop" => Expr::Verdict(Verdict::Drop),
+        "reject" => Expr::Verdict(Verdict::Reject),
+        "return" => Expr::Verdict(Verdict::Return),
+        "continue" => Expr::Verdict(Verdict::Continue),
+        "queue" => Expr::Verdict(Verdict::Queue),
+        "jump" => Expr::Verdict(Verdict::Jump(target())),
+        "goto" => Expr::Verdict(Verdict::Goto(target())),
+        "counter" => Expr::Counter,
+        "log" => Expr::Log { prefix: str_of(body, "prefix") },
+        "limit" => Expr::Limit(format!(
+            "{}/{}",
+            body.get("rate").map(Json::to_string).unwrap_or_default(),
+            str_of(body, "per").unwrap_or_default()
+        )),
+        other => Expr::Other(format!("{other} {body}")),
+    }
+}
+
+// ── Rendering ───────────────────────────────────────────────────────────────
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Num(n) => write!(f, "{n}"),
+            Value::Str(s) => write!(f, "{s}"),
+            Value::SetRef(s) => write!(f, "@{s}"),
+            Value::Prefix { addr, len } => write!(f, "{addr}/{len}"),
+            Value::Range(lo, hi) => write!(f, "{lo}-{hi}"),
+            Value::Set(items) => {
+                let parts: Vec<String> = items.iter().map(ToString::to_string).collect();
+                write!(f, "{{ {} }}", parts.join(", "))
+            }
+            Value::Other(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Meta(k) => write!(f, "meta {k}"),
+            Key::Ct(k) => write!(f, "ct {k}"),
+            Key::Payload { protocol, field } => write!(f, "{protocol} {field}"),
+            Key::Other(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Accept => f.write_str("accept"),
+            Verdict::Drop => f.write_str("drop"),
+            Verdict::Reject => f.write_str("reject"),
+            Verdict::Return => f.write_str("return"),
+            Verdict::Continue => f.write_str("continue"),
+            Verdict::Queue => f.write_str("queue"),
+            Verdict::Jump(t) => write!(f, "jump {t}"),
+            Verdict::Goto(t) => write!(f, "goto {t}"),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Match { left, op, right } if op == "==" || op == "in" => {
+                // `ct state established,related` is how nft prints flag lists.
+                match (left, right) {
+                    (Key::Ct(_), Value::Set(items)) => {
+                        let parts: Vec<String> = items.iter().map(ToString::to_string).collect();
+                        write!(f, "{left} {}", parts.join(","))
+                    }
+                    _ => write!(f, "{left} {right}"),
+                }
+            }
+            Expr::Match { left, op, right } => write!(f, "{left} {op} {right}"),
+            Expr::Verdict(v) => write!(f, "{v}"),
+            Expr::Counter => f.write_str("counter"),
+            Expr::Log { prefix: Some(p) } => write!(f, "log prefix {p:?}"),
+            Expr::Log { prefix: None } => f.write_str("log"),
+            Expr::Limit(rate) => write!(f, "limit rate {rate}"),
+            Expr::Other(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+// ── Lookup and evaluation ───────────────────────────────────────────────────
+
+/// Three-valued match result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tri {
+    Yes,
+    No,
+    Unknown,
+}
+
+impl Tri {
+    fn not(self) -> Tri {
+        match self {
+            Tri::Yes => Tri::No,
+            Tri::No => Tri::Yes,
+            Tri::Unknown => Tri::Unknown,
+        }
+    }
+
+    fn from_bool(b: bool) -> Tri {
+        if b {
+            Tri::Yes
+        } else {
+            Tri::No
+        }
+    }
+}
+
+/// What is known about a packet. Unset fields make matches on them
+/// [`Tri::Unknown`].
+#[derive(Debug, Clone, Serialize)]
+pub struct Packet {
+    pub src: Option<IpAddr>,
+    pub dst: IpAddr,
+    /// `tcp`, `udp`, `icmp`, `ipv6-icmp`, or a protocol number.
+    pub proto: String,
+    pub sport: Option<u16>,
+    pub dport: Option<u16>,
+    /// Output interface; `lo` is assumed for loopback destinations.
+    pub oif: Option<String>,
+    /// Socket owner uid (numeric, as nft stores it).
+    pub skuid: Option<u32>,
+    /// The owner's user name: `nft -j` prints `meta skuid` as a name when
+    /// the uid has one.
+    pub skuser: Option<String>,
+    /// Conntrack state; a first packet is `new`.
+    pub ct_state: Option<String>,
+}
+
+impl Packet {
+    /// A first outgoing packet to `dst`: conntrack state `new`, and `lo` as
+    /// output interface for loopback destinations.
+    pub fn new(dst: IpAddr, proto: &str, dport: Option<u16>) -> Self {
+        Packet {
+            src: None,
+            dst,
+            proto: proto.to_ascii_lowercase(),
+            sport: None,
+            dport,
+            oif: dst.is_loopback().then(|| "lo".to_string()),
+            skuid: None,
+            skuser: None,
+            ct_state: Some("new".into()),
+        }
+    }
+
+    /// Sets the socket owner, with its user name from `users` (uid → name)
+    /// so that rules nft prints by name can be decided.
+    pub fn set_owner(&mut self, uid: Option<u32>, users: &HashMap<u32, String>) {
+        self.skuid = uid;
+        self.skuser = uid.and_then(|u| users.get(&u).cloned());
+    }
+}
+
+/// IANA numbers for the protocol names nft uses with `meta l4proto`.
+fn proto_number(name: &str) -> Option<u64> {
+    Some(match name {
+        "icmp" => 1,
+        "igmp" => 2,
+        "tcp" => 6,
+        "udp" => 17,
+        "gre" => 47,
+        "esp" => 50,
+        "ah" => 51,
+        "ipv6-icmp" | "icmpv6" => 58,
+        "sctp" => 132,
+        "udplite" => 136,
+        n => return n.parse().ok(),
+    })
+}
+
+/// One rule's outcome while walking a chain.
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleStep {
+    pub chain: String,
+    pub handle: Option<u64>,
+    pub text: String,
+    pub result: Tri,
+    /// The first match that failed or could not be decided.
+    pub reason: Option<String>,
+    /// Every match held and only a `limit` left the rule undecided: it
+    /// matches until that rate is exceeded.
+    pub rate_limited: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainResult {
+    pub table: String,
+    pub family: String,
+    pub chain: String,
+    pub hook: Option<String>,
+    pub prio: Option<i64>,
+    pub policy: Option<String>,
+    /// `accept` / `drop` / … from the deciding rule, else the chain policy.
+    /// Rules in `undecided` are assumed not to match; always `Some` from
+    /// [`Table::evaluate_chain`].
+    pub verdict: Option<Verdict>,
+    /// Handle of the rule that decided the verdict; `None` means the policy.
+    pub matched_rule: Option<u64>,
+    pub matched_text: Option<String>,
+    /// Rules that might match given more information, in order.
+    pub undecided: Vec<u64>,
+    pub trace: Vec<RuleStep>,
+}
+
+impl Ruleset {
+    pub fn table(&self, family: &str, name: &str) -> Option<&Table> {
+        self.tables.iter().find(|t| t.family == family && t.name == name)
+    }
+
+    /// Every set, across all tables, whose elements include `ip`.
+    pub fn sets_containing(&self, ip: IpAddr) -> Vec<SetHit<'_>> {
+        let mut hits = Vec::new();
+        for t in &self.tables {
+            for s in &t.sets {
+                if let Some(e) = s.lookup_ip(ip) {
+                    hits.push(SetHit { table: t, set: s, element: e });
+                }
+            }
+        }
+        hits
+    }
+
+    /// Walks `packet` through every filter-type base chain on `hook`, in
+    /// priority order, across tables and families that apply to it. `nat`
+    /// and `route` chains on the same hook do not decide acceptance and are
+    /// skipped.
+    pub fn evaluate(&self, packet: &Packet, hook: &str) -> Vec<ChainResult> {
+        let mut chains: Vec<(&Table, &Chain)> = self
+            .tables
+            .iter()
+            .filter(|t| family_applies(&t.family, packet.dst))
+            .flat_map(|t| t.chains.iter().map(move |c| (t, c)))
+            .filter(|(_, c)| c.hook.as_deref() == Some(hook) && c.kind.as_deref() == Some("filter"))
+            .collect();
+        chains.sort_by_key(|(_, c)| c.prio.unwrap_or(0));
+        chains.into_iter().map(|(t, c)| t.evaluate_chain(c, packet)).collect()
+    }
+}
+
+/// `ip` tables see IPv4, `ip6` tables IPv6, `inet` both.
+fn family_applies(family: &str, addr: IpAddr) -> bool {
+    match family {
+        "ip" => addr.is_ipv4(),
+        "ip6" => addr.is_ipv6(),
+        "inet" => true,
+        _ => false,
+    }
+}
+
+pub struct SetHit<'a> {
+    pub table: &'a Table,
+    pub set: &'a Set,
+    pub element: &'a Element,
+}
+
+impl Set {
+    /// The element covering `ip`, for address-typed sets.
+    pub fn lookup_ip(&self, ip: IpAddr) -> Option<&Element> {
+        if !self.kind.contains("addr") {
+            return None;
+        }
+        self.elements.iter().find(|e| value_contains_ip(&e.value, ip) == Tri::Yes)
+    }
+}
+
+impl Table {
+    pub fn set(&self, name: &str) -> Option<&Set> {
+        self.sets.iter().find(|s| s.name == name)
+    }
+
+    pub fn evaluate_chain(&self, chain: &Chain, packet: &Packet) -> ChainResult {
+        let mut result = ChainResult {
+            table: self.name.clone(),
+            family: self.family.clone(),
+            chain: chain.name.clone(),
+            hook: chain.hook.clone(),
+            prio: chain.prio,
+            policy: chain.policy.clone(),
+            verdict: None,
+            matched_rule: None,
+            matched_text: None,
+            undecided: Vec::new(),
+            trace: Vec::new(),
+        };
+        match self.walk(chain, packet, &mut result, 0) {
+            Some((v, rule)) => {
+                result.verdict = Some(v);
+                if let Some(r) = rule {
+                    result.matched_rule = r.handle;
+                    result.matched_text = Some(r.text.clone());
+                }
+            }
+            // Fell off the end: the base chain's policy (accept by default).
+            None => {
+                result.verdict = Some(match chain.policy.as_deref() {
+                    Some("drop") => Verdict::Drop,
+                    _ => Verdict::Accept,
+                });
+            }
+        }
+        result
+    }
+
+    /// Returns the terminal verdict and the rule that issued it, or `None`
+    /// when the chain (and any chain it jumped to) fell through.
+    fn walk<'a>(
+        &'a self,
+        chain: &'a Chain,
+        packet: &Packet,
+        result: &mut ChainResult,
+        depth: usize,
+    ) -> Option<(Verdict, Option<&'a Rule>)> {
+        if depth > MAX_JUMP_DEPTH {
+            return None;
+        }
+        for rule in &chain.rules {
+            let (tri, reason, rate_limited) = self.rule_matches(rule, packet);
+            result.trace.push(RuleStep {
+                chain: chain.name.clone(),
+                handle: rule.handle,
+                text: rule.text.clone(),
+                result: tri,
+                reason,
+                rate_limited,
+            });
+            match tri {
+                Tri::No => continue,
+                Tri::Unknown => {
+                    if let Some(h) = rule.handle {
+                        result.undecided.push(h);
+                    }
+                    continue;
+                }
+                Tri::Yes => {}
+            }
+            match &rule.verdict {
+                None | Some(Verdict::Continue) => continue,
+                Some(Verdict::Return) => return None,
+                Some(Verdict::Jump(target)) => {
+                    if let Some(next) = self.chains.iter().find(|c| &c.name == target) {
+                        if let Some(v) = self.walk(next, packet, result, depth + 1) {
+                            return Some(v);
+                        }
+                    }
+                }
+                Some(Verdict::Goto(target)) => {
+                    return self
+                        .chains
+                        .iter()
+                        .find(|c| &c.name == target)
+                        .and_then(|next| self.walk(next, packet, result, depth + 1));
+                }
+                Some(v) => return Some((v.clone(), Some(rule))),
+            }
+        }
+        None
+    }
+
+    /// The rule's outcome, the reason for a `No` or `Unknown`, and whether a
+    /// `limit` was the only thing left undecided.
+    fn rule_matches(&self, rule: &Rule, packet: &Packet) -> (Tri, Option<String>, bool) {
+        let mut overall = Tri::Yes;
+        let mut reason = None;
+        let mut limit = None;
+        for e in &rule.exprs {
+            match e {
+                Expr::Match { left, op, right } => match self.match_one(left, op, right, packet) {
+                    Tri::No => return (Tri::No, Some(format!("{e} does not match")), false),
+                    Tri::Unknown if overall == Tri::Yes => {
+                        overall = Tri::Unknown;
+                        reason = Some(format!("{e}: not determined by the packet description"));
+                    }
+                    _ => {}
+                },
+                Expr::Other(s) if overall == Tri::Yes => {
+                    overall = Tri::Unknown;
+                    reason = Some(format!("unsupported expression: {s}"));
+                }
+                Expr::Limit(_) if limit.is_none() => limit = Some(format!("{e}: depends on the current packet rate")),
+                _ => {}
+            }
+        }
+        match (overall, limit) {
+            (Tri::Yes, Some(l)) => (Tri::Unknown, Some(l), true),
+            (t, _) => (t, reason, false),
+        }
+    }
+
+    fn match_one(&self, left: &Key, op: &str, right: &Value, p: &Packet) -> Tri {
+        let positive = match op {
+            "==" | "in" => true,
+            "!=" => false,
+            _ => return Tri::Unknown,
+        };
+        let t = match left {
+            Key::Payload { protocol, field } => match (protocol.as_str(), field.as_str()) {
+                ("ip", "daddr") | ("ip6", "daddr") | ("ip", "saddr") | ("ip6", "saddr") => {
+                    let v6 = protocol == "ip6";
+                    let addr = if field == "daddr" { Some(p.dst) } else { p.src };
+                    match addr {
+                        // `ip daddr …` implies an IPv4 packet; it never matches IPv6.
+                        Some(a) if a.is_ipv6() != v6 => return Tri::No,
+                        Some(a) => self.value_contains(right, &Actual::Addr(a)),
+                        None if p.dst.is_ipv6() != v6 => return Tri::No,
+                        None => Tri::Unknown,
+                    }
+                }
+                (l4 @ ("tcp" | "udp" | "th" | "sctp"), f @ ("sport" | "dport")) => {
+                    if l4 != "th" && p.proto != l4 {
+                        return Tri::No;
+                    }
+                    if l4 == "th" && !matches!(p.proto.as_str(), "tcp" | "udp" | "sctp" | "udplite") {
+                        return Tri::No;
+                    }
+                    match if f == "dport" { p.dport } else { p.sport } {
+                        Some(port) => self.value_contains(right, &Actual::Num(u64::from(port))),
+                        None => Tri::Unknown,
+                    }
+                }
+                _ => Tri::Unknown,
+            },
+            Key::Meta(k) => match k.as_str() {
+                "l4proto" => match proto_number(&p.proto) {
+                    Some(n) => self.value_contains(right, &Actual::Proto(n)),
+                    None => Tri::Unknown,
+                },
+                "nfproto" => self.value_contains(
+                    right,
+                    &Actual::Str(if p.dst.is_ipv4() { "ipv4" } else { "ipv6" }.into()),
+                ),
+                "oif" | "oifname" => match &p.oif {
+                    Some(i) => self.value_contains(right, &Actual::Str(i.clone())),
+                    // Not loopback and no interface given: anything but `lo`.
+                    None if matches!(right, Value::Str(s) if s == "lo") => Tri::No,
+                    None => Tri::Unknown,
+                },
+                "skuid" => match (p.skuid, &p.skuser) {
+                    (None, None) => Tri::Unknown,
+                    (uid, name) => self.value_contains(right, &Actual::User(uid, name.clone())),
+                },
+                _ => Tri::Unknown,
+            },
+            Key::Ct(k) if k == "state" => match &p.ct_state {
+                Some(s) => self.value_contains(right, &Actual::Str(s.clone())),
+                None => Tri::Unknown,
+            },
+            _ => Tri::Unknown,
+        };
+        if positive {
+            t
+        } else {
+            t.not()
+        }
+    }
+
+    /// Whether `right` (a literal, range, prefix, anonymous or named set)
+    /// contains `actual`.
+    fn value_contains(&self, right: &Value, actual: &Actual) -> Tri {
+        match right {
+            Value::SetRef(name) => match self.set(name) {
+                Some(set) => {
+                    let mut any_unknown = false;
+                    for e in &set.elements {
+                        match self.value_contains(&e.value, actual) {
+                            Tri::Yes => return Tri::Yes,
+                            Tri::Unknown => any_unknown = true,
+                            Tri::No => {}
+                        }
+                    }
+                    if any_unknown {
+                        Tri::Unknown
+                    } else {
+                        Tri::No
+                    }
+                }
+                None => Tri::Unknown,
+            },
+            Value::Set(items) => {
+                let results: Vec<Tri> = items.iter().map(|i| self.value_contains(i, actual)).collect();
+                if results.contains(&Tri::Yes) {
+                    Tri::Yes
+                } else if results.contains(&Tri::Unknown) {
+                    Tri::Unknown
+                } else {
+                    Tri::No
+                }
+            }
+            _ => match actual {
+                Actual::Addr(ip) => value_contains_ip(right, *ip),
+                Actual::Num(n) => match right {
+                    Value::Num(v) => Tri::from_bool(v == n),
+                    Value::Str(s) => s.parse::<u64>().map(|v| Tri::from_bool(v == *n)).unwrap_or(Tri::Unknown),
+                    Value::Range(lo, hi) => match (num_of(lo), num_of(hi)) {
+                        (Some(lo), Some(hi)) => Tri::from_bool(lo <= *n && *n <= hi),
+                        _ => Tri::Unknown,
+                    },
+                    _ => Tri::Unknown,
+                },
+                Actual::Proto(n) => match right {
+                    Value::Num(v) => Tri::from_bool(v == n),
+                    Value::Str(s) => proto_number(s).map(|v| Tri::from_bool(v == *n)).unwrap_or(Tri::Unknown),
+                    _ => Tri::Unknown,
+                },
+                Actual::Str(a) => match right {
+                    Value::Str(s) => Tri::from_bool(s == a),
+                    _ => Tri::Unknown,
+                },
+                // A uid literal, or the user name nft prints in its place.
+                Actual::User(uid, name) => match (num_of(right), right) {
+                    (Some(v), _) => uid.map_or(Tri::Unknown, |u| Tri::from_bool(v == u64::from(u))),
+                    (None, Value::Str(s)) => name.as_ref().map_or(Tri::Unknown, |n| Tri::from_bool(n == s)),
+                    _ => Tri::Unknown,
+                },
+            },
+        }
+    }
+}
+
+enum Actual {
+    Addr(IpAddr),
+    Num(u64),
+    Proto(u64),
+    Str(String),
+    /// Socket owner: uid and user name, either of which may be unknown.
+    User(Option<u32>, Option<String>),
+}
+
+fn num_of(v: &Value) -> Option<u64> {
+    match v {
+        Value::Num(n) => Some(*n),
+        Value::Str(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn value_contains_ip(v: &Value, ip: IpAddr) -> Tri {
+    match v {
+        Value::Str(s) => match s.parse::<IpAddr>() {
+            Ok(a) => Tri::from_bool(a == ip),
+            Err(_) => Tri::Unknown,
+        },
+        Value::Prefix { addr, len } => match addr.parse::<IpAddr>() {
+            Ok(net) => Tri::from_bool(in_prefix(ip, net, *len)),
+            Err(_) => Tri::Unknown,
+        },
+        Value::Range(lo, hi) => match (lo.as_ref(), hi.as_ref()) {
+            (Value::Str(lo), Value::Str(hi)) => match (lo.parse::<IpAddr>(), hi.parse::<IpAddr>()) {
+                (Ok(lo), Ok(hi)) => Tri::from_bool(ip_u128(lo).zip(ip_u128(hi)).is_some_and(|(l, h)| {
+                    lo.is_ipv4() == ip.is_ipv4() && (l..=h).contains(&ip_u128(ip).unwrap_or(0))
+                })),
+                _ => Tri::Unknown,
+            },
+            _ => Tri::Unknown,
+        },
+        Value::Set(items) => {
+            let hits: Vec<Tri> = items.iter().map(|i| value_contains_ip(i, ip)).collect();
+            if hits.contains(&Tri::Yes) {
+                Tri::Yes
+            } else if hits.contains(&Tri::Unknown) {
+                Tri::Unknown
+            } else {
+                Tri::No
+            }
+        }
+        _ => Tri::Unknown,
+    }
+}
+
+fn ip_u128(ip: IpAddr) -> Option<u128> {
+    Some(match ip {
+        IpAddr::V4(a) => u128::from(u32::from(a)),
+        IpAddr::V6(a) => u128::from(a),
+    })
+}
+
+/// Whether `ip` lies in `net/len`. Mismatched families never match.
+pub fn in_prefix(ip: IpAddr, net: IpAddr, len: u8) -> bool {
+    match (ip, net) {
+        (IpAddr::V4(a), IpAddr::V4(n)) => {
+            let len = u32::from(len.min(32));
+            let mask = if len == 0 { 0 } else { u32::MAX << (32 - len) };
+            u32::from(a) & mask == u32::from(n) & mask
+        }
+        (IpAddr::V6(a), IpAddr::V6(n)) => {
+            let len = u32::from(len.min(128));
+            let mask = if len == 0 { 0 } else { u128::MAX << (128 - len) };
+            u128::from(a) & mask == u128::from(n) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The strict-egress table as `nft -j list ruleset` prints it (trimmed),
+    /// plus an unrelated `ip` table with a jump and a nat chain, and stderr
+    /// noise.
+    const FIXTURE: &str = r#"{"nftables": [
+      {"metainfo": {"version": "1.1.1", "release_name": "Commodore Bullmoose #2", "json_schema_version": 1}},
+      {"table": {"family": "inet", "name": "strict-egress", "handle": 7}},
+      {"set": {"family": "inet", "name": "egress_static4", "table": "strict-egress", "type": "ipv4_addr", "handle": 1,
+               "flags": ["interval"], "elem": [{"prefix": {"addr": "140.82.112.0", "len": 20}}, {"range": ["151.101.0.1", "151.101.0.9"]}]}},
+      {"set": {"family": "inet", "name": "egress_static6", "table": "strict-egress", "type": "ipv6_addr", "handle": 2, "flags": ["interval"]}},
+      {"set": {"family": "inet", "name": "egress_dyn4", "table": "strict-egress", "type": "ipv4_addr", "handle": 3, "flags": ["timeout"],
+               "elem": [{"elem": {"val": "104.16.0.5", "timeout": 93600, "expires": 90000}},
+                        {"elem": {"val": "185.199.108.133", "timeout": 93600, "expires": 1200}}]}},
+      {"set": {"family": "inet", "name": "egress_dyn6", "table": "strict-egress", "type": "ipv6_addr", "handle": 4, "flags": ["timeout"],
+               "elem": [{"elem": {"val": "2606:4700::6810:5", "timeout": 93600, "expires": 5000}}]}},
+      {"chain": {"family": "inet", "table": "strict-egress", "name": "egress", "handle": 5, "type": "filter", "hook": "output", "prio": 10, "policy": "drop"}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 10,
+                "expr": [{"match": {"op": "==", "left": {"meta": {"key": "oif"}}, "right": "lo"}}, {"accept": null}]}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 11,
+                "expr": [{"match": {"op": "in", "left": {"ct": {"key": "state"}}, "right": ["established", "related"]}}, {"accept": null}]}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 12,
+                "expr": [{"match": {"op": "in", "left": {"ct": {"key": "state"}}, "right": "invalid"}}, {"drop": null}]}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 13,
+                "expr": [{"match": {"op": "==", "left": {"meta": {"key": "skuid"}}, "right": "systemd-resolve"}},
+                         {"match": {"op": "==", "left": {"payload": {"protocol": "udp", "field": "dport"}}, "right": 53}}, {"accept": null}]}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 14,
+                "expr": [{"match": {"op": "==", "left": {"meta": {"key": "l4proto"}}, "right": "icmp"}}, {"accept": null}]}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 15,
+                "expr": [{"match": {"op": "==", "left": {"payload": {"protocol": "udp", "field": "dport"}}, "right": {"set": [123, 41641]}}}, {"accept": null}]}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 16,
+                "expr": [{"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "daddr"}}, "right": "@egress_static4"}}, {"accept": null}]}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 17,
+                "expr": [{"match": {"op": "==", "left": {"payload": {"protocol": "ip6", "field": "daddr"}}, "right": "@egress_static6"}}, {"accept": null}]}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 18,
+                "expr": [{"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "daddr"}}, "right": "@egress_dyn4"}}, {"accept": null}]}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 19,
+                "expr": [{"match": {"op": "==", "left": {"payload": {"protocol": "ip6", "field": "daddr"}}, "right": "@egress_dyn6"}}, {"accept": null}]}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 20,
+                "expr": [{"limit": {"rate": 10, "burst": 5, "per": "minute"}}, {"log": {"prefix": "STRICT-EGRESS-BLOCKED: ", "level": "info"}},
+                         {"counter": {"packets": 3, "bytes": 180}}, {"drop": null}]}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 21,
+                "expr": [{"counter": {"packets": 40, "bytes": 2400}}, {"drop": null}]}},
+      {"table": {"family": "ip", "name": "lab", "handle": 9}},
+      {"chain": {"family": "ip", "table": "lab", "name": "out", "handle": 1, "type": "filter", "hook": "output", "prio": 0, "policy": "accept"}},
+      {"chain": {"family": "ip", "table": "lab", "name": "deny-range", "handle": 2}},
+      {"chain": {"family": "ip", "table": "lab", "name": "nat-out", "handle": 5, "type": "nat", "hook": "output", "prio": -100, "policy": "accept"}},
+      {"rule": {"family": "ip", "table": "lab", "chain": "out", "handle": 3,
+                "expr": [{"match": {"op": "==", "left": {"payload": {"protocol": "tcp", "field": "dport"}}, "right": {"range": [6000, 6010]}}}, {"jump": {"target": "deny-range"}}]}},
+      {"rule": {"family": "ip", "table": "lab", "chain": "deny-range", "handle": 4,
+                "expr": [{"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "daddr"}}, "right": {"prefix": {"addr": "10.0.0.0", "len": 8}}}}, {"reject": null}]}}
+    ]}
+Warning: table ip filter is managed by iptables-nft, do not touch!"#;
+
+    fn fixture() -> Ruleset {
+        parse(FIXTURE).unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_tables_chains_sets_and_rules() {
+        let rs = fixture();
+        assert_eq!(rs.tables.len(), 2);
+        let t = rs.table("inet", "strict-egress").unwrap();
+        let chain = &t.chains[0];
+        assert_eq!(chain.hook.as_deref(), Some("output"));
+        assert_eq!(chain.prio, Some(10));
+        assert_eq!(chain.policy.as_deref(), Some("drop"));
+        assert_eq!(chain.rules.len(), 12);
+        assert_eq!(chain.rules[1].text, "ct state established,related accept");
+        assert_eq!(chain.rules[5].text, "udp dport { 123, 41641 } accept");
+        assert_eq!(chain.rules[10].text, "limit rate 10/minute log prefix \"STRICT-EGRESS-BLOCKED: \" counter drop");
+        assert_eq!(chain.rules[10].verdict, Some(Verdict::Drop));
+        let dyn4 = t.sets.iter().find(|s| s.name == "egress_dyn4").unwrap();
+        assert_eq!(dyn4.element_count, 2);
+        assert_eq!(dyn4.elements_with_timeout, 2);
+        assert_eq!(dyn4.soonest_expiry, Some(1200));
+        assert!(parse("nft: Operation not permitted").is_err());
+    }
+
+    #[test]
+    fn finds_sets_containing_an_ip() {
+        let rs = fixture();
+        let names = |a: &str| -> Vec<String> { rs.sets_containing(ip(a)).iter().map(|h| h.set.name.clone()).collect() };
+        assert_eq!(names("140.82.121.4"), ["egress_static4"]);
+        assert_eq!(names("151.101.0.9"), ["egress_static4"]);
+        assert!(names("151.101.0.10").is_empty());
+        assert_eq!(names("185.199.108.133"), ["egress_dyn4"]);
+        assert_eq!(names("2606:4700::6810:5"), ["egress_dyn6"]);
+        let hit = &rs.sets_containing(ip("104.16.0.5"))[0];
+        assert_eq!(hit.element.timeout, Some(93600));
+    }
+
+    #[test]
+    fn evaluates_five_tuples() {
+        let rs = fixture();
+        let verdict = |p: Packet| {
+            let r = rs.evaluate(&p, "output");
+            let egress = r.into_iter().find(|c| c.chain == "egress").unwrap();
+            (egress.verdict, egress.matched_rule)
+        };
+        // Allowlisted by the dynamic set.
+        assert_eq!(verdict(Packet::new(ip("185.199.108.133"), "tcp", Some(443))), (Some(Verdict::Accept), Some(18)));
+        // NTP by port.
+        assert_eq!(verdict(Packet::new(ip("9.9.9.9"), "udp", Some(123))), (Some(Verdict::Accept), Some(15)));
+        // Not allowlisted: the rate-limited logging drop is undecided, so the
+        // catch-all drop decides.
+        assert_eq!(verdict(Packet::new(ip("9.9.9.9"), "tcp", Some(443))), (Some(Verdict::Drop), Some(21)));
+        // Loopback short-circuits.
+        assert_eq!(verdict(Packet::new(ip("127.0.0.1"), "tcp", Some(11434))), (Some(Verdict::Accept), Some(10)));
+        // Established traffic.
+        let mut p = Packet::new(ip("9.9.9.9"), "tcp", Some(443));
+        p.ct_state = Some("established".into());
+        assert_eq!(verdict(p), (Some(Verdict::Accept), Some(11)));
+        // DNS: the skuid rule is undecidable without a uid, so it is noted.
+        let r = rs.evaluate(&Packet::new(ip("9.9.9.9"), "udp", Some(53)), "output");
+        let egress = r.iter().find(|c| c.chain == "egress").unwrap();
+        assert_eq!(egress.undecided, [13, 20]);
+        assert_eq!(egress.verdict, Some(Verdict::Drop));
+        let limited = egress.trace.iter().find(|t| t.handle == Some(20)).unwrap();
+        assert!(limited.rate_limited && limited.result == Tri::Unknown);
+        assert!(!egress.trace.iter().find(|t| t.handle == Some(13)).unwrap().rate_limited);
+        // nft prints the uid as its user name; a bare uid cannot match it.
+        let mut p = Packet::new(ip("9.9.9.9"), "udp", Some(53));
+        p.skuid = Some(153);
+        assert_eq!(verdict(p.clone()), (Some(Verdict::Drop), Some(21)));
+        p.set_owner(Some(153), &HashMap::from([(153, "systemd-resolve".to_string())]));
+        assert_eq!(verdict(p.clone()), (Some(Verdict::Accept), Some(13)));
+        p.skuser = Some("alice".into());
+        assert_eq!(verdict(p), (Some(Verdict::Drop), Some(21)));
+    }
+
+    #[test]
+    fn follows_jumps_and_orders_by_priority() {
+        let rs = fixture();
+        let r = rs.evaluate(&Packet::new(ip("10.1.2.3"), "tcp", Some(6005)), "output");
+        assert_eq!(r[0].chain, "out", "prio 0 runs before prio 10; nat chains are skipped");
+        assert_eq!(r.len(), 2);
+        assert_eq!(r[0].verdict, Some(Verdict::Reject));
+        assert_eq!(r[0].matched_rule, Some(4));
+        assert_eq!(r[0].trace.len(), 2);
+        // IPv6 packets skip `ip` family tables entirely.
+        let r = rs.evaluate(&Packet::new(ip("2606:4700::6810:5"), "tcp", Some(6005)), "output");
+        assert_eq!(r.len(), 1);
+        assert_eq!(r[0].matched_rule, Some(19));
+    }
+
+    #[test]
+    fn prefix_math() {
+        assert!(in_prefix(ip("10.255.0.1"), ip("10.0.0.0"), 8));
+        assert!(!in_prefix(ip("11.0.0.1"), ip("10.0.0.0"), 8));
+        assert!(in_prefix(ip("1.2.3.4"), ip("0.0.0.0"), 0));
+        assert!(in_prefix(ip("2001:db8::1"), ip("2001:db8::"), 32));
+        assert!(!in_prefix(ip("2001:db8::1"), ip("10.0.0.0"), 8));
+        let set = |items: &[&str]| Value::Set(items.iter().map(|s| Value::Str(s.to_string())).collect());
+        assert_eq!(value_contains_ip(&set(&["1.1.1.1", "$dns"]), ip("1.1.1.1")), Tri::Yes);
+        assert_eq!(value_contains_ip(&set(&["1.1.1.1", "$dns"]), ip("9.9.9.9")), Tri::Unknown);
+        assert_eq!(value_contains_ip(&set(&["1.1.1.1"]), ip("9.9.9.9")), Tri::No);
+    }
+}
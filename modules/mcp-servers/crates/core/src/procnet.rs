@@ -0,0 +1,215 @@
+//! Sockets from `/proc/net/{tcp,tcp6,udp,udp6}` and their owning processes,
+//! read straight from procfs — no `ss` spawn.
+//!
+//! Each row carries the socket inode; [`socket_owners`] maps inodes to PIDs
+//! by walking `/proc/<pid>/fd`, which only sees processes this server may
+//! inspect (its own uid, or all of them with `CAP_SYS_PTRACE`). Sockets of
+//! other processes come back with no owner rather than an error.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+
+use serde::Serialize;
+
+/// The four tables, as `(protocol, path)`.
+pub const TABLES: &[(&str, &str)] = &[
+    ("tcp", "/proc/net/tcp"),
+    ("tcp6", "/proc/net/tcp6"),
+    ("udp", "/proc/net/udp"),
+    ("udp6", "/proc/net/udp6"),
+];
+
+/// `TCP_ESTABLISHED`; connected UDP sockets report it too.
+pub const ESTABLISHED: u8 = 0x01;
+/// `TCP_LISTEN`.
+pub const LISTEN: u8 = 0x0A;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Socket {
+    pub proto: &'static str,
+    pub local: SocketAddr,
+    pub remote: SocketAddr,
+    pub state: u8,
+    pub uid: u32,
+    pub inode: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Process {
+    pub pid: u32,
+    pub comm: Option<String>,
+    pub exe: Option<String>,
+    /// The innermost `.service`/`.scope` in the process's cgroup path.
+    pub unit: Option<String>,
+}
+
+/// Every socket in the four tables; unreadable tables are skipped.
+pub fn sockets() -> Vec<Socket> {
+    TABLES
+        .iter()
+        .filter_map(|(proto, path)| std::fs::read_to_string(path).ok().map(|t| parse(proto, &t)))
+        .flatten()
+        .collect()
+}
+
+/// Parses one `/proc/net/<proto>` table. Rows that do not decode are skipped.
+pub fn parse(proto: &'static str, text: &str) -> Vec<Socket> {
+    text.lines()
+        .skip(1)
+        .filter_map(|line| {
+            // sl local rem st tx:rx tr:when retrnsmt uid timeout inode …
+            let cols: Vec<&str> = line.split_whitespace().collect();
+            if cols.len() < 10 {
+                return None;
+            }
+            Some(Socket {
+                proto,
+                local: decode_endpoint(cols[1])?,
+                remote: decode_endpoint(cols[2])?,
+                state: u8::from_str_radix(cols[3], 16).ok()?,
+                uid: cols[7].parse().ok()?,
+                inode: cols[9].parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// `0100007F:0016` → `127.0.0.1:22`. Addresses are the kernel's in-memory
+/// bytes printed as native-endian 32-bit words, so each word is reversed on
+/// little-endian hosts.
+pub fn decode_endpoint(s: &str) -> Option<SocketAddr> {
+    let (addr, port) = s.rsplit_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let word = |i: usize| u32::from_str_radix(addr.get(i * 8..i * 8 + 8)?, 16).ok().map(u32::to_ne_bytes);
+    let ip = match addr.len() {
+        8 => IpAddr::V4(Ipv4Addr::from(word(0)?)),
+        32 => {
+            let mut bytes = [0u8; 16];
+            for i in 0..4 {
+                bytes[i * 4..i * 4 + 4].copy_from_slice(&word(i)?);
+            }
+            let v6 = Ipv6Addr::from(bytes);
+            // Dual-stack sockets show IPv4 peers as ::ffff:a.b.c.d.
+            v6.to_ipv4_mapped().map_or(IpAddr::V6(v6), IpAddr::V4)
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+/// inode → PID for every socket fd visible under `/proc`.
+pub fn socket_owners() -> HashMap<u64, u32> {
+    socket_owners_in(Path::new("/proc"))
+}
+
+fn socket_owners_in(proc_root: &Path) -> HashMap<u64, u32> {
+    let mut owners = HashMap::new();
+    let Ok(entries) = std::fs::read_dir(proc_root) else {
+        return owners;
+    };
+    for entry in entries.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|n| n.parse::<u32>().ok()) else {
+            continue;
+        };
+        let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        for fd in fds.flatten() {
+            let Ok(target) = std::fs::read_link(fd.path()) else {
+                continue;
+            };
+            if let Some(inode) = target.to_str().and_then(socket_inode) {
+                owners.entry(inode).or_insert(pid);
+            }
+        }
+    }
+    owners
+}
+
+/// `socket:[12345]` → 12345.
+fn socket_inode(link: &str) -> Option<u64> {
+    link.strip_prefix("socket:[")?.strip_suffix(']')?.parse().ok()
+}
+
+/// comm, exe and systemd unit of `pid`; fields that cannot be read are None.
+pub fn process(pid: u32) -> Process {
+    let dir = Path::new("/proc").join(pid.to_string());
+    Process {
+        pid,
+        comm: std::fs::read_to_string(dir.join("comm")).ok().map(|c| c.trim().to_string()),
+        exe: std::fs::read_link(dir.join("exe")).ok().map(|p| p.display().to_string()),
+        unit: std::fs::read_to_string(dir.join("cgroup")).ok().and_then(|c| unit_from_cgroup(&c)),
+    }
+}
+
+/// `0::/user.slice/user-1000.slice/user@1000.service/app.slice/firefox.scope`
+/// → `firefox.scope`.
+pub fn unit_from_cgroup(text: &str) -> Option<String> {
+    let path = text
+        .lines()
+        .find_map(|l| l.strip_prefix("0::"))
+        .or_else(|| text.lines().find_map(|l| l.split_once(":name=systemd:").map(|(_, p)| p)))?;
+    path.split('/')
+        .rev()
+        .find(|c| c.ends_with(".service") || c.ends_with(".scope"))
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TCP: &str = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:0277 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 21345 1 0000000000000000 100 0 0 10 0
+   1: 0501A8C0:C6A2 22D8B85D:01BB 01 00000000:00000000 02:000A3D70 00000000  1000        0 88231 2 0000000000000000 20 4 30 10 -1
+";
+
+    #[test]
+    fn parses_tcp_table() {
+        let s = parse("tcp", TCP);
+        assert_eq!(s.len(), 2);
+        assert_eq!(s[0].local, "127.0.0.1:631".parse().unwrap());
+        assert_eq!(s[0].state, LISTEN);
+        assert_eq!(s[1].remote, "93.184.216.34:443".parse().unwrap());
+        assert_eq!((s[1].state, s[1].uid, s[1].inode), (ESTABLISHED, 1000, 88231));
+    }
+
+    #[test]
+    fn decodes_v6_and_mapped_v4() {
+        assert_eq!(decode_endpoint("00000000000000000000000001000000:0035"), Some("[::1]:53".parse().unwrap()));
+        assert_eq!(
+            decode_endpoint("0000000000000000FFFF00000501A8C0:01BB"),
+            Some("192.168.1.5:443".parse().unwrap())
+        );
+        assert_eq!(
+            decode_endpoint("B80D0120000000000000000001000000:0050"),
+            Some("[2001:db8::1]:80".parse().unwrap())
+        );
+        assert_eq!(decode_endpoint("zz:0050"), None);
+    }
+
+    #[test]
+    fn unit_from_cgroup_paths() {
+        assert_eq!(
+            unit_from_cgroup("0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-firefox-1234.scope\n").as_deref(),
+            Some("app-firefox-1234.scope")
+        );
+        assert_eq!(unit_from_cgroup("0::/system.slice/sshd.service\n").as_deref(), Some("sshd.service"));
+        assert_eq!(unit_from_cgroup("0::/\n"), None);
+    }
+
+    #[test]
+    fn finds_own_socket_inodes() {
+        assert_eq!(socket_inode("socket:[88231]"), Some(88231));
+        assert_eq!(socket_inode("pipe:[1]"), None);
+        let dir = tempfile::tempdir().unwrap();
+        let fd = dir.path().join("42/fd");
+        std::fs::create_dir_all(&fd).unwrap();
+        std::os::unix::fs::symlink("socket:[777]", fd.join("3")).unwrap();
+        std::os::unix::fs::symlink("/dev/null", fd.join("4")).unwrap();
+        std::fs::create_dir_all(dir.path().join("self")).unwrap();
+        assert_eq!(socket_owners_in(dir.path()), HashMap::from([(777, 42)]));
+    }
+}
@@ -0,0 +1,32 @@
+//! Structured tool output.
+//!
+//! Tools that parse CLI output into typed models return them as pretty-printed
+//! JSON text: an MCP client can consume the result as data, and a human can
+//! still read it in a transcript. Serialization failures degrade to the usual
+//! `[error]` line rather than panicking.
+
+use serde::Serialize;
+
+/// Renders `value` as pretty-printed JSON, or an `[error]` line if it cannot
+/// be serialized.
+pub fn json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string_pretty(value)
+        .unwrap_or_else(|e| format!("[error] serializing report: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_pretty_json() {
+        #[derive(Serialize)]
+        struct Row {
+            unit: &'static str,
+            failed: bool,
+        }
+        let s = json(&Row { unit: "sshd.service", failed: true });
+        assert!(s.contains("\"unit\": \"sshd.service\""));
+        assert!(s.contains('\n'), "expected pretty-printed output");
+    }
+}
@@ -2,8 +2,9 @@
 //!
 //! Every `run` call is checked against [`crate::allowlist::is_allowed`] — a
 //! program not in the aspect's allowlist returns
-//! [`Error::Unavailable`] without spawning anything. This is the runtime
-//! backstop that complements the compile-time source-scan tests.
+//! [`Error::Unavailable`] without spawning anything, as does an argument list
+//! [`crate::allowlist::args_allowed`] refuses. This is the runtime backstop
+//! that complements the compile-time source-scan tests.
 
 use std::process::Command;
 use std::time::Duration;
@@ -22,22 +23,8 @@ pub const HEAVY_TIMEOUT: Duration = Duration::from_secs(900);
 /// Returns combined stdout+stderr as a string. Programs not on PATH return
 /// `Error::Unavailable`; timeouts return `Error::Timeout`.
 pub fn run(aspect: &str, prog: &str, args: &[&str], timeout: Duration) -> Result<String> {
-    if !allowlist::is_allowed(aspect, prog) {
-        // Log the rejection so capability drift is visible in the audit trail.
-        audit::log(aspect, "REJECTED", &format!("disallowed CLI: {prog}"), prog);
-        return Err(Error::Unavailable(format!(
-            "{prog} is not on the {aspect} allowlist"
-        )));
-    }
-    let cmd_path = match which(prog) {
-        Some(p) => p,
-        None => return Err(Error::Unavailable(prog.to_string())),
-    };
-    audit::log(aspect, "exec", &args.join(" "), prog);
-
-    let mut cmd = Command::new(&cmd_path);
-    cmd.args(args);
-    let output = wait_with_timeout(cmd, prog, args, timeout)?;
+    let cmd = prepare(aspect, prog, args, &args.join(" "))?;
+    let output = wait_with_timeout(cmd, prog, args, None, timeout)?;
     let mut combined = String::new();
     if !output.stdout.is_empty() {
         combined.push_str(&String::from_utf8_lossy(&output.stdout));
@@ -56,6 +43,91 @@ pub fn run(aspect: &str, prog: &str, args: &[&str], timeout: Duration) -> Result
     }
 }
 
+/// Like [`run`], but a command that exits having printed nothing yields an
+/// empty string instead of [`Error::Exit`]. For queries where "no rows" is a
+/// legitimate answer — `journalctl -o json` over a quiet window prints nothing.
+pub fn run_allow_empty(aspect: &str, prog: &str, args: &[&str], timeout: Duration) -> Result<String> {
+    match run(aspect, prog, args, timeout) {
+        Err(Error::Exit { .. }) => Ok(String::new()),
+        other => other,
+    }
+}
+
+/// Like [`run`], but a non-zero exit is an [`Error::Exit`] carrying stderr
+/// even when the command printed something. For CLIs such as `docker` that
+/// report "not found" as text on a failing exit, where [`run`] would hand
+/// that text back as if it were the answer. A successful exit with no output
+/// yields an empty string.
+pub fn run_checked(aspect: &str, prog: &str, args: &[&str], timeout: Duration) -> Result<String> {
+    let (mut combined, stderr) = run_streams(aspect, prog, args, timeout)?;
+    combined.push_str(&stderr);
+    Ok(combined.trim().to_string())
+}
+
+/// Like [`run_checked`], but stdout and stderr come back separately and
+/// untrimmed, for callers that must interleave or tell them apart.
+pub fn run_streams(aspect: &str, prog: &str, args: &[&str], timeout: Duration) -> Result<(String, String)> {
+    let cmd = prepare(aspect, prog, args, &args.join(" "))?;
+    let output = wait_with_timeout(cmd, prog, args, None, timeout)?;
+    if !output.status.success() {
+        return Err(Error::Exit {
+            code: output.status.code().unwrap_or(-1),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok((
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    ))
+}
+
+/// Runs `prog` as a filter: `input` is written to its stdin and its stdout is
+/// returned untrimmed, so formatters can be used without touching files.
+/// Unlike [`run`], a non-zero exit is an [`Error::Exit`] carrying stderr.
+pub fn run_with_stdin(
+    aspect: &str,
+    prog: &str,
+    args: &[&str],
+    input: &str,
+    timeout: Duration,
+) -> Result<String> {
+    let logged = format!("{} <stdin {} bytes>", args.join(" "), input.len());
+    let cmd = prepare(aspect, prog, args, &logged)?;
+    let output = wait_with_timeout(cmd, prog, args, Some(input.as_bytes().to_vec()), timeout)?;
+    if !output.status.success() {
+        return Err(Error::Exit {
+            code: output.status.code().unwrap_or(-1),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The allowlist check, PATH lookup and `exec` audit line every entry point
+/// goes through; `logged` is what the audit trail records for the arguments.
+fn prepare(aspect: &str, prog: &str, args: &[&str], logged: &str) -> Result<Command> {
+    if !allowlist::is_allowed(aspect, prog) {
+        // Log the rejection so capability drift is visible in the audit trail.
+        audit::log(aspect, "REJECTED", &format!("disallowed CLI: {prog}"), prog);
+        return Err(Error::Unavailable(format!(
+            "{prog} is not on the {aspect} allowlist"
+        )));
+    }
+    if !allowlist::args_allowed(prog, args) {
+        audit::log(aspect, "REJECTED", &format!("disallowed arguments: {prog} {}", args.join(" ")), prog);
+        return Err(Error::Unavailable(format!(
+            "{prog} {} is not a permitted invocation",
+            args.join(" ")
+        )));
+    }
+    let cmd_path = which(prog).ok_or_else(|| Error::Unavailable(prog.to_string()))?;
+    audit::log(aspect, "exec", logged, prog);
+
+    let mut cmd = Command::new(&cmd_path);
+    cmd.args(args);
+    Ok(cmd)
+}
+
 /// Tiny `which` — uses `PATH` lookups via `std::env::split_paths`.
 fn which(prog: &str) -> Option<std::path::PathBuf> {
     if prog.contains('/') {
@@ -78,28 +150,58 @@ pub fn which_detected(prog: &str) -> bool {
     which(prog).is_some()
 }
 
-/// Spawns `cmd`, polls with a deadline, and returns the Output. Kills the
-/// child on timeout.
+/// Spawns `cmd`, feeds it `stdin` if given, polls with a deadline, and
+/// returns the Output. Kills the child on timeout.
 fn wait_with_timeout(
     mut cmd: Command,
     prog: &str,
     args: &[&str],
+    stdin: Option<Vec<u8>>,
     timeout: Duration,
 ) -> Result<std::process::Output> {
     cmd.stdout(std::process::Stdio::piped());
     cmd.stderr(std::process::Stdio::piped());
+    cmd.stdin(if stdin.is_some() {
+        std::process::Stdio::piped()
+    } else {
+        std::process::Stdio::null()
+    });
     let mut child = cmd.spawn().map_err(|e| {
         Error::Io(std::io::Error::new(
             e.kind(),
             format!("spawning {prog}: {e}"),
         ))
     })?;
+    // Written from a thread so a child that starts printing before it has
+    // read all of its input cannot deadlock against us.
+    if let (Some(data), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        std::thread::spawn(move || {
+            use std::io::Write;
+            let _ = pipe.write_all(&data);
+        });
+    }
+    // Pipes are drained while we poll; otherwise a child with more output
+    // than the pipe buffer blocks on write and only the timeout ends it.
+    let drain = |pipe: Option<Box<dyn std::io::Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(mut p) = pipe {
+                let _ = p.read_to_end(&mut buf);
+            }
+            buf
+        })
+    };
+    let stdout = drain(child.stdout.take().map(|p| Box::new(p) as _));
+    let stderr = drain(child.stderr.take().map(|p| Box::new(p) as _));
     let deadline = std::time::Instant::now() + timeout;
     loop {
         match child.try_wait()? {
-            Some(_) => {
-                let out = child.wait_with_output()?;
-                return Ok(out);
+            Some(status) => {
+                return Ok(std::process::Output {
+                    status,
+                    stdout: stdout.join().unwrap_or_default(),
+                    stderr: stderr.join().unwrap_or_default(),
+                });
             }
             None => {
                 if std::time::Instant::now() >= deadline {
@@ -122,5 +224,17 @@ mod tests {
         // `ls` is not on any allowlist.
         let res = run("system", "ls", &[], QUICK_TIMEOUT);
         assert!(matches!(res, Err(Error::Unavailable(_))));
+        let res = run_with_stdin("system", "ls", &[], "", QUICK_TIMEOUT);
+        assert!(matches!(res, Err(Error::Unavailable(_))));
+        let res = run_checked("system", "ls", &[], QUICK_TIMEOUT);
+        assert!(matches!(res, Err(Error::Unavailable(_))));
+        let res = run_streams("system", "ls", &[], QUICK_TIMEOUT);
+        assert!(matches!(res, Err(Error::Unavailable(_))));
+    }
+
+    #[test]
+    fn rejects_disallowed_arguments() {
+        let res = run("secrets", "age-keygen", &["-o", "/tmp/key.txt"], QUICK_TIMEOUT);
+        assert!(matches!(res, Err(Error::Unavailable(m)) if m.contains("not a permitted invocation")));
     }
 }
\ No newline at end of file
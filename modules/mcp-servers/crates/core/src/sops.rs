@@ -0,0 +1,333 @@
+//! The `sops` metadata block of an encrypted file, read without decrypting.
+//!
+//! YAML and JSON files carry a top-level `sops:` mapping; dotenv files
+//! flatten the same tree into `sops_<path>=<value>` lines, with
+//! `__list_N__map_` standing for list items. Only the metadata and the shape
+//! of the document are read: for every other key this module records the
+//! key path and whether its value is an `ENC[…]` envelope, never the value.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+use serde_yaml::Value;
+
+/// The prefix of every sops-encrypted value.
+pub const ENC_PREFIX: &str = "ENC[";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Yaml,
+    Json,
+    Env,
+}
+
+/// A public key a file is encrypted to. `id` is the age recipient, PGP
+/// fingerprint, KMS ARN or equivalent — never private material.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Recipient {
+    pub kind: String,
+    pub id: String,
+}
+
+impl Recipient {
+    pub fn new(kind: &str, id: &str) -> Self {
+        let id = id.trim();
+        // PGP fingerprints are written both spaced and upper/lower case.
+        let id = if kind == "pgp" { id.replace(' ', "").to_ascii_uppercase() } else { id.to_string() };
+        Recipient { kind: kind.to_string(), id }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Metadata {
+    pub format: Format,
+    pub version: Option<String>,
+    /// RFC 3339, as sops wrote it.
+    pub lastmodified: Option<String>,
+    pub mac_present: bool,
+    /// 0 when the file uses the flat (single-group) layout.
+    pub key_groups: usize,
+    pub shamir_threshold: Option<u64>,
+    pub recipients: BTreeSet<Recipient>,
+    /// `recipients` split by key group; the flat layout is one group.
+    pub groups: Vec<BTreeSet<Recipient>>,
+    pub encrypted_regex: Option<String>,
+    pub unencrypted_suffix: Option<String>,
+    /// Key paths whose value is an `ENC[…]` envelope.
+    pub encrypted_keys: Vec<String>,
+    /// Key paths left in the clear (by `unencrypted_suffix`/`encrypted_regex`).
+    pub unencrypted_keys: Vec<String>,
+}
+
+/// Which recipient field names map to which key kind, per sops' metadata
+/// schema (`age[].recipient`, `pgp[].fp`, `kms[].arn`, …).
+const RECIPIENT_FIELDS: &[(&str, &str)] = &[
+    ("recipient", "age"),
+    ("fp", "pgp"),
+    ("arn", "kms"),
+    ("resource_id", "gcp_kms"),
+    ("vault_url", "azure_kv"),
+    ("vault_address", "hc_vault"),
+];
+
+/// Parses `text` as `format` and returns its sops metadata, or None when the
+/// file is not sops-encrypted (or not parseable as that format).
+pub fn inspect(format: Format, text: &str) -> Option<Metadata> {
+    match format {
+        Format::Yaml => from_tree(format, serde_yaml::from_str(text).ok()?),
+        Format::Json => from_tree(format, serde_json::from_str(text).ok()?),
+        Format::Env => from_env(text),
+    }
+}
+
+/// Guesses the format from the file name: `.yaml`/`.yml`, `.json`, `.env`
+/// (also as an inner extension, as in `dcf-id.enc.env`).
+pub fn format_for(name: &str) -> Option<Format> {
+    let ext = name.rsplit('.').next()?;
+    match ext {
+        "yaml" | "yml" => Some(Format::Yaml),
+        "json" => Some(Format::Json),
+        "env" => Some(Format::Env),
+        _ => None,
+    }
+}
+
+fn from_tree(format: Format, doc: Value) -> Option<Metadata> {
+    let Value::Mapping(map) = doc else {
+        return None;
+    };
+    let sops = map.get("sops")?;
+    let field = |k: &str| sops.get(k).and_then(scalar);
+    let mut recipients = BTreeSet::new();
+    collect_recipients(sops, &mut recipients);
+    let key_groups = sops.get("key_groups").and_then(Value::as_sequence);
+    let groups = match key_groups {
+        Some(list) => list
+            .iter()
+            .map(|g| {
+                let mut group = BTreeSet::new();
+                collect_recipients(g, &mut group);
+                group
+            })
+            .collect(),
+        None => vec![recipients.clone()],
+    };
+    let mut encrypted_keys = Vec::new();
+    let mut unencrypted_keys = Vec::new();
+    for (k, v) in &map {
+        let key = scalar(k).unwrap_or_default();
+        if key != "sops" {
+            walk(&key, v, &mut encrypted_keys, &mut unencrypted_keys);
+        }
+    }
+    Some(Metadata {
+        format,
+        version: field("version"),
+        lastmodified: field("lastmodified"),
+        mac_present: field("mac").is_some_and(|m| m.starts_with(ENC_PREFIX)),
+        key_groups: key_groups.map_or(0, Vec::len),
+        shamir_threshold: sops.get("shamir_threshold").and_then(Value::as_u64),
+        recipients,
+        groups,
+        encrypted_regex: field("encrypted_regex"),
+        unencrypted_suffix: field("unencrypted_suffix"),
+        encrypted_keys,
+        unencrypted_keys,
+    })
+}
+
+fn scalar(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn collect_recipients(v: &Value, out: &mut BTreeSet<Recipient>) {
+    match v {
+        Value::Mapping(m) => {
+            for (k, v) in m {
+                let kind = k.as_str().and_then(|k| RECIPIENT_FIELDS.iter().find(|(f, _)| *f == k));
+                match (kind, v.as_str()) {
+                    (Some((_, kind)), Some(id)) => {
+                        out.insert(Recipient::new(kind, id));
+                    }
+                    _ => collect_recipients(v, out),
+                }
+            }
+        }
+        Value::Sequence(items) => items.iter().for_each(|i| collect_recipients(i, out)),
+        _ => {}
+    }
+}
+
+/// Records the key path of every leaf, split by whether it is encrypted.
+fn walk(path: &str, v: &Value, enc: &mut Vec<String>, plain: &mut Vec<String>) {
+    match v {
+        Value::Mapping(m) => {
+            for (k, v) in m {
+                walk(&format!("{path}.{}", scalar(k).unwrap_or_default()), v, enc, plain);
+            }
+        }
+        Value::Sequence(items) => {
+            for (i, v) in items.iter().enumerate() {
+                walk(&format!("{path}[{i}]"), v, enc, plain);
+            }
+        }
+        Value::String(s) if s.starts_with(ENC_PREFIX) => enc.push(path.to_string()),
+        Value::Tagged(t) => walk(path, &t.value, enc, plain),
+        _ => plain.push(path.to_string()),
+    }
+}
+
+fn from_env(text: &str) -> Option<Metadata> {
+    let mut meta = Metadata {
+        format: Format::Env,
+        version: None,
+        lastmodified: None,
+        mac_present: false,
+        key_groups: 0,
+        shamir_threshold: None,
+        recipients: BTreeSet::new(),
+        groups: Vec::new(),
+        encrypted_regex: None,
+        unencrypted_suffix: None,
+        encrypted_keys: Vec::new(),
+        unencrypted_keys: Vec::new(),
+    };
+    // Recipients by key group index; None is the flat layout.
+    let mut groups: BTreeMap<Option<usize>, BTreeSet<Recipient>> = BTreeMap::new();
+    let mut is_sops = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let Some(field) = key.strip_prefix("sops_") else {
+            if value.starts_with(ENC_PREFIX) {
+                meta.encrypted_keys.push(key.to_string());
+            } else {
+                meta.unencrypted_keys.push(key.to_string());
+            }
+            continue;
+        };
+        is_sops = true;
+        match field {
+            "version" => meta.version = Some(value.to_string()),
+            "lastmodified" => meta.lastmodified = Some(value.trim_matches('"').to_string()),
+            "mac" => meta.mac_present = value.starts_with(ENC_PREFIX),
+            "shamir_threshold" => meta.shamir_threshold = value.parse().ok(),
+            "encrypted_regex" => meta.encrypted_regex = Some(value.to_string()),
+            "unencrypted_suffix" => meta.unencrypted_suffix = Some(value.to_string()),
+            _ => {}
+        }
+        // key_groups__list_0__map_age__list_1__map_recipient
+        let group = field
+            .strip_prefix("key_groups__list_")
+            .and_then(|rest| rest.split_once("__"))
+            .and_then(|(n, _)| n.parse().ok());
+        let entry = groups.entry(group).or_default();
+        if let Some(last) = field.rsplit("__map_").next().filter(|_| field.contains("__map_")) {
+            if let Some((_, kind)) = RECIPIENT_FIELDS.iter().find(|(f, _)| *f == last) {
+                entry.insert(Recipient::new(kind, value));
+                meta.recipients.insert(Recipient::new(kind, value));
+            }
+        }
+    }
+    let flat = groups.remove(&None).unwrap_or_default();
+    meta.key_groups = groups.len();
+    meta.groups = if groups.is_empty() { vec![flat] } else { groups.into_values().collect() };
+    is_sops.then_some(meta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YAML: &str = "\
+db:
+    user: ENC[AES256_GCM,data:Zm9v,iv:aXY=,tag:dGFn,type:str]
+    port_unencrypted: 5432
+tokens:
+    - ENC[AES256_GCM,data:YmFy,iv:aXY=,tag:dGFn,type:str]
+sops:
+    kms: []
+    age:
+        - recipient: age1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqq
+          enc: |
+            -----BEGIN AGE ENCRYPTED FILE-----
+            YWdlLWVuY3J5cHRpb24ub3JnL3YxCg==
+            -----END AGE ENCRYPTED FILE-----
+    pgp:
+        - created_at: \"2025-01-01T00:00:00Z\"
+          enc: '-----BEGIN PGP MESSAGE-----'
+          fp: 85d7 7543 b3d6 24b6 3cea 9e6d bc17 301b 491b 3f21
+    lastmodified: \"2025-10-01T12:00:00Z\"
+    mac: ENC[AES256_GCM,data:bWFj,iv:aXY=,tag:dGFn,type:str]
+    unencrypted_suffix: _unencrypted
+    version: 3.9.0
+";
+
+    #[test]
+    fn reads_yaml_metadata_and_key_shape() {
+        let m = inspect(Format::Yaml, YAML).unwrap();
+        assert_eq!(m.version.as_deref(), Some("3.9.0"));
+        assert_eq!(m.lastmodified.as_deref(), Some("2025-10-01T12:00:00Z"));
+        assert!(m.mac_present);
+        assert_eq!(m.key_groups, 0);
+        let kinds: Vec<&str> = m.recipients.iter().map(|r| r.kind.as_str()).collect();
+        assert_eq!(kinds, ["age", "pgp"]);
+        assert!(m.recipients.contains(&Recipient::new("pgp", "85D77543B3D624B63CEA9E6DBC17301B491B3F21")));
+        assert_eq!(m.encrypted_keys, ["db.user", "tokens[0]"]);
+        assert_eq!(m.unencrypted_keys, ["db.port_unencrypted"]);
+    }
+
+    #[test]
+    fn reads_json_with_key_groups() {
+        let json = r#"{"token": "ENC[AES256_GCM,data:eA==,type:str]",
+          "sops": {"key_groups": [{"age": [{"recipient": "age1a", "enc": "x"}]}, {"age": [{"recipient": "age1b", "enc": "y"}]}],
+                   "shamir_threshold": 2, "lastmodified": "2025-01-01T00:00:00Z", "mac": "", "version": "3.8.1"}}"#;
+        let m = inspect(Format::Json, json).unwrap();
+        assert_eq!((m.key_groups, m.shamir_threshold), (2, Some(2)));
+        assert_eq!(m.recipients.len(), 2);
+        assert_eq!(m.groups, [BTreeSet::from([Recipient::new("age", "age1a")]), BTreeSet::from([Recipient::new("age", "age1b")])]);
+        assert!(!m.mac_present);
+        assert_eq!(m.encrypted_keys, ["token"]);
+    }
+
+    #[test]
+    fn reads_dotenv_metadata() {
+        let env = "\
+DCF_ID=ENC[AES256_GCM,data:aWQ=,iv:aXY=,tag:dGFn,type:str]
+REGION_unencrypted=eu
+sops_age__list_0__map_enc=-----BEGIN AGE ENCRYPTED FILE-----\\nYWdl\\n-----END AGE ENCRYPTED FILE-----\\n
+sops_age__list_0__map_recipient=age1zzzz
+sops_lastmodified=2025-10-02T08:00:00Z
+sops_mac=ENC[AES256_GCM,data:bWFj,iv:aXY=,tag:dGFn,type:str]
+sops_unencrypted_suffix=_unencrypted
+sops_version=3.9.0
+";
+        let m = inspect(Format::Env, env).unwrap();
+        assert_eq!(m.recipients.iter().next(), Some(&Recipient::new("age", "age1zzzz")));
+        assert_eq!((m.key_groups, m.groups.len()), (0, 1));
+        assert!(m.mac_present);
+        assert_eq!(m.encrypted_keys, ["DCF_ID"]);
+        assert_eq!(m.unencrypted_keys, ["REGION_unencrypted"]);
+        assert_eq!(m.lastmodified.as_deref(), Some("2025-10-02T08:00:00Z"));
+    }
+
+    #[test]
+    fn plain_files_are_not_sops() {
+        assert_eq!(inspect(Format::Yaml, "a: 1\n"), None);
+        assert_eq!(inspect(Format::Json, "[1, 2]"), None);
+        assert_eq!(inspect(Format::Env, "A=1\n"), None);
+        assert_eq!(format_for("dcf-id.enc.env"), Some(Format::Env));
+        assert_eq!(format_for("flake.nix"), None);
+    }
+}
@@ -0,0 +1,38 @@
+//! Local accounts from `/etc/passwd`: uid and name, nothing else.
+//!
+//! `nft -j` prints `meta skuid` as a user name when the uid has one, and the
+//! kernel log's `UID=` is numeric; both sides are mapped through here.
+
+use std::collections::HashMap;
+
+pub const PASSWD: &str = "/etc/passwd";
+
+/// uid → user name. An unreadable file is an empty map, so names are simply
+/// not resolved.
+pub fn names() -> HashMap<u32, String> {
+    std::fs::read_to_string(PASSWD).map(|t| parse(&t)).unwrap_or_default()
+}
+
+/// Parses `passwd(5)` lines; malformed lines are skipped.
+pub fn parse(text: &str) -> HashMap<u32, String> {
+    text.lines()
+        .filter_map(|l| {
+            let mut cols = l.split(':');
+            let name = cols.next().filter(|n| !n.is_empty())?;
+            let uid = cols.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names_by_uid() {
+        let u = parse("root:x:0:0::/root:/bin/sh\n# comment\nsystemd-resolve:x:153:153::/:/sbin/nologin\n:x:9:9\n");
+        assert_eq!(u.len(), 2);
+        assert_eq!(u[&153], "systemd-resolve");
+    }
+}
@@ -0,0 +1,349 @@
+//! Typed tool parameters for values that end up in a CLI's argv.
+//!
+//! A bare `String` parameter lets `--help` or `-t TXT` through as a flag of
+//! the wrapped program. The newtypes here deserialize from any string — so a
+//! bad value still reaches the tool and can be denied with an audit line
+//! instead of failing as an opaque MCP parameter error — and publish a JSON
+//! Schema (pattern, length, description) so clients see the constraint in
+//! the tool's `inputSchema`. Tools pass them through [`admit`] before use,
+//! and put what it returns — the [`Validate::canonical`] form — on the
+//! command line.
+
+use std::borrow::Cow;
+use std::net::IpAddr;
+
+use rmcp::schemars;
+use rmcp::schemars::gen::SchemaGenerator;
+use rmcp::schemars::schema::{InstanceType, Metadata, Schema, SchemaObject, StringValidation};
+use serde::{Deserialize, Serialize};
+
+use crate::audit;
+
+/// A parameter with a syntax check.
+pub trait Validate {
+    /// The raw value, as given.
+    fn as_str(&self) -> &str;
+    /// Checks the value; the error says what is wrong with it.
+    fn validate(&self) -> Result<(), String>;
+    /// The form to pass on, once valid: the raw value unless the type has a
+    /// canonical spelling (a [`Host`]'s A-label form).
+    fn canonical(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self.as_str())
+    }
+}
+
+/// Validates `value` for `tool` and returns its canonical form. A rejected
+/// value is audit-logged as `DENIED` and comes back as the tool's
+/// `[denied] …` reply.
+pub fn admit<'a, T: Validate>(aspect: &str, tool: &str, value: &'a T) -> Result<Cow<'a, str>, String> {
+    match value.validate() {
+        Ok(()) => Ok(value.canonical()),
+        Err(e) => {
+            audit::log(aspect, "DENIED", &format!("{tool}: {e}"), "");
+            Err(format!("[denied] {e}"))
+        }
+    }
+}
+
+macro_rules! param {
+    ($(#[$doc:meta])* $name:ident, $desc:literal, $pattern:literal, $max:literal, $check:path) => {
+        param!($(#[$doc])* $name, $desc, $pattern, $max, $check, Cow::Borrowed);
+    };
+    ($(#[$doc:meta])* $name:ident, $desc:literal, $pattern:literal, $max:literal, $check:path, $canon:path) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+        #[serde(transparent)]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn new(value: impl Into<String>) -> Self {
+                $name(value.into())
+            }
+        }
+
+        impl Validate for $name {
+            fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            fn validate(&self) -> Result<(), String> {
+                $check(&self.0)
+            }
+
+            fn canonical(&self) -> Cow<'_, str> {
+                $canon(&self.0)
+            }
+        }
+
+        impl schemars::JsonSchema for $name {
+            fn schema_name() -> String {
+                stringify!($name).into()
+            }
+
+            fn json_schema(_: &mut SchemaGenerator) -> Schema {
+                string_schema($desc, $pattern, $max)
+            }
+        }
+    };
+}
+
+param!(
+    /// A DNS name or an IP address literal. Internationalised names are
+    /// passed on in A-label (`xn--…`) form.
+    Host,
+    "DNS hostname or IP address. Internationalised names (bücher.example) are converted to xn-- form.",
+    "^[^\\s-][^\\s]*$",
+    253,
+    check_host,
+    canonical_host
+);
+
+param!(
+    /// A single IPv4 or IPv6 address.
+    Ip,
+    "IPv4 or IPv6 address.",
+    "^[0-9A-Fa-f:.]+$",
+    45,
+    check_ip
+);
+
+param!(
+    /// An address or an `addr/len` network.
+    IpOrCidr,
+    "IPv4/IPv6 address or CIDR network (addr/len).",
+    "^[0-9A-Fa-f:.]+(/[0-9]{1,3})?$",
+    49,
+    check_ip_or_cidr
+);
+
+param!(
+    /// A systemd unit name, optionally without its suffix (`sshd`).
+    UnitName,
+    "systemd unit name, e.g. sshd.service or getty@tty1.service.",
+    "^[A-Za-z0-9:_.\\\\@][A-Za-z0-9:_.\\\\@-]*$",
+    255,
+    check_unit
+);
+
+param!(
+    /// A VM name as vm-manager and libvirt know it.
+    VmName,
+    "VM name: letters, digits, '.', '_' and '-'.",
+    "^[A-Za-z0-9_][A-Za-z0-9._-]*$",
+    64,
+    check_vm
+);
+
+fn string_schema(description: &str, pattern: &str, max_length: u32) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        metadata: Some(Box::new(Metadata { description: Some(description.into()), ..Default::default() })),
+        string: Some(Box::new(StringValidation {
+            max_length: Some(max_length),
+            min_length: Some(1),
+            pattern: Some(pattern.into()),
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+/// Option injection is the point of all of these, so it gets its own message.
+fn not_a_flag(s: &str, what: &str) -> Result<(), String> {
+    if s.starts_with('-') {
+        Err(format!("{what} may not start with '-': {s:?}"))
+    } else if s.is_empty() {
+        Err(format!("{what} is empty"))
+    } else {
+        Ok(())
+    }
+}
+
+/// RFC 1123 names: up to 253 bytes, labels of 1–63 letters, digits and `-`
+/// not at either end. `_` is allowed for service labels (`_dmarc`).
+/// Internationalised names are checked in their A-label form, see
+/// [`host_to_ascii`].
+pub fn check_host(s: &str) -> Result<(), String> {
+    not_a_flag(s, "host")?;
+    if s.parse::<IpAddr>().is_ok() {
+        return Ok(());
+    }
+    let ascii = host_to_ascii(s)?;
+    let name = ascii.strip_suffix('.').unwrap_or(&ascii);
+    if name.len() > 253 {
+        return Err(format!("host is longer than 253 characters: {s:?}"));
+    }
+    for label in name.split('.') {
+        let ok = (1..=63).contains(&label.len())
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !ok {
+            return Err(format!("invalid label {label:?} in host {s:?}"));
+        }
+        if label.len() > 4 && label[..4].eq_ignore_ascii_case("xn--") && label[4..].starts_with('-') {
+            return Err(format!("invalid A-label {label:?} in host {s:?}"));
+        }
+    }
+    Ok(())
+}
+
+/// The IDNA (UTS #46) ToASCII form of a non-ASCII name — `bücher.example`
+/// → `xn--bcher-kva.example`. ASCII input is returned unchanged, so case and
+/// `_` labels survive as given.
+pub fn host_to_ascii(s: &str) -> Result<String, String> {
+    if s.is_ascii() {
+        return Ok(s.to_string());
+    }
+    idna::domain_to_ascii(s).map_err(|_| format!("host {s:?} is not a valid internationalised domain name"))
+}
+
+fn canonical_host(s: &str) -> Cow<'_, str> {
+    host_to_ascii(s).map_or(Cow::Borrowed(s), Cow::Owned)
+}
+
+pub fn check_ip(s: &str) -> Result<(), String> {
+    not_a_flag(s, "IP address")?;
+    s.parse::<IpAddr>().map(|_| ()).map_err(|_| format!("not an IP address: {s:?}"))
+}
+
+pub fn check_ip_or_cidr(s: &str) -> Result<(), String> {
+    let Some((addr, len)) = s.split_once('/') else {
+        return check_ip(s);
+    };
+    let ip: IpAddr = addr.parse().map_err(|_| format!("not an IP network: {s:?}"))?;
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    match len.parse::<u8>() {
+        Ok(n) if n <= max => Ok(()),
+        _ => Err(format!("prefix length must be 0-{max}: {s:?}")),
+    }
+}
+
+/// A journalctl/systemd time specification (`-24h`, `yesterday`,
+/// `2025-10-01 12:00:00`, `@1760000000`): letters, digits and ` :-+._@`, at
+/// most 64 bytes. Passed as `--since=…`, so a leading `-` is fine here.
+pub fn check_time(s: &str) -> Result<(), String> {
+    let ok = !s.is_empty()
+        && s.len() <= 64
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || " :-+._@".contains(c));
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("invalid time specification: {s:?}"))
+    }
+}
+
+/// A unit name or glob as `journalctl --unit` takes it: the unit-name
+/// alphabet plus `*?[]`, and never a leading `-`.
+pub fn check_unit_pattern(s: &str) -> Result<(), String> {
+    let ok = !s.is_empty()
+        && s.len() <= 256
+        && !s.starts_with('-')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || ":-_.@\\*?[]".contains(c));
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("invalid unit name or pattern: {s:?}"))
+    }
+}
+
+/// systemd's unit-name alphabet (`unit_name_is_valid`): ASCII letters,
+/// digits and `:-_.\`, plus one `@` for template instances.
+pub fn check_unit(s: &str) -> Result<(), String> {
+    not_a_flag(s, "unit name")?;
+    let ok = s.len() <= 255
+        && s.matches('@').count() <= 1
+        && !s.starts_with('@')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || ":-_.\\@".contains(c));
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("invalid systemd unit name: {s:?}"))
+    }
+}
+
+pub fn check_vm(s: &str) -> Result<(), String> {
+    not_a_flag(s, "VM name")?;
+    let ok = s.len() <= 64
+        && !s.starts_with('.')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || "._-".contains(c));
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("invalid VM name: {s:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hosts() {
+        for ok in ["github.com", "cache.nixos.org.", "xn--bcher-kva.example", "_dmarc.example.org", "1.1.1.1", "2606:4700::1111"] {
+            assert_eq!(check_host(ok), Ok(()), "{ok}");
+        }
+        for bad in ["-t", "--help", "", "a..b", "bad-.example", "a b", "xn---x.example", "a;b", "bü cher.example"] {
+            assert!(check_host(bad).is_err(), "{bad}");
+        }
+        assert_eq!(check_host("bücher.example"), Ok(()));
+        assert_eq!(host_to_ascii("Bücher.example").as_deref(), Ok("xn--bcher-kva.example"));
+        assert_eq!(host_to_ascii("münchen.de.").as_deref(), Ok("xn--mnchen-3ya.de."));
+        assert_eq!(host_to_ascii("_dmarc.Example.org").as_deref(), Ok("_dmarc.Example.org"));
+        assert!(check_host(&format!("{}.com", "a".repeat(64))).is_err());
+    }
+
+    #[test]
+    fn ips_and_networks() {
+        assert!(check_ip("192.0.2.1").is_ok());
+        assert!(check_ip("-1").is_err());
+        assert!(check_ip("192.0.2.0/24").is_err());
+        assert!(check_ip_or_cidr("192.0.2.0/24").is_ok());
+        assert!(check_ip_or_cidr("2001:db8::/129").is_err());
+        assert!(check_ip_or_cidr("example.org/8").is_err());
+    }
+
+    #[test]
+    fn units_and_vms() {
+        for ok in ["sshd", "sshd.service", "getty@tty1.service", "dev-disk-by\\x2duuid.device"] {
+            assert!(check_unit(ok).is_ok(), "{ok}");
+        }
+        for bad in ["--all", "a b", "x@y@z.service", "@.service", "*.service"] {
+            assert!(check_unit(bad).is_err(), "{bad}");
+        }
+        assert!(check_unit_pattern("pipewire*.service").is_ok());
+        assert!(check_unit_pattern("--flush").is_err());
+        assert!(check_unit_pattern("a;b").is_err());
+        assert!(check_vm("win11-dsp").is_ok());
+        assert!(check_vm("-x").is_err());
+        assert!(check_vm("../etc").is_err());
+    }
+
+    #[test]
+    fn times() {
+        for ok in ["-24h", "yesterday", "2025-10-01 12:00:00", "@1760000000", "-1d+2h"] {
+            assert!(check_time(ok).is_ok(), "{ok}");
+        }
+        for bad in ["", "today\n--vacuum-size=1", "now;reboot", &"1".repeat(65)] {
+            assert!(check_time(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn deserializes_anything_and_denies_on_admit() {
+        let host: Host = serde_json::from_str("\"--help\"").unwrap();
+        let denied = admit("test-validate", "t", &host).unwrap_err();
+        assert!(denied.starts_with("[denied] host may not start with '-'"));
+        assert_eq!(admit("test-validate", "t", &Host::new("github.com")).as_deref(), Ok("github.com"));
+        assert_eq!(admit("test-validate", "t", &Host::new("bücher.example")).as_deref(), Ok("xn--bcher-kva.example"));
+    }
+
+    #[test]
+    fn schema_carries_pattern() {
+        let schema = serde_json::to_value(schemars::schema_for!(UnitName)).unwrap();
+        assert_eq!(schema["type"], "string");
+        assert_eq!(schema["maxLength"], 255);
+        assert!(schema["pattern"].as_str().unwrap().starts_with('^'));
+        assert!(schema["description"].as_str().unwrap().contains("systemd"));
+    }
+}
@@ -0,0 +1,411 @@
+//! `node_container` / `node_logs`: the DCF community node's container, via
+//! read-only `docker` verbs.
+//!
+//! `docker` is on this aspect's allowlist as a whole program, so the verb is
+//! checked here: apart from `dcf_status`'s one-field state probe, this
+//! crate's docker calls go through [`docker`], which refuses anything
+//! outside [`READ_ONLY_VERBS`] before spawning. `docker inspect` output is
+//! deserialised into the fields below and nothing else — in particular
+//! `.Config.Env`, which carries the node's secrets, is never surfaced.
+
+use std::collections::BTreeMap;
+
+use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
+use oligarchy_mcp_core::{report, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::{ASPECT, NODE_CONTAINER};
+
+/// `docker` invocations this module may make, as argument prefixes.
+const READ_ONLY_VERBS: &[&[&str]] = &[&["inspect"], &["image", "inspect"], &["stats", "--no-stream"], &["logs"]];
+const DEFAULT_TAIL: u32 = 100;
+const MAX_TAIL: u32 = 1_000;
+/// Logs beyond this are cut from the front, keeping the newest lines.
+const MAX_LOG_BYTES: usize = 128 * 1024;
+/// Health-check output is per probe; keep it to a line's worth.
+const MAX_PROBE_OUTPUT: usize = 200;
+
+/// Runs a read-only docker verb. Errors come back as tool output: `[denied]`
+/// for a refused verb, `[absent]` when docker says the object does not exist,
+/// `[error]` otherwise. The exit status is checked, because docker prints its
+/// "No such object" text on a failing exit and [`runner::run`] would return
+/// that text as if it were the result.
+pub(crate) fn docker(args: &[&str]) -> Result<String, String> {
+    read_only(args)?;
+    outcome(runner::run_checked(ASPECT, "docker", args, QUICK_TIMEOUT))
+}
+
+fn read_only(args: &[&str]) -> Result<(), String> {
+    if !READ_ONLY_VERBS.iter().any(|verb| args.starts_with(verb)) {
+        return Err(format!("[denied] docker {} is not a read-only verb", args.first().unwrap_or(&"")));
+    }
+    Ok(())
+}
+
+fn outcome<T>(res: oligarchy_mcp_core::Result<T>) -> Result<T, String> {
+    match res {
+        Ok(out) => Ok(out),
+        Err(Error::Exit { stderr, .. }) if stderr.contains("No such") => Err(format!("[absent] {stderr}")),
+        Err(e) => Err(format!("[error] {e}")),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Inspect {
+    id: String,
+    created: String,
+    /// The image ID (`sha256:…`) the container was created from.
+    image: String,
+    restart_count: u64,
+    state: State,
+    config: Config,
+    host_config: HostConfig,
+    network_settings: NetworkSettings,
+    #[serde(default)]
+    mounts: Vec<Mount>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct State {
+    status: String,
+    started_at: String,
+    finished_at: String,
+    exit_code: i64,
+    #[serde(rename = "OOMKilled")]
+    oom_killed: bool,
+    health: Option<Health>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct Health {
+    status: String,
+    failing_streak: u64,
+    #[serde(default)]
+    log: Vec<Probe>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct Probe {
+    start: String,
+    end: String,
+    exit_code: i64,
+    output: String,
+}
+
+/// Only the image reference — `Env`, `Cmd` and the rest stay unread.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Config {
+    image: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HostConfig {
+    restart_policy: RestartPolicy,
+    #[serde(default)]
+    memory: u64,
+    #[serde(default)]
+    nano_cpus: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RestartPolicy {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct NetworkSettings {
+    /// `"7777/udp": [{"HostIp": "0.0.0.0", "HostPort": "7777"}]`, or `null`
+    /// for an exposed but unpublished port.
+    #[serde(default)]
+    ports: Option<BTreeMap<String, Option<Vec<Binding>>>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Binding {
+    host_ip: String,
+    host_port: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct Mount {
+    #[serde(rename(serialize = "type"))]
+    r#type: String,
+    #[serde(default, rename(serialize = "source"))]
+    source: String,
+    #[serde(rename(serialize = "destination"))]
+    destination: String,
+    #[serde(rename(deserialize = "RW", serialize = "rw"))]
+    rw: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Port {
+    container: String,
+    /// `host_ip:host_port` per binding; empty when exposed but not published.
+    published: Vec<String>,
+}
+
+/// One `docker stats --no-stream --format '{{json .}}'` row, plus the
+/// percentages as numbers.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Stats {
+    #[serde(rename = "CPUPerc")]
+    cpu: String,
+    #[serde(rename = "MemUsage")]
+    memory: String,
+    #[serde(rename = "MemPerc")]
+    memory_percent: String,
+    #[serde(rename = "NetIO")]
+    net_io: String,
+    #[serde(rename = "BlockIO")]
+    block_io: String,
+    #[serde(rename = "PIDs")]
+    pids: String,
+    #[serde(skip_deserializing)]
+    cpu_pct: Option<f64>,
+    #[serde(skip_deserializing)]
+    memory_pct: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    name: &'static str,
+    id: String,
+    created: String,
+    image: String,
+    image_id: String,
+    /// `RepoDigests` of the image — what to pin instead of `:latest`.
+    image_digests: Vec<String>,
+    restart_count: u64,
+    restart_policy: String,
+    memory_limit_bytes: Option<u64>,
+    cpu_limit: Option<f64>,
+    state: State,
+    ports: Vec<Port>,
+    mounts: Vec<Mount>,
+    stats: Option<Stats>,
+    notes: Vec<String>,
+}
+
+pub fn report() -> String {
+    let inspect = match docker(&["inspect", NODE_CONTAINER]).and_then(|j| parse_inspect(&j)) {
+        Ok(i) => i,
+        Err(e) => return e,
+    };
+    let mut notes = Vec::new();
+    let image_digests = docker(&["image", "inspect", "--format", "{{json .RepoDigests}}", &inspect.image])
+        .map_err(|e| notes.push(format!("image digests: {e}")))
+        .ok()
+        .and_then(|j| serde_json::from_str(&j).ok())
+        .unwrap_or_default();
+    let stats = if inspect.state.status == "running" {
+        docker(&["stats", "--no-stream", "--format", "{{json .}}", NODE_CONTAINER])
+            .map_err(|e| notes.push(format!("stats: {e}")))
+            .ok()
+            .and_then(|j| parse_stats(&j))
+    } else {
+        notes.push(format!("not running ({}); no resource usage", inspect.state.status));
+        None
+    };
+    report::json(&build(inspect, image_digests, stats, notes))
+}
+
+/// The one container `docker inspect` described; `[]` means it does not
+/// exist.
+fn parse_inspect(json: &str) -> Result<Inspect, String> {
+    let mut all: Vec<Inspect> = serde_json::from_str(json).map_err(|e| format!("[error] docker inspect: {e}"))?;
+    all.pop().ok_or_else(|| format!("[absent] container {NODE_CONTAINER} does not exist"))
+}
+
+fn parse_stats(json: &str) -> Option<Stats> {
+    let mut s: Stats = serde_json::from_str(json.lines().next()?).ok()?;
+    let pct = |v: &str| v.trim().trim_end_matches('%').parse().ok();
+    s.cpu_pct = pct(&s.cpu);
+    s.memory_pct = pct(&s.memory_percent);
+    Some(s)
+}
+
+fn build(i: Inspect, image_digests: Vec<String>, stats: Option<Stats>, mut notes: Vec<String>) -> Report {
+    let mut state = i.state;
+    if let Some(h) = &mut state.health {
+        for p in &mut h.log {
+            p.output = p.output.trim().chars().take(MAX_PROBE_OUTPUT).collect();
+        }
+        if h.failing_streak > 0 {
+            notes.push(format!("health check failing {} time(s) in a row", h.failing_streak));
+        }
+    }
+    if state.oom_killed {
+        notes.push("last exit was an OOM kill".into());
+    }
+    if i.config.image.ends_with(":latest") || !i.config.image.contains(':') {
+        notes.push(format!("{} is a floating tag; pin one of image_digests", i.config.image));
+    }
+    let ports = i
+        .network_settings
+        .ports
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(container, bindings)| Port {
+            container,
+            published: bindings.unwrap_or_default().into_iter().map(|b| format!("{}:{}", b.host_ip, b.host_port)).collect(),
+        })
+        .collect();
+    Report {
+        name: NODE_CONTAINER,
+        id: i.id.chars().take(12).collect(),
+        created: i.created,
+        image: i.config.image,
+        image_id: i.image,
+        image_digests,
+        restart_count: i.restart_count,
+        restart_policy: i.host_config.restart_policy.name,
+        memory_limit_bytes: (i.host_config.memory > 0).then_some(i.host_config.memory),
+        cpu_limit: (i.host_config.nano_cpus > 0).then(|| i.host_config.nano_cpus as f64 / 1e9),
+        state,
+        ports,
+        mounts: i.mounts,
+        stats,
+        notes,
+    }
+}
+
+/// `docker logs --tail N --timestamps`, N clamped to 1..=1000, cut to the
+/// newest [`MAX_LOG_BYTES`]. docker replays the container's stderr on its own
+/// stderr, so the two streams are merged back into time order first.
+pub fn logs(tail: Option<u32>) -> String {
+    let n = tail.unwrap_or(DEFAULT_TAIL).clamp(1, MAX_TAIL).to_string();
+    let args = ["logs", "--tail", &n, "--timestamps", NODE_CONTAINER];
+    let streams = read_only(&args).and_then(|()| outcome(runner::run_streams(ASPECT, "docker", &args, QUICK_TIMEOUT)));
+    match streams {
+        Ok((stdout, stderr)) => newest(merge_by_timestamp(&stdout, &stderr), MAX_LOG_BYTES),
+        Err(e) => e,
+    }
+}
+
+/// Interleaves two `--timestamps` streams, each already in order, by their
+/// leading timestamp. docker prints RFC 3339 UTC with fixed-width nanoseconds,
+/// so the timestamps order as strings. A line without one stays behind the
+/// line before it.
+fn merge_by_timestamp(a: &str, b: &str) -> String {
+    let keyed = |text: &str| -> Vec<(String, String)> {
+        let mut key = String::new();
+        text.lines()
+            .map(|line| {
+                if let Some(stamp) = line.split(' ').next().filter(|s| s.len() >= 20 && s.as_bytes()[4] == b'-') {
+                    key = stamp.to_string();
+                }
+                (key.clone(), line.to_string())
+            })
+            .collect()
+    };
+    let (a, b) = (keyed(a), keyed(b));
+    let (mut i, mut j) = (0, 0);
+    let mut out = String::new();
+    while i < a.len() || j < b.len() {
+        let take_a = j == b.len() || (i < a.len() && a[i].0 <= b[j].0);
+        let line = if take_a { &a[i].1 } else { &b[j].1 };
+        if take_a {
+            i += 1;
+        } else {
+            j += 1;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+fn newest(out: String, max: usize) -> String {
+    if out.len() <= max {
+        return out;
+    }
+    let mut start = out.len() - max;
+    // Whole lines only, and never split a UTF-8 sequence.
+    while !out.is_char_boundary(start) {
+        start += 1;
+    }
+    let start = out[start..].find('\n').map_or(start, |n| start + n + 1);
+    format!("[... older lines truncated ...]\n{}", &out[start..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INSPECT: &str = r#"[{
+        "Id": "4f2a9c1e7b3d5a6f8e9d0c1b2a3f4e5d",
+        "Created": "2026-10-01T08:00:00.123Z",
+        "Image": "sha256:abc123",
+        "RestartCount": 3,
+        "State": {"Status": "running", "StartedAt": "2026-10-18T09:00:00Z", "FinishedAt": "2026-10-18T08:59:50Z",
+                  "ExitCode": 0, "OOMKilled": false,
+                  "Health": {"Status": "unhealthy", "FailingStreak": 2,
+                             "Log": [{"Start": "s", "End": "e", "ExitCode": 1, "Output": "  curl: (7) refused\n"}]}},
+        "Config": {"Image": "alh477/dcf-rs:latest", "Env": ["DCF_NODE_PRIVATE_KEY=hunter2"]},
+        "HostConfig": {"RestartPolicy": {"Name": "unless-stopped"}, "Memory": 536870912, "NanoCpus": 1000000000},
+        "NetworkSettings": {"Ports": {"7777/udp": [{"HostIp": "0.0.0.0", "HostPort": "7777"}], "9000/tcp": null}},
+        "Mounts": [{"Type": "bind", "Source": "/nix/store/x-config.toml", "Destination": "/tmp/config.toml", "RW": false}]
+    }]"#;
+
+    #[test]
+    fn builds_report_without_env() {
+        let r = build(parse_inspect(INSPECT).unwrap(), vec!["alh477/dcf-rs@sha256:def".into()], None, Vec::new());
+        assert_eq!((r.id.as_str(), r.restart_count, r.cpu_limit), ("4f2a9c1e7b3d", 3, Some(1.0)));
+        assert_eq!(r.ports[0], Port { container: "7777/udp".into(), published: vec!["0.0.0.0:7777".into()] });
+        assert!(r.ports[1].published.is_empty());
+        assert_eq!(r.state.health.as_ref().unwrap().log[0].output, "curl: (7) refused");
+        assert!(r.notes.iter().any(|n| n.contains("failing 2")) && r.notes.iter().any(|n| n.contains("floating tag")));
+        let json = report::json(&r);
+        assert!(!json.contains("hunter2") && json.contains("\"rw\": false"));
+    }
+
+    #[test]
+    fn missing_container_is_absent() {
+        let missing = Err(Error::Exit { code: 1, stderr: "Error: No such object: dcf-sdk".into() });
+        assert_eq!(outcome::<String>(missing).unwrap_err(), "[absent] Error: No such object: dcf-sdk");
+        let daemon = Err(Error::Exit { code: 1, stderr: "Cannot connect to the Docker daemon".into() });
+        assert!(outcome::<String>(daemon).unwrap_err().starts_with("[error]"));
+        assert!(parse_inspect("[]").unwrap_err().starts_with("[absent]"));
+        assert!(parse_inspect("Error: No such object: dcf-sdk").unwrap_err().starts_with("[error]"));
+    }
+
+    #[test]
+    fn parses_stats_row() {
+        let row = r#"{"BlockIO":"0B / 0B","CPUPerc":"1.25%","Container":"dcf-sdk","ID":"4f2a","MemPerc":"2.34%","MemUsage":"12MiB / 512MiB","Name":"dcf-sdk","NetIO":"1kB / 2kB","PIDs":"7"}"#;
+        let s = parse_stats(row).unwrap();
+        assert_eq!((s.cpu_pct, s.memory_pct, s.pids.as_str()), (Some(1.25), Some(2.34), "7"));
+    }
+
+    #[test]
+    fn refuses_mutating_verbs_and_bounds_logs() {
+        for args in [&["rm", "-f", NODE_CONTAINER][..], &["restart", NODE_CONTAINER], &["image", "rm", "x"], &["stats"], &[]] {
+            assert!(docker(args).unwrap_err().starts_with("[denied]"), "{args:?}");
+        }
+        let out: String = (0..100).map(|i| format!("line {i}\n")).collect();
+        let cut = newest(out, 40);
+        assert!(cut.starts_with("[... older lines truncated ...]\nline "));
+        assert!(cut.ends_with("line 99\n") && cut.len() < 80);
+    }
+
+    #[test]
+    fn merges_streams_before_truncating() {
+        let stdout = "2026-10-18T09:00:00.000000001Z up\n2026-10-18T09:00:02.000000000Z peer joined\n";
+        let stderr = "2026-10-18T09:00:01.000000000Z warn: slow\n  continued\n2026-10-18T09:00:03.000000000Z error\n";
+        let merged = merge_by_timestamp(stdout, stderr);
+        let messages: Vec<&str> = merged.lines().map(|l| l.split_once(' ').map_or(l, |(_, m)| m)).collect();
+        assert_eq!(messages, ["up", "warn: slow", " continued", "peer joined", "error"]);
+        let cut = newest(merged, 90);
+        assert!(cut.contains("peer joined") && cut.ends_with("error\n") && !cut.contains(" up"));
+    }
+}
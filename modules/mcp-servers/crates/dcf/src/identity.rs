@@ -0,0 +1,387 @@
+//! `identity_status`: where the DCF node identity lives, never what it is.
+//!
+//! Four questions, each answered from metadata only:
+//!
+//! - **Public key / fingerprint** — from `hydramesh status`, else from a
+//!   `*_unencrypted` public-key entry that sops leaves in cleartext.
+//! - **Private half** — which sops file under the flake's secret dirs holds
+//!   an identity key, by key *name*, from [`oligarchy_mcp_core::sops`];
+//!   values stay `ENC[…]` and are never read into the report. sops
+//!   `lastmodified` and the recipient count come along.
+//! - **Mounted** — whether `dcf-id` (which consumes the `dcf-id-env` secret,
+//!   see `modules/dcf-identity.nix`) or `dcf-sdk` has a sops-nix runtime
+//!   secret bind-mounted. This is a heuristic: only `.Mounts` is inspected,
+//!   and `.Config.Env` is deliberately not read since it would carry secret
+//!   values, so a secret passed with `--env-file` leaves no trace.
+//! - **Recognised** — whether `hydramesh list-peers` carries our fingerprint.
+//!
+//! Nothing is decrypted and no key material other than the public key leaves
+//! this module.
+
+use std::path::Path;
+
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use base64::Engine;
+use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
+use oligarchy_mcp_core::sops::{self, Metadata};
+use oligarchy_mcp_core::{report, sandbox};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{container, ASPECT, NODE_CONTAINER};
+
+/// Flake-relative dirs the `.sops.yaml` creation rules encrypt into.
+const SECRET_DIRS: &[&str] = &["modules/secrets", "secrets"];
+/// Where sops-nix materialises decrypted secrets at activation.
+const SOPS_RUNTIME: &[&str] = &["/run/secrets", "/run/secrets.d", "/run/secrets-for-users"];
+/// The identity service container, consumer of the `dcf-id-env` secret.
+const IDENTITY_CONTAINER: &str = "dcf-id";
+/// What the mount check can and cannot see; carried into every report.
+const MOUNT_CHECK: &str = "heuristic: bind mounts from the sops-nix runtime dirs only; \
+     a secret passed with --env-file is not visible without reading .Config.Env, which this tool does not do";
+const MAX_SOPS_BYTES: usize = 256 * 1024;
+/// Field names a public key is published under, in SDK output and peer rows.
+const PUBKEY_FIELDS: &[&str] = &["public_key", "pubkey", "node_pubkey", "identity_key", "node_key"];
+const FINGERPRINT_FIELDS: &[&str] = &["fingerprint", "key_fingerprint", "pubkey_fingerprint"];
+
+#[derive(Debug, Serialize)]
+struct PublicKey {
+    /// `hydramesh status` or the sops file the cleartext entry came from.
+    source: String,
+    key: String,
+    /// `SHA256:` + unpadded base64 of the SHA-256 of the decoded key bytes.
+    /// Not comparable with `ssh-keygen -l`, which hashes the SSH wire blob.
+    fingerprint: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct SopsFile {
+    file: String,
+    /// Encrypted entries that look like a node identity's private half.
+    identity_keys: Vec<String>,
+    /// Every other encrypted entry, by name.
+    other_keys: Vec<String>,
+    lastmodified: Option<String>,
+    recipients: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Container {
+    name: &'static str,
+    role: &'static str,
+    /// `None` when docker could not be asked or gave no usable answer.
+    present: Option<bool>,
+    /// `None` when the container could not be inspected.
+    secret_mounted: Option<bool>,
+    /// Destinations of sops runtime mounts inside the container.
+    secret_mounts: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PeerCheck {
+    peers: Option<usize>,
+    /// `None` when there is no fingerprint to look for or no peer table.
+    recognized: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    sops_config: String,
+    sops_config_present: bool,
+    public_key: Option<PublicKey>,
+    private_half: Vec<SopsFile>,
+    mount_check: &'static str,
+    containers: Vec<Container>,
+    peer_table: PeerCheck,
+    notes: Vec<String>,
+}
+
+pub fn status() -> String {
+    let flake = sandbox::flake_dir();
+    let sops_yaml = flake.join(".sops.yaml");
+    let mut notes = Vec::new();
+
+    let mut private_half = Vec::new();
+    let mut cleartext_keys = Vec::new();
+    for (rel, text) in sops_files(&flake) {
+        if let Some(key) = cleartext_pubkey(&text) {
+            cleartext_keys.push((rel.clone(), key));
+        }
+        if let Some(f) = sops::format_for(&rel).and_then(|fmt| sops::inspect(fmt, &text)) {
+            private_half.push(sops_file(rel, f));
+        }
+    }
+    private_half.retain(|f| !f.identity_keys.is_empty() || !f.other_keys.is_empty());
+    if !private_half.iter().any(|f| !f.identity_keys.is_empty()) {
+        notes.push(format!(
+            "no sops file under {} has a node identity key entry",
+            SECRET_DIRS.join(", ")
+        ));
+    }
+
+    let status = runner::run(ASPECT, "hydramesh", &["status"], QUICK_TIMEOUT);
+    let public_key = status
+        .as_deref()
+        .ok()
+        .and_then(pubkey_from_status)
+        .map(|key| ("hydramesh status".to_string(), key))
+        .or_else(|| cleartext_keys.into_iter().next())
+        .map(|(source, key)| PublicKey { fingerprint: fingerprint(&key), source, key });
+    if let Err(e) = &status {
+        notes.push(format!("hydramesh status: {e}"));
+    }
+    if public_key.is_none() {
+        notes.push("no public key published by the node or in cleartext sops metadata".into());
+    }
+
+    let containers = vec![
+        container(IDENTITY_CONTAINER, "identity service; consumes dcf-id-env as an env file"),
+        container(NODE_CONTAINER, "community node"),
+    ];
+    if !containers.iter().any(|c| c.secret_mounted == Some(true)) {
+        notes.push("no container has a sops runtime bind mount; see mount_check".into());
+    }
+
+    let peer_table = match runner::run(ASPECT, "hydramesh", &["list-peers"], QUICK_TIMEOUT) {
+        Ok(out) => PeerCheck {
+            peers: peer_rows(&out).map(|rows| rows.len()),
+            recognized: public_key.as_ref().map(|k| recognizes(&out, &k.fingerprint)),
+        },
+        Err(e) => {
+            notes.push(format!("hydramesh list-peers: {e}"));
+            PeerCheck { peers: None, recognized: None }
+        }
+    };
+
+    report::json(&Report {
+        sops_config: sops_yaml.display().to_string(),
+        sops_config_present: sops_yaml.is_file(),
+        public_key,
+        private_half,
+        mount_check: MOUNT_CHECK,
+        containers,
+        peer_table,
+        notes,
+    })
+}
+
+/// Flake-relative paths and contents of the files in [`SECRET_DIRS`], read
+/// through the sandbox.
+fn sops_files(flake: &Path) -> Vec<(String, String)> {
+    let mut files = Vec::new();
+    for dir in SECRET_DIRS {
+        let Ok(entries) = std::fs::read_dir(flake.join(dir)) else { continue };
+        let mut names: Vec<String> =
+            entries.filter_map(|e| e.ok()?.file_name().into_string().ok()).collect();
+        names.sort();
+        for name in names {
+            let rel = format!("{dir}/{name}");
+            if let Ok(text) = sandbox::read_file(flake, &rel, MAX_SOPS_BYTES) {
+                files.push((rel, text));
+            }
+        }
+    }
+    files
+}
+
+/// `DCF_NODE_PRIVATE_KEY`, `mesh_identity_seed`, … but not
+/// `STRIPE_SECRET_KEY`: the name must say whose key it is.
+fn is_identity_key(name: &str) -> bool {
+    let n = name.to_ascii_lowercase();
+    ["node", "identity", "dcf", "mesh"].iter().any(|w| n.contains(w))
+        && ["key", "seed", "secret"].iter().any(|w| n.contains(w))
+        && !n.contains("public")
+        && !n.contains("pub_")
+}
+
+/// Entry names and metadata of one sops file, identity keys split out.
+fn sops_file(file: String, meta: Metadata) -> SopsFile {
+    let (identity_keys, other_keys) = meta.encrypted_keys.into_iter().partition(|k| is_identity_key(k));
+    SopsFile { file, identity_keys, other_keys, lastmodified: meta.lastmodified, recipients: meta.recipients.len() }
+}
+
+/// `KEY=value` (dotenv) or `key: value` (YAML/JSON), split at whichever
+/// separator comes first so `=` padding inside a YAML value stays put.
+fn split_entry(line: &str) -> Option<(&str, &str)> {
+    let at = line.find(['=', ':'])?;
+    Some((&line[..at], &line[at + 1..]))
+}
+
+/// A `*public*_unencrypted` entry: sops leaves the `_unencrypted` suffix in
+/// cleartext by default, which is where a public half can sit next to the
+/// private one.
+fn cleartext_pubkey(text: &str) -> Option<String> {
+    text.lines().find_map(|line| {
+        let (key, value) = split_entry(line)?;
+        let key = key.trim().trim_matches('"').to_ascii_lowercase();
+        let value = value.trim().trim_matches(|c| c == '"' || c == ',');
+        (key.ends_with("_unencrypted") && key.contains("pub") && !value.is_empty() && !value.starts_with("ENC["))
+            .then(|| value.to_string())
+    })
+}
+
+/// First string under a [`PUBKEY_FIELDS`] name, anywhere in the status JSON,
+/// or a `public key: …` line in text output.
+fn pubkey_from_status(out: &str) -> Option<String> {
+    if let Ok(v) = serde_json::from_str::<Value>(out) {
+        return find_field(&v, PUBKEY_FIELDS);
+    }
+    out.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        let key = key.trim().to_ascii_lowercase().replace(' ', "_");
+        PUBKEY_FIELDS.contains(&key.as_str()).then(|| value.trim().to_string()).filter(|v| !v.is_empty())
+    })
+}
+
+fn find_field(v: &Value, names: &[&str]) -> Option<String> {
+    match v {
+        Value::Object(map) => names
+            .iter()
+            .find_map(|n| map.get(*n).and_then(Value::as_str).map(str::to_string))
+            .or_else(|| map.values().find_map(|v| find_field(v, names))),
+        Value::Array(items) => items.iter().find_map(|v| find_field(v, names)),
+        _ => None,
+    }
+}
+
+/// The key's bytes: base64 or hex when it decodes as either, after dropping
+/// an `ed25519:`-style algorithm prefix; the literal string otherwise.
+fn key_bytes(key: &str) -> Vec<u8> {
+    let body = key.trim().rsplit(':').next().unwrap_or(key).trim();
+    if body.len().is_multiple_of(2) && body.len() >= 32 && body.chars().all(|c| c.is_ascii_hexdigit()) {
+        if let Ok(b) = (0..body.len()).step_by(2).map(|i| u8::from_str_radix(&body[i..i + 2], 16)).collect() {
+            return b;
+        }
+    }
+    STANDARD
+        .decode(body)
+        .or_else(|_| STANDARD_NO_PAD.decode(body))
+        .unwrap_or_else(|_| key.trim().as_bytes().to_vec())
+}
+
+fn fingerprint(key: &str) -> String {
+    format!("SHA256:{}", STANDARD_NO_PAD.encode(Sha256::digest(key_bytes(key))))
+}
+
+fn peer_rows(out: &str) -> Option<Vec<Value>> {
+    match serde_json::from_str::<Value>(out).ok()? {
+        Value::Array(rows) => Some(rows),
+        Value::Object(mut map) => match map.remove("peers")? {
+            Value::Array(rows) => Some(rows),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A peer row whose key fingerprints to ours or whose fingerprint field
+/// matches; for text tables, the fingerprint appearing anywhere.
+fn recognizes(out: &str, ours: &str) -> bool {
+    let bare = ours.trim_start_matches("SHA256:");
+    let Some(rows) = peer_rows(out) else {
+        return out.contains(bare);
+    };
+    rows.iter().any(|row| {
+        find_field(row, PUBKEY_FIELDS).is_some_and(|k| fingerprint(&k) == ours)
+            || find_field(row, FINGERPRINT_FIELDS).is_some_and(|f| f.trim_start_matches("SHA256:") == bare)
+    })
+}
+
+fn container(name: &'static str, role: &'static str) -> Container {
+    let inspected = container::docker(&["inspect", "--format", "{{json .Mounts}}", name]);
+    let (present, secret_mounts) = match inspected.as_deref().map(runtime_mounts) {
+        Ok(Some(mounts)) => (Some(true), Some(mounts)),
+        // Neither a mount list nor docker's "no such object": unknown.
+        Ok(None) => (None, None),
+        Err(e) => (e.starts_with("[absent]").then_some(false), None),
+    };
+    Container {
+        name,
+        role,
+        present,
+        secret_mounted: secret_mounts.as_ref().map(|m| !m.is_empty()),
+        secret_mounts: secret_mounts.unwrap_or_default(),
+    }
+}
+
+/// Destinations of mounts whose source is under a sops-nix runtime dir;
+/// `None` when `json` is not a mount list.
+fn runtime_mounts(json: &str) -> Option<Vec<String>> {
+    let Ok(Value::Array(mounts)) = serde_json::from_str::<Value>(json) else {
+        return None;
+    };
+    let dests = mounts
+        .iter()
+        .filter(|m| {
+            m.get("Source").and_then(Value::as_str).is_some_and(|s| {
+                SOPS_RUNTIME.iter().any(|dir| Path::new(s).starts_with(dir))
+            })
+        })
+        .filter_map(|m| m.get("Destination").and_then(Value::as_str).map(str::to_string))
+        .collect();
+    Some(dests)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOTENV: &str = "\
+DCF_NODE_PRIVATE_KEY=ENC[AES256_GCM,data:abc,iv:x,tag:y,type:str]
+DCF_NODE_PUBLIC_KEY_unencrypted=ed25519:11qYAYKxCrfVS/7TyWQHOg7hcvPapiMlrwIaaPcHURo=
+STRIPE_SECRET_KEY=ENC[AES256_GCM,data:def,iv:x,tag:y,type:str]
+sops_age__list_0__map_recipient=age1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqq
+sops_lastmodified=2026-09-01T10:00:00Z
+sops_version=3.9.0
+";
+
+    #[test]
+    fn reads_sops_metadata_without_values() {
+        let scan = |file: &str, text: &str| {
+            sops::inspect(sops::format_for(file)?, text).map(|m| sops_file(file.to_string(), m))
+        };
+        let f = scan("modules/secrets/dcf-id.enc.env", DOTENV).unwrap();
+        assert_eq!(f.identity_keys, ["DCF_NODE_PRIVATE_KEY"]);
+        assert_eq!(f.other_keys, ["STRIPE_SECRET_KEY"]);
+        assert_eq!((f.lastmodified.as_deref(), f.recipients), (Some("2026-09-01T10:00:00Z"), 1));
+        assert!(scan("plain.env", "DCF_NODE_KEY=hunter2\n").is_none());
+
+        let yaml = "node_identity_seed: ENC[AES256_GCM,data:q]\nsops:\n    age:\n        - recipient: age1abc\n    lastmodified: \"2026-09-02T00:00:00Z\"\n";
+        let f = scan("secrets/node.yaml", yaml).unwrap();
+        assert_eq!((f.identity_keys.len(), f.recipients), (1, 1));
+        assert_eq!(f.lastmodified.as_deref(), Some("2026-09-02T00:00:00Z"));
+    }
+
+    #[test]
+    fn fingerprints_and_recognises_peers() {
+        let key = cleartext_pubkey(DOTENV).unwrap();
+        let fp = fingerprint(&key);
+        assert!(fp.starts_with("SHA256:") && !fp.ends_with('='));
+        // Same bytes whether published as base64 or hex.
+        let hex: String = key_bytes(&key).iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(fingerprint(&hex), fp);
+
+        let status = format!(r#"{{"node_id":"n1","identity":{{"public_key":"{key}"}}}}"#);
+        assert_eq!(pubkey_from_status(&status), Some(key.clone()));
+        assert_eq!(pubkey_from_status("Node: n1\nPublic key: abc\n").as_deref(), Some("abc"));
+
+        let peers = format!(r#"{{"peers":[{{"id":"a","pubkey":"{hex}"}},{{"id":"b"}}]}}"#);
+        assert!(recognizes(&peers, &fp));
+        assert_eq!(peer_rows(&peers).map(|r| r.len()), Some(2));
+        let by_fp = format!(r#"[{{"id":"a","fingerprint":"{}"}}]"#, fp.trim_start_matches("SHA256:"));
+        assert!(recognizes(&by_fp, &fp));
+        assert!(!recognizes(r#"[{"id":"a","pubkey":"AAAA"}]"#, &fp));
+    }
+
+    #[test]
+    fn finds_sops_runtime_mounts() {
+        let json = r#"[{"Type":"bind","Source":"/nix/store/x-dcf_config.toml","Destination":"/tmp/config.toml"},
+                       {"Type":"bind","Source":"/run/secrets/dcf-node-key","Destination":"/run/keys/node"}]"#;
+        assert_eq!(runtime_mounts(json).unwrap(), ["/run/keys/node"]);
+        assert_eq!(runtime_mounts("[]"), Some(Vec::new()));
+        // Docker's error text is not an empty mount list.
+        assert_eq!(runtime_mounts("Cannot connect to the Docker daemon at unix:///var/run/docker.sock"), None);
+        assert!(is_identity_key("mesh_identity_seed") && !is_identity_key("DISCORD_CLIENT_SECRET"));
+    }
+}
@@ -1,19 +1,24 @@
 //! oligarchy-dcf-mcp — read-only MCP server for the Oligarchy DCF services:
-//! the community-node container, sops identity presence, and the tray unit.
+//! the community-node container, sops identity metadata, and the tray unit.
 //! See `docs/mcp-servers-roadmap.md` §4.3.
 //!
 //! The mesh/protocol stack itself (HydraMesh + DCF SDK CLIs, HydraModem) lives
 //! in the `hydramesh` aspect — see §4.9.
 
+mod container;
+mod identity;
+
 use oligarchy_mcp_core::audit;
 use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
 use oligarchy_mcp_core::runner_mcp;
-use oligarchy_mcp_core::sandbox;
 
 use rmcp::{ServerHandler, model::{ServerCapabilities, ServerInfo}, tool};
 
 const ASPECT: &str = "dcf";
 
+/// The community node's container (`modules/dcf-community-node.nix`).
+const NODE_CONTAINER: &str = "dcf-sdk";
+
 #[derive(Debug, Clone, Default)]
 struct Server;
 
@@ -39,6 +44,18 @@ impl Server {
         .unwrap_or_else(|e| format!("[error] {e}"))
     }
 
+    #[tool(description = "DCF community node container report from read-only docker verbs: image, image digests, created, restart count and policy, state with health-check history, published ports, mounts, CPU/memory limits and `docker stats --no-stream` usage. Never shows the container's environment.")]
+    fn node_container(&self) -> String {
+        audit::tool(ASPECT, "node_container", NODE_CONTAINER);
+        container::report()
+    }
+
+    #[tool(description = "Last lines of the DCF community node's container log (`docker logs --tail N --timestamps`). tail defaults to 100, clamped to 1..1000; output capped at 128 KiB, newest kept.")]
+    fn node_logs(&self, #[tool(param)] tail: Option<u32>) -> String {
+        audit::tool(ASPECT, "node_logs", &format!("tail={}", tail.unwrap_or_default()));
+        container::logs(tail)
+    }
+
     #[tool(description = "DCF mesh peer list (via the HydraMesh SDK). See the `hydramesh` aspect for the full mesh surface.")]
     fn mesh_peers(&self) -> String {
         audit::tool(ASPECT, "mesh_peers", "");
@@ -46,17 +63,10 @@ impl Server {
             .unwrap_or_else(|e| format!("[error] {e}"))
     }
 
-    #[tool(description = "sops identity key PRESENCE only (never decrypt). Shows .sops.yaml path presence.")]
+    #[tool(description = "DCF node identity, metadata only (never decrypts): public key and SHA256 fingerprint (from `hydramesh status` or a cleartext `*_unencrypted` sops entry), which sops file holds the private half (by entry name, with lastmodified and recipient count), whether the dcf-sdk container has a sops runtime secret mounted, and whether `hydramesh list-peers` recognises our fingerprint.")]
     fn identity_status(&self) -> String {
         audit::tool(ASPECT, "identity_status", "");
-        let flake = sandbox::flake_dir();
-        let sops_yaml = flake.join(".sops.yaml");
-        let present = sops_yaml.is_file();
-        format!(
-            "sops config: {} ({})",
-            sops_yaml.display(),
-            if present { "present" } else { "absent" }
-        )
+        identity::status()
     }
 
     #[tool(description = "DCF tray systemd unit status (read-only).")]
@@ -76,7 +86,7 @@ impl Server {
 impl ServerHandler for Server {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
-            instructions: Some("Oligarchy DCF mesh aspect — node status, peers, identity presence (read-only).".into()),
+            instructions: Some("Oligarchy DCF mesh aspect — node status, peers, identity metadata (read-only).".into()),
             capabilities: ServerCapabilities::builder().enable_tools().build(),
             ..Default::default()
         }
@@ -87,7 +97,7 @@ impl ServerHandler for Server {
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt().init();
     tracing::info!(aspect = ASPECT, "starting");
-    runner_mcp::serve(Server::default()).await
+    runner_mcp::serve(Server).await
 }
 
 #[cfg(test)]
@@ -58,7 +58,7 @@ impl ServerHandler for Server {
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt().init();
     tracing::info!(aspect = ASPECT, "starting");
-    runner_mcp::serve(Server::default()).await
+    runner_mcp::serve(Server).await
 }
 
 #[cfg(test)]
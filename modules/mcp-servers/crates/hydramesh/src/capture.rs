@@ -0,0 +1,410 @@
+//! `decode_capture`: offline decoding of DCF frames from field-test captures.
+//!
+//! The wire quantum is the 17-byte DeModFrame (version 1), as specified by
+//! the reference codec and mirrored in
+//! `modules/hypr-controller/android/app/src/main/kotlin/dcf/Frame.kt`:
+//!
+//! ```text
+//!   [0]     sync = 0xD3
+//!   [1]     version[7:4] = 1 | frame_type[3:0]
+//!   [2:4]   seq      u16
+//!   [4:6]   src      u16
+//!   [6:8]   dst      u16
+//!   [8:12]  payload  4 bytes
+//!   [12:15] ts_us    u24
+//!   [15:17] CRC-16/CCITT-FALSE over bytes [0..15)
+//! ```
+//!
+//! Captures are raw byte dumps, hex dumps, or WAV files. A WAV is only
+//! unwrapped, not demodulated: frames are found in its data chunk when the
+//! recorder stored demodulated bytes, and otherwise the header is reported
+//! with a note, because turning audio into bits needs the modem (`frame_rx`)
+//! and this tool never touches the audio plane. Files are read from one
+//! sandboxed directory and nothing is executed or transmitted.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use oligarchy_mcp_core::{audit, report, sandbox, Error};
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+
+use crate::ASPECT;
+
+const SYNC: u8 = 0xD3;
+const VERSION: u8 = 1;
+const FRAME_SIZE: usize = 17;
+const CRC_COVER: usize = 15;
+const BROADCAST: u16 = 0xFFFF;
+/// Captures larger than this are decoded up to the cap and flagged.
+const MAX_CAPTURE_BYTES: u64 = 16 * 1024 * 1024;
+const DEFAULT_MAX_FRAMES: usize = 200;
+const MAX_FRAMES: usize = 5000;
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct CaptureQuery {
+    /// Capture file, relative to the captures directory.
+    pub file: String,
+    /// How many frame records to list (default 200, max 5000). Counts and
+    /// sequence analysis always cover the whole capture.
+    pub max_frames: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct FrameRecord {
+    offset: usize,
+    version: u8,
+    frame_type: &'static str,
+    seq: u16,
+    src: u16,
+    dst: u16,
+    broadcast: bool,
+    payload: String,
+    ts_us: u32,
+    crc: String,
+    crc_ok: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct WavInfo {
+    audio_format: u16,
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    data_bytes: usize,
+    duration_secs: Option<f64>,
+}
+
+/// Sequence accounting for one source address, over CRC-valid frames.
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct Stream {
+    src: u16,
+    frames: usize,
+    first_seq: u16,
+    last_seq: u16,
+    /// Sequence numbers skipped between consecutive frames (u16 wrap aware).
+    missing: usize,
+    duplicates: usize,
+    /// Frames whose seq went backwards by less than half the space.
+    reordered: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    file: String,
+    size: u64,
+    truncated: bool,
+    /// `raw`, `hex` or `wav`.
+    container: &'static str,
+    wav: Option<WavInfo>,
+    frames_total: usize,
+    crc_ok: usize,
+    crc_bad: usize,
+    /// Bytes scanned that belong to no frame.
+    unframed_bytes: usize,
+    by_type: BTreeMap<&'static str, usize>,
+    streams: Vec<Stream>,
+    frames: Vec<FrameRecord>,
+    frames_omitted: usize,
+    notes: Vec<String>,
+}
+
+/// `OLIGARCHY_HYDRAMESH_CAPTURES`, else `captures/` in the aspect's state dir.
+pub fn captures_dir() -> PathBuf {
+    std::env::var("OLIGARCHY_HYDRAMESH_CAPTURES")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| audit::state_dir(ASPECT).join("captures"))
+}
+
+pub fn decode(q: &CaptureQuery) -> String {
+    decode_in(&captures_dir(), q)
+}
+
+fn decode_in(dir: &Path, q: &CaptureQuery) -> String {
+    let Ok(base) = dir.canonicalize() else {
+        return format!("[absent] captures directory {} does not exist", dir.display());
+    };
+    let path = match sandbox::resolve_within(&base, &q.file) {
+        Ok(p) if p.is_file() => p,
+        Ok(_) => return format!("[error] {} is not a file", q.file),
+        Err(e @ Error::PathEscape(_)) => return format!("[denied] {e}"),
+        Err(e) => return format!("[error] {e}"),
+    };
+    let (bytes, size) = match read_capped(&path) {
+        Ok(r) => r,
+        Err(e) => return format!("[error] {}: {e}", path.display()),
+    };
+    let max_frames = q.max_frames.unwrap_or(DEFAULT_MAX_FRAMES).min(MAX_FRAMES);
+    report::json(&analyse(&q.file, size, &bytes, max_frames))
+}
+
+fn read_capped(path: &Path) -> std::io::Result<(Vec<u8>, u64)> {
+    let file = std::fs::File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut bytes = Vec::new();
+    file.take(MAX_CAPTURE_BYTES).read_to_end(&mut bytes)?;
+    Ok((bytes, size))
+}
+
+fn analyse(file: &str, size: u64, bytes: &[u8], max_frames: usize) -> Report {
+    let mut notes = Vec::new();
+    let truncated = size > bytes.len() as u64;
+    if truncated {
+        notes.push(format!("decoded the first {MAX_CAPTURE_BYTES} of {size} bytes"));
+    }
+    let (container, wav, data) = if let Some((info, data)) = parse_wav(bytes) {
+        ("wav", Some(info), data.to_vec())
+    } else if let Some(data) = parse_hex(bytes) {
+        ("hex", None, data)
+    } else {
+        ("raw", None, bytes.to_vec())
+    };
+
+    let frames = scan(&data);
+    let crc_ok = frames.iter().filter(|f| f.crc_ok).count();
+    let framed = frames.len() * FRAME_SIZE;
+    let mut by_type = BTreeMap::new();
+    for f in frames.iter().filter(|f| f.crc_ok) {
+        *by_type.entry(f.frame_type).or_insert(0) += 1;
+    }
+    if frames.is_empty() {
+        notes.push(match container {
+            "wav" => "no frames in the WAV data chunk; audio captures must be demodulated \
+                      with the modem (frame_rx) first"
+                .into(),
+            _ => format!("no 0x{SYNC:02X}-synced version-{VERSION} frames found"),
+        });
+    }
+    Report {
+        file: file.to_string(),
+        size,
+        truncated,
+        container,
+        wav,
+        frames_total: frames.len(),
+        crc_ok,
+        crc_bad: frames.len() - crc_ok,
+        unframed_bytes: data.len() - framed,
+        by_type,
+        streams: streams(&frames),
+        frames_omitted: frames.len().saturating_sub(max_frames),
+        frames: frames.into_iter().take(max_frames).collect(),
+        notes,
+    }
+}
+
+/// CRC-16/CCITT-FALSE: poly 0x1021, init 0xFFFF, no reflection, no xorout.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &b in data {
+        crc ^= u16::from(b) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+fn frame_type(t: u8) -> &'static str {
+    match t {
+        0 => "data",
+        1 => "ack",
+        2 => "beacon",
+        3 => "ctrl",
+        _ => "reserved",
+    }
+}
+
+fn hex(b: &[u8]) -> String {
+    b.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn decode_frame(offset: usize, w: &[u8]) -> FrameRecord {
+    let u16_at = |i: usize| u16::from_be_bytes([w[i], w[i + 1]]);
+    let stored = u16_at(15);
+    let dst = u16_at(6);
+    FrameRecord {
+        offset,
+        version: w[1] >> 4,
+        frame_type: frame_type(w[1] & 0x0F),
+        seq: u16_at(2),
+        src: u16_at(4),
+        dst,
+        broadcast: dst == BROADCAST,
+        payload: hex(&w[8..12]),
+        ts_us: u32::from_be_bytes([0, w[12], w[13], w[14]]),
+        crc: format!("{stored:04x}"),
+        crc_ok: crc16(&w[..CRC_COVER]) == stored,
+    }
+}
+
+/// Walks the buffer for sync + version. A CRC-valid candidate is a frame; a
+/// CRC-invalid one is only reported as a corrupt frame when it starts
+/// exactly where the previous frame ended (a contiguous dump), since a
+/// stray 0xD3 in noise would otherwise be counted as one.
+fn scan(data: &[u8]) -> Vec<FrameRecord> {
+    let mut frames = Vec::new();
+    let mut i = 0;
+    let mut last_end = 0;
+    while i + FRAME_SIZE <= data.len() {
+        if data[i] == SYNC && data[i + 1] >> 4 == VERSION {
+            let f = decode_frame(i, &data[i..i + FRAME_SIZE]);
+            if f.crc_ok || i == last_end {
+                frames.push(f);
+                i += FRAME_SIZE;
+                last_end = i;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    frames
+}
+
+fn streams(frames: &[FrameRecord]) -> Vec<Stream> {
+    let mut by_src: BTreeMap<u16, Stream> = BTreeMap::new();
+    for f in frames.iter().filter(|f| f.crc_ok) {
+        let s = by_src.entry(f.src).or_insert_with(|| Stream { src: f.src, first_seq: f.seq, ..Default::default() });
+        if s.frames > 0 {
+            match f.seq.wrapping_sub(s.last_seq) {
+                0 => s.duplicates += 1,
+                d if d < 0x8000 => s.missing += usize::from(d - 1),
+                _ => s.reordered += 1,
+            }
+        }
+        s.frames += 1;
+        if s.frames == 1 || f.seq.wrapping_sub(s.last_seq) < 0x8000 {
+            s.last_seq = f.seq;
+        }
+    }
+    by_src.into_values().collect()
+}
+
+/// The `fmt ` header and `data` chunk of a RIFF/WAVE file.
+fn parse_wav(bytes: &[u8]) -> Option<(WavInfo, &[u8])> {
+    if bytes.len() < 12 || &bytes[..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return None;
+    }
+    let le16 = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+    let le32 = |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+    let mut fmt = None;
+    let mut i = 12;
+    while i + 8 <= bytes.len() {
+        let id = &bytes[i..i + 4];
+        let len = le32(i + 4) as usize;
+        let body = i + 8;
+        if id == b"fmt " && len >= 16 && body + 16 <= bytes.len() {
+            fmt = Some((le16(body), le16(body + 2), le32(body + 4), le16(body + 14)));
+        } else if id == b"data" {
+            let (audio_format, channels, sample_rate, bits_per_sample) = fmt?;
+            // A recorder killed mid-capture leaves the length unpatched.
+            let data = &bytes[body..(body + len).min(bytes.len())];
+            let frame_bytes = u32::from(channels) * u32::from(bits_per_sample).div_ceil(8);
+            let duration_secs = (frame_bytes > 0 && sample_rate > 0)
+                .then(|| data.len() as f64 / f64::from(frame_bytes) / f64::from(sample_rate));
+            let info = WavInfo { audio_format, channels, sample_rate, bits_per_sample, data_bytes: data.len(), duration_secs };
+            return Some((info, data));
+        }
+        i = body + len + (len & 1);
+    }
+    None
+}
+
+/// A text hex dump (`d3 13 12 …`, `0xd3,0x13`, one frame per line, …).
+/// `None` unless the file is nothing but hex digits, separators and an
+/// optional `offset:` column per line.
+fn parse_hex(bytes: &[u8]) -> Option<Vec<u8>> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    let mut digits = String::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let line = line.split_once(':').map_or(line, |(_, rest)| rest);
+        for token in line.split(|c: char| c.is_whitespace() || c == ',') {
+            let token = token.strip_prefix("0x").unwrap_or(token);
+            if !token.chars().all(|c| c.is_ascii_hexdigit()) {
+                return None;
+            }
+            digits.push_str(token);
+        }
+    }
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..digits.len()).step_by(2).map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The reference codec's example frame: ctrl, seq 0x1234, 1 -> broadcast.
+    const EXAMPLE: &str = "d31312340001ffffdeadbeefab12cd24c0";
+
+    fn frame(seq: u16, src: u16) -> Vec<u8> {
+        let mut w = vec![SYNC, VERSION << 4];
+        w.extend(seq.to_be_bytes());
+        w.extend(src.to_be_bytes());
+        w.extend(BROADCAST.to_be_bytes());
+        w.extend([0, 0, 0, 0, 0, 0, 1]);
+        w.extend(crc16(&w).to_be_bytes());
+        w
+    }
+
+    #[test]
+    fn matches_reference_anchors() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+        assert_eq!(crc16(&[0; 15]), 0x4EC3);
+        let f = &scan(&parse_hex(EXAMPLE.as_bytes()).unwrap())[0];
+        assert_eq!((f.frame_type, f.seq, f.src, f.dst, f.broadcast), ("ctrl", 0x1234, 1, BROADCAST, true));
+        assert_eq!((f.payload.as_str(), f.ts_us, f.crc_ok), ("deadbeef", 0xAB12CD, true));
+    }
+
+    #[test]
+    fn scans_stream_with_noise_corruption_and_gaps() {
+        let mut data = vec![0x00, SYNC, 0x42];
+        for seq in [1, 2, 5, 5, 4] {
+            data.extend(frame(seq, 7));
+        }
+        let mut bad = frame(6, 7);
+        bad[9] ^= 0xFF;
+        data.extend(bad);
+        data.extend(frame(0xFFFF, 8));
+        data.extend(frame(1, 8));
+
+        let r = analyse("t.bin", data.len() as u64, &data, 3);
+        assert_eq!((r.container, r.frames_total, r.crc_ok, r.crc_bad), ("raw", 8, 7, 1));
+        assert_eq!(r.unframed_bytes, 3);
+        assert_eq!((r.frames.len(), r.frames_omitted), (3, 5));
+        assert_eq!(r.streams[0], Stream { src: 7, frames: 5, first_seq: 1, last_seq: 5, missing: 2, duplicates: 1, reordered: 1 });
+        assert_eq!((r.streams[1].missing, r.streams[1].last_seq), (1, 1));
+    }
+
+    #[test]
+    fn unwraps_wav_and_confines_reads() {
+        let payload = frame(3, 2);
+        let mut wav = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+        wav.extend(16u32.to_le_bytes());
+        wav.extend([1, 0, 1, 0]);
+        wav.extend(8000u32.to_le_bytes());
+        wav.extend(8000u32.to_le_bytes());
+        wav.extend([1, 0, 8, 0]);
+        wav.extend(b"data");
+        wav.extend((payload.len() as u32).to_le_bytes());
+        wav.extend(&payload);
+        let r = analyse("t.wav", wav.len() as u64, &wav, 10);
+        let info = r.wav.as_ref().unwrap();
+        assert_eq!((info.sample_rate, info.bits_per_sample, info.data_bytes), (8000, 8, 17));
+        assert_eq!((r.container, r.crc_ok), ("wav", 1));
+
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("captures");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("cap.hex"), format!("0000: {EXAMPLE}\n")).unwrap();
+        std::fs::write(root.path().join("outside.bin"), frame(1, 1)).unwrap();
+        let q = |file: &str| CaptureQuery { file: file.into(), max_frames: None };
+        assert!(decode_in(&dir, &q("cap.hex")).contains("\"crc_ok\": 1"));
+        assert!(decode_in(&dir, &q("../outside.bin")).starts_with("[denied]"));
+        assert!(decode_in(&root.path().join("nope"), &q("cap.hex")).starts_with("[absent]"));
+    }
+}
@@ -0,0 +1,378 @@
+//! `node_config`: the DCF node config (`/etc/hydramesh/dcf_config.toml`)
+//! parsed, linted, and diffed against what `modules/hydramesh.nix` generates.
+//!
+//! The lint checks the three `[server]` binds against each other and against
+//! [`known_endpoints`] (port collisions with other services, transport
+//! mismatches with the `dcf-node-*` entries), flags management listeners
+//! reachable from the LAN, and sanity-checks the discovery settings. The
+//! defaults are read from the module in the flake, not copied here, so the
+//! diff follows the module as it changes.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::Path;
+
+use oligarchy_mcp_core::known_endpoints::{self, Proto};
+use oligarchy_mcp_core::{report, sandbox};
+use serde::{Deserialize, Serialize};
+use toml::Value;
+
+/// Cap for config reads, matching the `read_module` convention.
+const MAX_CONFIG_BYTES: usize = 64 * 1024;
+/// Where the generated defaults live, relative to the flake.
+const MODULE: &str = "modules/hydramesh.nix";
+/// The Nix attribute whose `''…''` string is the generated config.
+const MODULE_ATTR: &str = "\"hydramesh/dcf_config.toml\".text = ''";
+/// Discovery modes the SDKs implement.
+const DISCOVERY_MODES: &[&str] = &["central", "p2p", "mdns", "static"];
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct NodeConfig {
+    pub mode: Option<String>,
+    pub node_id: Option<String>,
+    #[serde(default)]
+    pub server: ServerSection,
+    #[serde(default)]
+    pub network: NetworkSection,
+    pub shim: Option<ShimSection>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ServerSection {
+    /// HydraMesh binary protocol (UDP).
+    pub bind_udp: Option<String>,
+    /// gRPC management (TCP).
+    pub bind_grpc: Option<String>,
+    /// Shim listener (UDP).
+    pub bind_shim: Option<String>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct NetworkSection {
+    pub discovery_mode: Option<String>,
+    pub node_type: Option<String>,
+    pub gateway_url: Option<String>,
+    pub peers: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ShimSection {
+    pub target: Option<String>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Finding {
+    /// `error`, `warn` or `info`.
+    pub severity: &'static str,
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Change {
+    pub key: String,
+    pub default: Option<String>,
+    pub actual: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    path: String,
+    config: NodeConfig,
+    findings: Vec<Finding>,
+    /// Keys whose value differs from the module's generated config.
+    defaults_diff: Option<Vec<Change>>,
+    unavailable: Vec<String>,
+}
+
+pub fn parse(text: &str) -> Result<NodeConfig, String> {
+    toml::from_str(text).map_err(|e| format!("parsing DCF config: {e}"))
+}
+
+pub fn report(path: &Path) -> String {
+    let (base, name) = match (path.parent(), path.file_name()) {
+        (Some(b), Some(n)) => (b.to_path_buf(), n.to_string_lossy().into_owned()),
+        _ => return format!("[error] malformed config path: {}", path.display()),
+    };
+    if !path.is_file() {
+        return format!("[absent] {} — is custom.hydramesh.enable set?", path.display());
+    }
+    let text = match sandbox::read_file(&base, &name, MAX_CONFIG_BYTES) {
+        Ok(s) => s,
+        Err(e) => return format!("[error] {e}"),
+    };
+    let config = match parse(&text) {
+        Ok(c) => c,
+        Err(e) => return format!("[error] {e}"),
+    };
+    let mut unavailable = Vec::new();
+    let defaults_diff = match module_defaults() {
+        Ok(defaults) => Some(diff(&defaults, &text)),
+        Err(e) => {
+            unavailable.push(format!("{MODULE}: {e}"));
+            None
+        }
+    };
+    report::json(&Report {
+        path: path.display().to_string(),
+        findings: lint(&config),
+        config,
+        defaults_diff,
+        unavailable,
+    })
+}
+
+/// The config `modules/hydramesh.nix` writes, as TOML text.
+fn module_defaults() -> Result<String, String> {
+    let nix = sandbox::read_file(&sandbox::flake_dir(), MODULE, MAX_CONFIG_BYTES).map_err(|e| e.to_string())?;
+    extract_defaults(&nix).ok_or_else(|| format!("no {MODULE_ATTR}…'' block"))
+}
+
+/// The body of the indented string after [`MODULE_ATTR`], de-indented the
+/// way Nix does.
+fn extract_defaults(nix: &str) -> Option<String> {
+    let start = nix.find(MODULE_ATTR)? + MODULE_ATTR.len();
+    let body = &nix[start..start + nix[start..].find("'';")?];
+    let lines: Vec<&str> = body.lines().skip_while(|l| l.trim().is_empty()).collect();
+    let indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    Some(lines.iter().map(|l| l.get(indent..).unwrap_or("").trim_end()).collect::<Vec<_>>().join("\n"))
+}
+
+/// Leaf values under dotted keys.
+fn leaves(prefix: &str, v: &Value, out: &mut BTreeMap<String, String>) {
+    match v {
+        Value::Table(t) => {
+            for (k, v) in t {
+                let key = if prefix.is_empty() { k.clone() } else { format!("{prefix}.{k}") };
+                leaves(&key, v, out);
+            }
+        }
+        other => {
+            out.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+fn diff(defaults: &str, actual: &str) -> Vec<Change> {
+    let flat = |text: &str| {
+        let mut out = BTreeMap::new();
+        if let Ok(v) = text.parse::<toml::Table>() {
+            leaves("", &Value::Table(v), &mut out);
+        }
+        out
+    };
+    let (d, a) = (flat(defaults), flat(actual));
+    let mut keys: Vec<&String> = d.keys().chain(a.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter(|k| d.get(*k) != a.get(*k))
+        .map(|k| Change { key: k.clone(), default: d.get(k).cloned(), actual: a.get(k).cloned() })
+        .collect()
+}
+
+fn finding(severity: &'static str, field: &str, message: String) -> Finding {
+    Finding { severity, field: field.to_string(), message }
+}
+
+pub fn lint(c: &NodeConfig) -> Vec<Finding> {
+    let mut f = Vec::new();
+    let binds = [
+        ("server.bind_udp", &c.server.bind_udp, Proto::Udp),
+        ("server.bind_grpc", &c.server.bind_grpc, Proto::Tcp),
+        ("server.bind_shim", &c.server.bind_shim, Proto::Udp),
+    ];
+    let mut parsed: Vec<(&str, SocketAddr, Proto)> = Vec::new();
+    for (field, value, proto) in binds {
+        match value.as_deref().map(str::parse::<SocketAddr>) {
+            None => f.push(finding("warn", field, "not set; the SDK default applies".into())),
+            Some(Err(_)) => {
+                f.push(finding("error", field, format!("not an ip:port bind address: {:?}", value.as_deref().unwrap_or(""))))
+            }
+            Some(Ok(addr)) => {
+                if addr.port() == 0 {
+                    f.push(finding("error", field, "port 0 binds a random port peers cannot reach".into()));
+                }
+                if addr.ip().is_unspecified() && proto == Proto::Tcp {
+                    f.push(finding(
+                        "warn",
+                        field,
+                        format!("gRPC management listens on {addr}, reachable from the LAN; bind 127.0.0.1 unless remote management is intended"),
+                    ));
+                }
+                parsed.push((field, addr, proto));
+            }
+        }
+    }
+    for (i, (field, addr, proto)) in parsed.iter().enumerate() {
+        for (other, oaddr, oproto) in &parsed[..i] {
+            if addr.port() == oaddr.port() && proto == oproto {
+                f.push(finding("error", field, format!("port {} collides with {other}", addr.port())));
+            }
+        }
+        for ep in known_endpoints::KNOWN.iter().filter(|ep| ep.port == Some(addr.port())) {
+            if ep.name.starts_with("dcf-node-") {
+                if ep.proto != *proto {
+                    f.push(finding(
+                        "warn",
+                        field,
+                        format!("transport mismatch: known_endpoints has {} as {:?} on port {}", ep.name, ep.proto, addr.port()),
+                    ));
+                }
+            } else if ep.proto == *proto {
+                f.push(finding("error", field, format!("port {} collides with known endpoint {}", addr.port(), ep.name)));
+            }
+        }
+    }
+    for ep in known_endpoints::KNOWN.iter().filter(|ep| ep.name.starts_with("dcf-node-")) {
+        if let Some(port) = ep.port {
+            if !parsed.iter().any(|(_, a, p)| a.port() == port && *p == ep.proto) {
+                f.push(finding(
+                    "info",
+                    "server",
+                    format!("known_endpoints expects {} on {:?} port {port}; egress/port audits will disagree", ep.name, ep.proto),
+                ));
+            }
+        }
+    }
+
+    match c.network.discovery_mode.as_deref() {
+        None => f.push(finding("warn", "network.discovery_mode", "not set; the SDK default applies".into())),
+        Some(m) if !DISCOVERY_MODES.contains(&m) => f.push(finding(
+            "error",
+            "network.discovery_mode",
+            format!("unknown mode {m:?}; expected one of {}", DISCOVERY_MODES.join(", ")),
+        )),
+        Some("static") if c.network.peers.as_ref().is_none_or(Vec::is_empty) => f.push(finding(
+            "error",
+            "network.peers",
+            "static discovery with no peers: the node will never find the mesh".into(),
+        )),
+        Some("central") if c.network.gateway_url.is_none() => f.push(finding(
+            "info",
+            "network.gateway_url",
+            "central discovery without a gateway_url; the SDK's built-in gateway is used".into(),
+        )),
+        Some(m) if m != "static" && c.network.peers.as_ref().is_some_and(|p| !p.is_empty()) => {
+            f.push(finding("info", "network.peers", format!("static peers are ignored in {m} discovery")))
+        }
+        _ => {}
+    }
+    if let Some(url) = &c.network.gateway_url {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            f.push(finding("error", "network.gateway_url", format!("not an http(s) URL: {url:?}")));
+        }
+    }
+    if let (Some(mode), Some(node_type)) = (&c.mode, &c.network.node_type) {
+        if mode != node_type {
+            f.push(finding("warn", "network.node_type", format!("{node_type:?} disagrees with mode {mode:?}")));
+        }
+    }
+    let udp_port = parsed.iter().find(|(field, ..)| *field == "server.bind_udp").map(|(_, a, _)| a.port());
+    if let Some(target) = c.shim.as_ref().and_then(|s| s.target.as_deref()) {
+        match (target.parse::<SocketAddr>(), udp_port) {
+            (Err(_), _) => f.push(finding("error", "shim.target", format!("not an ip:port address: {target:?}"))),
+            (Ok(t), Some(p)) if t.port() != p => f.push(finding(
+                "warn",
+                "shim.target",
+                format!("shim forwards to port {} but the binary protocol binds {p}", t.port()),
+            )),
+            _ => {}
+        }
+    }
+    f
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NIX: &str = r#"
+    environment.etc."hydramesh/dcf_config.toml".text = ''
+      # Generated by modules/hydramesh.nix — do not edit.
+      mode = "community"
+
+      [server]
+      bind_udp = "0.0.0.0:7777"
+      bind_grpc = "0.0.0.0:50051"
+      bind_shim = "0.0.0.0:8888"
+
+      [network]
+      discovery_mode = "central"
+      node_type = "community"
+    '';
+"#;
+
+    fn severities(f: &[Finding]) -> Vec<(&str, &str)> {
+        f.iter().map(|f| (f.severity, f.field.as_str())).collect()
+    }
+
+    #[test]
+    fn module_defaults_lint_to_expected_notes_only() {
+        let defaults = extract_defaults(NIX).unwrap();
+        assert!(defaults.starts_with("# Generated"));
+        let c = parse(&defaults).unwrap();
+        assert_eq!(c.server.bind_grpc.as_deref(), Some("0.0.0.0:50051"));
+        assert_eq!(
+            severities(&lint(&c)),
+            [("warn", "server.bind_grpc"), ("info", "network.gateway_url")]
+        );
+        assert!(diff(&defaults, &defaults).is_empty());
+    }
+
+    #[test]
+    fn flags_collisions_and_bad_discovery() {
+        let c = parse(
+            r#"mode = "community"
+[server]
+bind_udp = "0.0.0.0:7777"
+bind_grpc = "127.0.0.1:11434"
+bind_shim = "0.0.0.0:7777"
+[network]
+discovery_mode = "static"
+node_type = "relay"
+[shim]
+target = "127.0.0.1:9999"
+"#,
+        )
+        .unwrap();
+        let findings = lint(&c);
+        let got = severities(&findings);
+        for want in [
+            ("error", "server.bind_grpc"), // ollama's port
+            ("error", "server.bind_shim"), // same UDP port as bind_udp
+            ("info", "server"),            // dcf-node-grpc/-shim not where known_endpoints has them
+            ("error", "network.peers"),
+            ("warn", "network.node_type"),
+            ("warn", "shim.target"),
+        ] {
+            assert!(got.contains(&want), "{want:?} missing from {got:?}");
+        }
+        assert!(parse("[server]\nbind_udp = 7777\n").is_err());
+    }
+
+    #[test]
+    fn diffs_against_defaults() {
+        let defaults = extract_defaults(NIX).unwrap();
+        let d = diff(&defaults, "mode = \"community\"\n[server]\nbind_udp = \"0.0.0.0:7778\"\n[extra]\nk = 1\n");
+        let keys: Vec<&str> = d.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            ["extra.k", "network.discovery_mode", "network.node_type", "server.bind_grpc", "server.bind_shim", "server.bind_udp"]
+        );
+        assert_eq!(d[5].actual.as_deref(), Some("\"0.0.0.0:7778\""));
+    }
+}
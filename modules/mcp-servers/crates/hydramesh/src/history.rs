@@ -0,0 +1,323 @@
+//! Mesh metrics over time: the opt-in recorder (`--record`) and the
+//! `mesh_history` tool that reads what it wrote.
+//!
+//! Each `oligarchy-hydramesh-mcp --record` run samples `hydramesh metrics`
+//! and `dcf status` once and appends one JSON line per source to
+//! `metrics/<YYYY-MM-DD>.jsonl` under the aspect's state dir. The interval
+//! is the caller's: the NixOS module's `hydrameshRecorder` user timer, off by
+//! default. Lines are never rewritten; retention removes whole day files.
+//! A failed source is recorded as an `error` line, so gaps in the series
+//! can be told apart from a recorder that was not running.
+
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
+use oligarchy_mcp_core::{audit, report};
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::{Date, OffsetDateTime};
+
+use crate::sdk;
+use crate::ASPECT;
+
+pub const DEFAULT_RETENTION_DAYS: u32 = 30;
+/// Longest window `mesh_history` will aggregate.
+const MAX_WINDOW_SECS: i64 = 31 * 86_400;
+/// More buckets than this is a table nobody reads; widen the bucket.
+const MAX_BUCKETS: i64 = 500;
+/// Recorder errors listed in a history report, newest last.
+const MAX_ERRORS: usize = 50;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Sample {
+    pub ts: i64,
+    pub source: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub values: BTreeMap<String, f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, schemars::JsonSchema)]
+pub struct HistoryQuery {
+    /// How far back to look: `<n>m`, `<n>h` or `<n>d`. Default `24h`, at most 31 days.
+    pub window: Option<String>,
+    /// Bucket width, same syntax. Default `1h`.
+    pub bucket: Option<String>,
+    /// Only metrics whose `source.key` name contains this string.
+    pub metric: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Stats {
+    samples: usize,
+    min: f64,
+    avg: f64,
+    max: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Bucket {
+    start: String,
+    #[serde(flatten)]
+    stats: Stats,
+}
+
+#[derive(Debug, Serialize)]
+struct Series {
+    overall: Stats,
+    buckets: Vec<Bucket>,
+}
+
+#[derive(Debug, Serialize)]
+struct History {
+    window_secs: i64,
+    bucket_secs: i64,
+    from: String,
+    to: String,
+    /// Keyed `source.metric`.
+    metrics: BTreeMap<String, Series>,
+    errors: Vec<Sample>,
+}
+
+impl HistoryQuery {
+    pub fn summary(&self) -> String {
+        format!(
+            "window={} bucket={} metric={}",
+            self.window.as_deref().unwrap_or("24h"),
+            self.bucket.as_deref().unwrap_or("1h"),
+            self.metric.as_deref().unwrap_or("")
+        )
+    }
+}
+
+pub fn store_dir() -> PathBuf {
+    audit::state_dir(ASPECT).join("metrics")
+}
+
+fn day_file(dir: &Path, t: OffsetDateTime) -> PathBuf {
+    let day = t.date().format(format_description!("[year]-[month]-[day]")).unwrap_or_default();
+    dir.join(format!("{day}.jsonl"))
+}
+
+fn file_date(path: &Path) -> Option<Date> {
+    let stem = path.file_name()?.to_str()?.strip_suffix(".jsonl")?;
+    Date::parse(stem, format_description!("[year]-[month]-[day]")).ok()
+}
+
+/// `--record`: one sample per source, appended to today's file, then
+/// retention. Returns a one-line summary for the timer's journal.
+pub fn record(retention_days: u32) -> Result<String, String> {
+    let now = OffsetDateTime::now_utc();
+    audit::tool(ASPECT, "record", &format!("retention={retention_days}d"));
+    let samples: Vec<Sample> = [("hydramesh", "hydramesh", "metrics"), ("dcf", "dcf", "status")]
+        .into_iter()
+        .map(|(source, prog, verb)| {
+            let (values, error) = match runner::run(ASPECT, prog, &[verb], QUICK_TIMEOUT) {
+                Ok(raw) => (sdk::parse_metrics(&raw).values, None),
+                Err(e) => (BTreeMap::new(), Some(e.to_string())),
+            };
+            Sample { ts: now.unix_timestamp(), source: source.into(), values, error }
+        })
+        .collect();
+    let dir = store_dir();
+    append(&dir, now, &samples).map_err(|e| format!("{}: {e}", dir.display()))?;
+    let pruned = prune(&dir, now.date(), retention_days);
+    let ok = samples.iter().filter(|s| s.error.is_none()).count();
+    Ok(format!("recorded {ok}/{} sources to {}; pruned {pruned} day files", samples.len(), dir.display()))
+}
+
+fn append(dir: &Path, now: OffsetDateTime, samples: &[Sample]) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let mut text = String::new();
+    for s in samples {
+        text.push_str(&serde_json::to_string(s).map_err(std::io::Error::other)?);
+        text.push('\n');
+    }
+    // One write per sample run keeps concurrent appends line-atomic.
+    OpenOptions::new().create(true).append(true).open(day_file(dir, now))?.write_all(text.as_bytes())
+}
+
+/// Removes day files older than `days`. Returns how many went.
+fn prune(dir: &Path, today: Date, days: u32) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    // A retention reaching past the calendar's start keeps everything.
+    let Some(cutoff) = today.checked_sub(time::Duration::days(i64::from(days))) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|e| file_date(&e.path()).is_some_and(|d| d < cutoff))
+        .filter(|e| std::fs::remove_file(e.path()).is_ok())
+        .count()
+}
+
+/// `5m`, `2h`, `7d` in seconds.
+fn parse_span(s: &str) -> Option<i64> {
+    let (at, unit) = s.char_indices().last()?;
+    let n: i64 = s[..at].parse().ok().filter(|n| *n > 0)?;
+    n.checked_mul(match unit {
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86_400,
+        _ => return None,
+    })
+}
+
+pub fn run(q: &HistoryQuery) -> String {
+    let window = q.window.as_deref().unwrap_or("24h");
+    let bucket = q.bucket.as_deref().unwrap_or("1h");
+    let (Some(window_secs), Some(bucket_secs)) = (parse_span(window), parse_span(bucket)) else {
+        return format!("[denied] window and bucket must look like 30m, 6h or 7d (got {window:?}, {bucket:?})");
+    };
+    if window_secs > MAX_WINDOW_SECS {
+        return format!("[denied] window {window} is longer than 31 days");
+    }
+    if window_secs / bucket_secs > MAX_BUCKETS {
+        return format!("[denied] {window} in {bucket} buckets is more than {MAX_BUCKETS} buckets");
+    }
+    let dir = store_dir();
+    let now = OffsetDateTime::now_utc();
+    let from = now - time::Duration::seconds(window_secs);
+    let samples = match load(&dir, from.date()) {
+        Ok(s) if !s.is_empty() => s,
+        Ok(_) => {
+            return format!(
+                "[absent] no samples under {}; the recorder is opt-in (custom.mcpServers.hydrameshRecorder.enable)",
+                dir.display()
+            )
+        }
+        Err(e) => return format!("[error] {}: {e}", dir.display()),
+    };
+    report::json(&history(&samples, from.unix_timestamp(), now.unix_timestamp(), bucket_secs, q.metric.as_deref()))
+}
+
+/// Every sample in day files dated `from` or later. Unparseable lines (a
+/// torn write) are skipped.
+fn load(dir: &Path, from: Date) -> std::io::Result<Vec<Sample>> {
+    let mut files: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries.flatten().map(|e| e.path()).filter(|p| file_date(p).is_some_and(|d| d >= from)).collect(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    files.sort();
+    let mut out = Vec::new();
+    for f in files {
+        let text = std::fs::read_to_string(&f)?;
+        out.extend(text.lines().filter_map(|l| serde_json::from_str::<Sample>(l).ok()));
+    }
+    Ok(out)
+}
+
+fn stats(values: &[f64]) -> Stats {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    Stats { samples: values.len(), min, avg: values.iter().sum::<f64>() / values.len() as f64, max }
+}
+
+fn history(samples: &[Sample], from: i64, to: i64, bucket_secs: i64, metric: Option<&str>) -> History {
+    let mut series: BTreeMap<String, BTreeMap<i64, Vec<f64>>> = BTreeMap::new();
+    let mut errors = Vec::new();
+    for s in samples.iter().filter(|s| (from..=to).contains(&s.ts)) {
+        if s.error.is_some() {
+            errors.push(s.clone());
+        }
+        for (k, v) in &s.values {
+            let key = format!("{}.{k}", s.source);
+            if metric.is_some_and(|m| !key.contains(m)) || !v.is_finite() {
+                continue;
+            }
+            let start = s.ts - (s.ts - from).rem_euclid(bucket_secs);
+            series.entry(key).or_default().entry(start).or_default().push(*v);
+        }
+    }
+    let rfc3339 = |t: i64| {
+        OffsetDateTime::from_unix_timestamp(t).ok().and_then(|t| t.format(&Rfc3339).ok()).unwrap_or_default()
+    };
+    let metrics = series
+        .into_iter()
+        .map(|(key, buckets)| {
+            let all: Vec<f64> = buckets.values().flatten().copied().collect();
+            let buckets = buckets.into_iter().map(|(start, v)| Bucket { start: rfc3339(start), stats: stats(&v) }).collect();
+            (key, Series { overall: stats(&all), buckets })
+        })
+        .collect();
+    let skip = errors.len().saturating_sub(MAX_ERRORS);
+    History {
+        window_secs: to - from,
+        bucket_secs,
+        from: rfc3339(from),
+        to: rfc3339(to),
+        metrics,
+        errors: errors.split_off(skip),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(ts: i64, source: &str, values: &[(&str, f64)]) -> Sample {
+        Sample {
+            ts,
+            source: source.into(),
+            values: values.iter().map(|(k, v)| (k.to_string(), *v)).collect(),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn spans() {
+        assert_eq!(parse_span("30m"), Some(1800));
+        assert_eq!(parse_span("7d"), Some(604_800));
+        for bad in ["", "h", "0h", "-1h", "1w", "1.5h", "5µ", "µ"] {
+            assert_eq!(parse_span(bad), None, "{bad}");
+        }
+        assert!(run(&HistoryQuery { window: Some("60d".into()), ..Default::default() }).starts_with("[denied]"));
+        assert!(run(&HistoryQuery { window: Some("7d".into()), bucket: Some("1m".into()), ..Default::default() })
+            .starts_with("[denied]"));
+    }
+
+    #[test]
+    fn aggregates_windows_and_buckets() {
+        let mut failed = sample(3700, "dcf", &[]);
+        failed.error = Some("dcf is not on PATH".into());
+        let samples = vec![
+            sample(10, "hydramesh", &[("rtt_avg", 100.0)]),
+            sample(3600, "hydramesh", &[("rtt_avg", 10.0), ("frames", 1.0)]),
+            sample(3660, "hydramesh", &[("rtt_avg", 30.0)]),
+            failed,
+            sample(7300, "hydramesh", &[("rtt_avg", 50.0)]),
+        ];
+        let h = history(&samples, 3600, 7200, 3600, Some("rtt"));
+        assert_eq!(h.metrics.keys().collect::<Vec<_>>(), ["hydramesh.rtt_avg"]);
+        let s = &h.metrics["hydramesh.rtt_avg"];
+        assert_eq!(s.overall, Stats { samples: 2, min: 10.0, avg: 20.0, max: 30.0 });
+        assert_eq!(s.buckets.len(), 1);
+        assert_eq!(s.buckets[0].start, "1970-01-01T01:00:00Z");
+        assert_eq!(h.errors.len(), 1);
+    }
+
+    #[test]
+    fn appends_and_prunes_day_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let day = |d: &str| OffsetDateTime::parse(&format!("{d}T12:00:00Z"), &Rfc3339).unwrap();
+        append(dir.path(), day("2026-01-01"), &[sample(1, "dcf", &[("peers", 3.0)])]).unwrap();
+        append(dir.path(), day("2026-01-20"), &[sample(2, "dcf", &[("peers", 4.0)])]).unwrap();
+        append(dir.path(), day("2026-01-20"), &[sample(3, "dcf", &[("peers", 5.0)])]).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "keep").unwrap();
+        assert_eq!(load(dir.path(), day("2026-01-01").date()).unwrap().len(), 3);
+        assert_eq!(prune(dir.path(), day("2026-01-20").date(), u32::MAX), 0);
+        assert_eq!(prune(dir.path(), day("2026-01-20").date(), 14), 1);
+        let left = load(dir.path(), day("2025-01-01").date()).unwrap();
+        assert_eq!(left.iter().map(|s| s.ts).collect::<Vec<_>>(), [2, 3]);
+        assert!(dir.path().join("notes.txt").exists());
+    }
+}
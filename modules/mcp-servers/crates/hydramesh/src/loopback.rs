@@ -0,0 +1,308 @@
+//! `hydramodem_loopback` results: BER, SNR, sync success and throughput
+//! parsed from `dcf_loopback`, compared against a per-host baseline.
+//!
+//! The first successful run on a host becomes its baseline
+//! (`loopback-baseline-<host>.json` in the aspect's state dir); later runs
+//! pass or fail against it with fixed tolerances, so a DSP regression after
+//! a kernel or PipeWire update shows up as a failed check rather than as
+//! slightly different numbers in a transcript. `update_baseline` replaces it
+//! once a change is understood.
+
+use std::path::PathBuf;
+
+use oligarchy_mcp_core::runner::{self, HEAVY_TIMEOUT};
+use oligarchy_mcp_core::{audit, report};
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::ASPECT;
+
+/// BER may grow to this multiple of the baseline…
+const BER_FACTOR: f64 = 2.0;
+/// …but differences below this floor are noise at loopback sample sizes.
+const BER_FLOOR: f64 = 1e-6;
+/// SNR may drop by this many dB.
+const SNR_DROP_DB: f64 = 3.0;
+/// Sync success rate (0–1) may drop by this much.
+const SYNC_DROP: f64 = 0.05;
+/// Throughput may drop by this fraction.
+const THROUGHPUT_DROP: f64 = 0.10;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct Figures {
+    pub ber: Option<f64>,
+    pub snr_db: Option<f64>,
+    /// Fraction of frames that achieved sync, 0–1.
+    pub sync_rate: Option<f64>,
+    pub throughput_bps: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Baseline {
+    host: String,
+    recorded: String,
+    kernel: Option<String>,
+    figures: Figures,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Check {
+    metric: &'static str,
+    baseline: f64,
+    current: f64,
+    /// The worst value that still passes.
+    limit: f64,
+    pass: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Outcome {
+    host: String,
+    kernel: Option<String>,
+    figures: Figures,
+    /// `created`, `updated`, `compared`, or `unparsed` when no figure was
+    /// found in the output.
+    baseline_status: &'static str,
+    baseline_recorded: Option<String>,
+    baseline_kernel: Option<String>,
+    checks: Vec<Check>,
+    pass: bool,
+    notes: Vec<String>,
+    output: String,
+}
+
+/// Runs `dcf_loopback` and reports against the baseline. The caller has
+/// already checked the confirmation.
+pub fn run(update_baseline: bool) -> String {
+    let output = match runner::run(ASPECT, "dcf_loopback", &[], HEAVY_TIMEOUT) {
+        Ok(o) => o,
+        Err(e) => return format!("[error] {e}"),
+    };
+    let host = read_trimmed("/proc/sys/kernel/hostname").unwrap_or_else(|| "localhost".into());
+    let kernel = read_trimmed("/proc/sys/kernel/osrelease");
+    let path = baseline_path(&host);
+    let previous: Option<Baseline> =
+        std::fs::read_to_string(&path).ok().and_then(|s| serde_json::from_str(&s).ok());
+    let figures = parse(&output);
+    let mut outcome = evaluate(host, kernel, figures, previous.as_ref(), update_baseline, output);
+    if matches!(outcome.baseline_status, "created" | "updated") {
+        let baseline = Baseline {
+            host: outcome.host.clone(),
+            recorded: OffsetDateTime::now_utc().format(&Rfc3339).unwrap_or_default(),
+            kernel: outcome.kernel.clone(),
+            figures: outcome.figures.clone(),
+        };
+        if let Err(e) = save(&path, &baseline) {
+            outcome.notes.push(format!("baseline not saved to {}: {e}", path.display()));
+        } else {
+            outcome.baseline_recorded = Some(baseline.recorded);
+            outcome.baseline_kernel = baseline.kernel;
+        }
+    }
+    report::json(&outcome)
+}
+
+fn read_trimmed(path: &str) -> Option<String> {
+    std::fs::read_to_string(path).ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+fn baseline_path(host: &str) -> PathBuf {
+    let host: String = host.chars().filter(|c| c.is_ascii_alphanumeric() || "-_.".contains(*c)).collect();
+    audit::state_dir(ASPECT).join(format!("loopback-baseline-{host}.json"))
+}
+
+fn save(path: &PathBuf, b: &Baseline) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(b).map_err(std::io::Error::other)?)
+}
+
+/// The first number in `s`, accepting exponents (`1.2e-5`).
+fn number(s: &str) -> Option<f64> {
+    let start = s.find(|c: char| c.is_ascii_digit() || c == '-' || c == '.')?;
+    let token = s[start..].split(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c))).next()?;
+    // Longest prefix that parses: drops a trailing `e` from `5e` or `-` from `5-`.
+    (1..=token.len()).rev().find_map(|n| token[..n].parse().ok())
+}
+
+/// `key: value` / `key = value` lines; the key decides the figure.
+pub fn parse(output: &str) -> Figures {
+    let mut f = Figures::default();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':').or_else(|| line.split_once('=')) else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        let words: Vec<&str> = key.split(|c: char| !c.is_ascii_alphanumeric()).collect();
+        if f.ber.is_none() && words.contains(&"ber") {
+            f.ber = number(value);
+        } else if f.snr_db.is_none() && words.contains(&"snr") {
+            f.snr_db = number(value);
+        } else if f.sync_rate.is_none() && words.iter().any(|w| w.starts_with("sync")) {
+            f.sync_rate = sync_rate(value);
+        } else if f.throughput_bps.is_none() && words.iter().any(|w| *w == "throughput" || *w == "bitrate") {
+            f.throughput_bps = throughput(value);
+        }
+    }
+    f
+}
+
+/// `98/100`, `98%`, `0.98`, or a bare verdict.
+fn sync_rate(value: &str) -> Option<f64> {
+    let v = value.to_ascii_lowercase();
+    if let Some((a, b)) = v.split_once('/') {
+        let (a, b) = (number(a)?, number(b)?);
+        return (b > 0.0).then(|| a / b);
+    }
+    if v.contains('%') {
+        return number(&v).map(|n| n / 100.0);
+    }
+    match v.split_whitespace().next()? {
+        "ok" | "yes" | "true" | "locked" | "success" | "pass" => Some(1.0),
+        "fail" | "failed" | "no" | "false" | "lost" => Some(0.0),
+        _ => number(&v).filter(|n| (0.0..=1.0).contains(n)),
+    }
+}
+
+/// Bits per second from `2.4 kbps`, `2.4 kb/s`, `1.2 Mbit/s`, `300 B/s`,
+/// `2.4 kB/s`, … The SI prefix and the unit are read separately, and case
+/// decides the unit: `b`/`bit` is bits, `B`/`byte` is bytes (×8).
+fn throughput(value: &str) -> Option<f64> {
+    let n = number(value)?;
+    let start = value.find(|c: char| c.is_ascii_digit() || c == '.')?;
+    let unit = value[start..]
+        .trim_start_matches(|c: char| c.is_ascii_digit() || "+-.eE".contains(c))
+        .split_whitespace()
+        .next()
+        .unwrap_or("");
+    let (prefix, unit) = match unit.chars().next() {
+        Some('k' | 'K') => (1e3, &unit[1..]),
+        Some('M' | 'm') => (1e6, &unit[1..]),
+        Some('G' | 'g') => (1e9, &unit[1..]),
+        _ => (1.0, unit),
+    };
+    let per_unit = if unit.starts_with('B') || unit.to_ascii_lowercase().starts_with("byte") { 8.0 } else { 1.0 };
+    Some(n * prefix * per_unit)
+}
+
+fn evaluate(
+    host: String,
+    kernel: Option<String>,
+    figures: Figures,
+    baseline: Option<&Baseline>,
+    update: bool,
+    output: String,
+) -> Outcome {
+    let mut notes = Vec::new();
+    let unparsed = figures == Figures::default();
+    let baseline_status = match (unparsed, baseline, update) {
+        (true, _, _) => "unparsed",
+        (false, None, _) => "created",
+        (false, Some(_), true) => "updated",
+        (false, Some(_), false) => "compared",
+    };
+    let mut checks = Vec::new();
+    if let (Some(b), "compared") = (baseline, baseline_status) {
+        if b.kernel != kernel {
+            notes.push(format!(
+                "kernel changed since the baseline: {} -> {}",
+                b.kernel.as_deref().unwrap_or("?"),
+                kernel.as_deref().unwrap_or("?")
+            ));
+        }
+        let base = &b.figures;
+        let mut check = |metric, baseline: Option<f64>, current: Option<f64>, limit: &dyn Fn(f64) -> f64, higher_is_worse| {
+            if let (Some(baseline), Some(current)) = (baseline, current) {
+                let limit = limit(baseline);
+                let pass = if higher_is_worse { current <= limit } else { current >= limit };
+                checks.push(Check { metric, baseline, current, limit, pass });
+            } else if baseline.is_some() {
+                notes.push(format!("{metric} is in the baseline but not in this run's output"));
+            }
+        };
+        check("ber", base.ber, figures.ber, &|b| (b * BER_FACTOR).max(BER_FLOOR), true);
+        check("snr_db", base.snr_db, figures.snr_db, &|b| b - SNR_DROP_DB, false);
+        check("sync_rate", base.sync_rate, figures.sync_rate, &|b| b - SYNC_DROP, false);
+        check("throughput_bps", base.throughput_bps, figures.throughput_bps, &|b| b * (1.0 - THROUGHPUT_DROP), false);
+    }
+    if unparsed {
+        notes.push("no BER/SNR/sync/throughput figure found in the output; baseline untouched".into());
+    }
+    Outcome {
+        host,
+        kernel,
+        figures,
+        baseline_status,
+        baseline_recorded: baseline.filter(|_| baseline_status == "compared").map(|b| b.recorded.clone()),
+        baseline_kernel: baseline.filter(|_| baseline_status == "compared").and_then(|b| b.kernel.clone()),
+        pass: !unparsed && checks.iter().all(|c| c.pass),
+        checks,
+        notes,
+        output,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUT: &str = "\
+dcf_loopback: 200 frames, QPSK
+BER: 1.5e-5
+SNR = 18.2 dB
+Sync success: 196/200
+Throughput: 2.4 kbps
+";
+
+    #[test]
+    fn parses_figures() {
+        let f = parse(OUT);
+        assert_eq!(f.ber, Some(1.5e-5));
+        assert_eq!(f.snr_db, Some(18.2));
+        assert_eq!(f.sync_rate, Some(0.98));
+        assert_eq!(f.throughput_bps, Some(2400.0));
+        assert_eq!(parse("sync: locked\nbitrate = 300 B/s").sync_rate, Some(1.0));
+        assert_eq!(parse("bitrate = 300 B/s").throughput_bps, Some(2400.0));
+        for (text, bps) in [
+            ("2.4 kb/s", 2_400.0),
+            ("2.4 kB/s", 19_200.0),
+            ("2.4kbit/s", 2_400.0),
+            ("1.5 Mbit/s", 1.5e6),
+            ("2 MBps", 16e6),
+            ("1 kbyte/s", 8_000.0),
+            ("9600", 9_600.0),
+        ] {
+            assert_eq!(throughput(text), Some(bps), "{text}");
+        }
+        assert_eq!(parse("frames sent: 12\n"), Figures::default());
+    }
+
+    #[test]
+    fn compares_against_baseline() {
+        let base = Baseline {
+            host: "h".into(),
+            recorded: "2026-01-01T00:00:00Z".into(),
+            kernel: Some("6.17.1".into()),
+            figures: Figures { ber: Some(1e-5), snr_db: Some(20.0), sync_rate: Some(1.0), throughput_bps: Some(2400.0) },
+        };
+        let o = evaluate("h".into(), Some("6.18.0".into()), parse(OUT), Some(&base), false, String::new());
+        assert_eq!(o.baseline_status, "compared");
+        let verdicts: Vec<(&str, bool)> = o.checks.iter().map(|c| (c.metric, c.pass)).collect();
+        assert_eq!(verdicts, [("ber", true), ("snr_db", true), ("sync_rate", true), ("throughput_bps", true)]);
+        assert!(o.pass);
+        assert!(o.notes[0].contains("6.17.1 -> 6.18.0"));
+
+        let worse = parse("BER: 5e-5\nSNR: 15 dB\nsync: 180/200\nthroughput: 2000 bps\n");
+        let o = evaluate("h".into(), Some("6.17.1".into()), worse, Some(&base), false, String::new());
+        assert!(o.checks.iter().all(|c| !c.pass));
+        assert!(!o.pass);
+
+        let first = evaluate("h".into(), None, parse(OUT), None, false, String::new());
+        assert_eq!((first.baseline_status, first.pass), ("created", true));
+        let garbage = evaluate("h".into(), None, Figures::default(), Some(&base), true, String::new());
+        assert_eq!((garbage.baseline_status, garbage.pass), ("unparsed", false));
+    }
+}
@@ -12,19 +12,23 @@
 //! The transmit-side HydraModem tools (`frame_tx`, `tx_campaign`, `sense_node`,
 //! the SSTV senders) are deliberately absent from this aspect's allowlist.
 
-use std::path::{Path, PathBuf};
+mod capture;
+mod config;
+mod history;
+mod loopback;
+mod sdk;
+mod topology;
+
+use std::path::PathBuf;
 
 use oligarchy_mcp_core::audit;
-use oligarchy_mcp_core::runner::{self, HEAVY_TIMEOUT, QUICK_TIMEOUT};
+use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
 use oligarchy_mcp_core::runner_mcp;
 
 use rmcp::{ServerHandler, model::{ServerCapabilities, ServerInfo}, tool};
 
 const ASPECT: &str = "hydramesh";
 
-/// Cap for `node_config` reads, matching the `read_module` convention.
-const MAX_CONFIG_BYTES: usize = 64 * 1024;
-
 /// The HydraModem CLI toolbox, as installed by the `hydramodem-tools` package.
 /// Probed for presence only — only `dcf_loopback` is ever executed, and only
 /// behind the confirmation guard.
@@ -40,9 +44,10 @@ const MODEM_TOOLS: &[&str] = &[
     "snake_loopback",
 ];
 
-/// Resolves the DCF node config written by `modules/hydramesh.nix`, split into
-/// (parent dir, file name) so the read goes through the sandbox's
-/// resolve-and-confine check rather than a bare `read_to_string`.
+/// Resolves the DCF node config written by `modules/hydramesh.nix`.
+/// [`config::report`] splits it into (parent dir, file name) so the read goes
+/// through the sandbox's resolve-and-confine check rather than a bare
+/// `read_to_string`.
 fn node_config_path() -> PathBuf {
     std::env::var("OLIGARCHY_HYDRAMESH_CONFIG")
         .map(PathBuf::from)
@@ -68,11 +73,16 @@ impl Server {
             .unwrap_or_else(|e| format!("[error] {e}"))
     }
 
-    #[tool(description = "HydraMesh node metrics (`hydramesh metrics`).")]
+    #[tool(description = "HydraMesh node metrics (`hydramesh metrics`) as JSON: numeric values under dotted keys, anything else listed separately.")]
     fn hydramesh_metrics(&self) -> String {
         audit::tool(ASPECT, "hydramesh_metrics", "");
-        runner::run(ASPECT, "hydramesh", &["metrics"], QUICK_TIMEOUT)
-            .unwrap_or_else(|e| format!("[error] {e}"))
+        sdk::metrics()
+    }
+
+    #[tool(description = "Mesh metrics history from the opt-in recorder (custom.mcpServers.hydrameshRecorder): min/avg/max per `source.metric` over a window (default 24h, max 31d) and per bucket (default 1h), plus recorder errors. metric filters by substring.")]
+    fn mesh_history(&self, #[tool(aggr)] q: history::HistoryQuery) -> String {
+        audit::tool(ASPECT, "mesh_history", &q.summary());
+        history::run(&q)
     }
 
     #[tool(description = "HydraMesh / DCF SDK version. Falls back to the Rust SDK if the D-LISP CLI is absent.")]
@@ -83,18 +93,22 @@ impl Server {
             .unwrap_or_else(|e| format!("[error] {e}"))
     }
 
-    #[tool(description = "DCF node status from the Rust SDK (`dcf status`, JSON).")]
+    #[tool(description = "DCF node status from the Rust SDK (`dcf status`), parsed: node id, mode, version, uptime, peer count; unrecognised fields kept under `extra`.")]
     fn dcf_node_status(&self) -> String {
         audit::tool(ASPECT, "dcf_node_status", "");
-        runner::run(ASPECT, "dcf", &["status"], QUICK_TIMEOUT)
-            .unwrap_or_else(|e| format!("[error] {e}"))
+        sdk::status()
     }
 
-    #[tool(description = "DCF peer table from the Rust SDK (`dcf list-peers`, JSON).")]
-    fn dcf_node_peers(&self) -> String {
-        audit::tool(ASPECT, "dcf_node_peers", "");
-        runner::run(ASPECT, "dcf", &["list-peers"], QUICK_TIMEOUT)
-            .unwrap_or_else(|e| format!("[error] {e}"))
+    #[tool(description = "DCF peer table from the Rust SDK (`dcf list-peers`), parsed: id, address, RTT, last seen, role per peer, plus stale peers (not seen for stale_secs, default 300), RTT percentiles and peers joined/left since the previous call.")]
+    fn dcf_node_peers(&self, #[tool(aggr)] q: sdk::PeerQuery) -> String {
+        audit::tool(ASPECT, "dcf_node_peers", &q.stale_secs.map(|s| s.to_string()).unwrap_or_default());
+        sdk::peers(&q)
+    }
+
+    #[tool(description = "Mesh topology graph merging `dcf list-peers` and `hydramesh list-peers`: nodes (addresses, roles), links with which SDK saw them, RTT and quality (good/fair/poor/stale/unknown). format: json (default), dot (Graphviz) or both. Sorted for diffing.")]
+    fn mesh_topology(&self, #[tool(aggr)] q: topology::TopologyQuery) -> String {
+        audit::tool(ASPECT, "mesh_topology", q.format.as_deref().unwrap_or(""));
+        topology::run(&q)
     }
 
     #[tool(description = "systemd/container status of the DCF community node (read-only).")]
@@ -117,25 +131,17 @@ impl Server {
         .unwrap_or_else(|e| format!("[error] {e}"))
     }
 
-    #[tool(description = "DCF node wire configuration (bind ports, discovery mode). Sandboxed file read, no exec.")]
+    #[tool(description = "DCF node wire configuration (/etc/hydramesh/dcf_config.toml) parsed into a typed model, with lint findings (bind addresses, port collisions with known endpoints, transport mismatches, discovery-mode sanity) and a diff against the defaults modules/hydramesh.nix generates. Sandboxed file reads, no exec.")]
     fn node_config(&self) -> String {
         let path = node_config_path();
         audit::tool(ASPECT, "node_config", &path.display().to_string());
+        config::report(&path)
+    }
 
-        let (base, name) = match (path.parent(), path.file_name()) {
-            (Some(b), Some(n)) => (b.to_path_buf(), n.to_string_lossy().into_owned()),
-            _ => return format!("[error] malformed config path: {}", path.display()),
-        };
-        if !path.is_file() {
-            return format!(
-                "[absent] {} — is custom.hydramesh.enable set?",
-                path.display()
-            );
-        }
-        match oligarchy_mcp_core::sandbox::read_file(Path::new(&base), &name, MAX_CONFIG_BYTES) {
-            Ok(s) => format!("# {}\n{s}", path.display()),
-            Err(e) => format!("[error] {e}"),
-        }
+    #[tool(description = "Decode DCF frames from a field-test capture (raw bytes, hex dump, or a WAV whose data chunk holds demodulated bytes) in the captures directory ($OLIGARCHY_HYDRAMESH_CAPTURES, else captures/ in the aspect state dir): type, seq, src/dst, payload, timestamp and CRC validity per frame, plus per-source sequence gaps, duplicates and reordering. max_frames limits the listing (default 200). Sandboxed read; no audio, no exec, no transmit.")]
+    fn decode_capture(&self, #[tool(aggr)] q: capture::CaptureQuery) -> String {
+        audit::tool(ASPECT, "decode_capture", &q.file);
+        capture::decode(&q)
     }
 
     #[tool(description = "Which HydraModem CLI tools are present on PATH. Presence probe only — executes nothing.")]
@@ -163,16 +169,20 @@ impl Server {
         out
     }
 
-    #[tool(description = "Run the HydraModem local DSP loopback self-test (`dcf_loopback`). Requires confirm=\"yes\"; no network, no transmit.")]
-    fn hydramodem_loopback(&self, #[tool(param)] confirm: String) -> String {
-        audit::tool(ASPECT, "hydramodem_loopback", &confirm);
+    #[tool(description = "Run the HydraModem local DSP loopback self-test (`dcf_loopback`) and parse BER, SNR, sync success and throughput. The first run on a host is saved as its baseline; later runs pass/fail against it (BER x2, SNR -3 dB, sync -5 points, throughput -10%). update_baseline=true replaces it. Requires confirm=\"yes\"; no network, no transmit.")]
+    fn hydramodem_loopback(
+        &self,
+        #[tool(param)] confirm: String,
+        #[tool(param)] update_baseline: Option<bool>,
+    ) -> String {
+        let update = update_baseline.unwrap_or(false);
+        audit::tool(ASPECT, "hydramodem_loopback", &format!("confirm={confirm} update_baseline={update}"));
         if confirm != "yes" {
             return "[denied] hydramodem_loopback runs the modem DSP self-test; \
                     pass confirm=\"yes\" to proceed"
                 .into();
         }
-        runner::run(ASPECT, "dcf_loopback", &[], HEAVY_TIMEOUT)
-            .unwrap_or_else(|e| format!("[error] {e}"))
+        loopback::run(update)
     }
 }
 
@@ -193,9 +203,30 @@ impl ServerHandler for Server {
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    // CLI: `oligarchy-hydramesh-mcp --record [--retention-days N]` takes one
+    // metrics sample for `mesh_history` and exits (run by the opt-in
+    // recorder timer). With no args it serves the MCP server over stdio.
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|a| a == "--record") {
+        let retention = args
+            .iter()
+            .position(|a| a == "--retention-days")
+            .and_then(|i| args.get(i + 1))
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(history::DEFAULT_RETENTION_DAYS);
+        match history::record(retention) {
+            Ok(summary) => println!("{summary}"),
+            Err(e) => {
+                eprintln!("[error] {e}");
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
     tracing_subscriber::fmt().init();
     tracing::info!(aspect = ASPECT, "starting");
-    runner_mcp::serve(Server::default()).await
+    runner_mcp::serve(Server).await
 }
 
 #[cfg(test)]
@@ -209,7 +240,7 @@ mod tests {
 
     #[test]
     fn loopback_requires_confirmation() {
-        let s = Server::default().hydramodem_loopback("no".into());
+        let s = Server.hydramodem_loopback("no".into(), None);
         assert!(s.starts_with("[denied]"), "unconfirmed loopback must be denied");
     }
 
@@ -0,0 +1,357 @@
+//! Typed views of the SDK CLIs' output: `dcf status`, `dcf list-peers` and
+//! `hydramesh metrics`.
+//!
+//! The SDKs' JSON is not versioned, so the models are lenient: every field is
+//! optional, common spellings are accepted as aliases (`peer_id`/`node_id`,
+//! `addr`/`endpoint`, `rtt`/`latency_ms`), and anything unrecognised is kept
+//! under `extra` rather than dropped. `last_seen` may be unix seconds or an
+//! RFC 3339 timestamp.
+//!
+//! Peer churn is measured against the peer set seen by the previous call,
+//! kept in `peers-last.json` under the aspect's state dir.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
+use oligarchy_mcp_core::{audit, report};
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::ASPECT;
+
+/// A peer not heard from for this long is reported as stale.
+const DEFAULT_STALE_SECS: u64 = 300;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct NodeStatus {
+    #[serde(alias = "id", alias = "node")]
+    pub node_id: Option<String>,
+    pub mode: Option<String>,
+    pub version: Option<String>,
+    #[serde(alias = "uptime")]
+    pub uptime_secs: Option<f64>,
+    #[serde(alias = "peers", default, deserialize_with = "count_or_list")]
+    pub peer_count: Option<u64>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Peer {
+    #[serde(alias = "peer_id", alias = "node_id")]
+    pub id: String,
+    #[serde(alias = "addr", alias = "endpoint")]
+    pub address: Option<String>,
+    #[serde(alias = "rtt", alias = "latency_ms")]
+    pub rtt_ms: Option<f64>,
+    pub last_seen: Option<LastSeen>,
+    pub role: Option<String>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum LastSeen {
+    Unix(f64),
+    Text(String),
+}
+
+impl LastSeen {
+    pub fn unix(&self) -> Option<i64> {
+        match self {
+            LastSeen::Unix(t) => Some(*t as i64),
+            LastSeen::Text(s) => s
+                .parse::<f64>()
+                .ok()
+                .map(|t| t as i64)
+                .or_else(|| OffsetDateTime::parse(s, &Rfc3339).ok().map(OffsetDateTime::unix_timestamp)),
+        }
+    }
+}
+
+/// `peers` in `dcf status` is a count on some builds and the peer list itself
+/// on others; either way it is read as a count.
+fn count_or_list<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum CountOrList {
+        Count(u64),
+        List(Vec<Value>),
+    }
+    Ok(Option::<CountOrList>::deserialize(d)?.map(|c| match c {
+        CountOrList::Count(n) => n,
+        CountOrList::List(l) => l.len() as u64,
+    }))
+}
+
+/// `list-peers` prints either a bare array or an object wrapping one.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PeerList {
+    Bare(Vec<Peer>),
+    Wrapped { peers: Vec<Peer> },
+}
+
+pub fn parse_status(text: &str) -> Result<NodeStatus, String> {
+    serde_json::from_str(text).map_err(|e| format!("parsing dcf status JSON: {e}"))
+}
+
+pub fn parse_peers(text: &str) -> Result<Vec<Peer>, String> {
+    match serde_json::from_str(text) {
+        Ok(PeerList::Bare(p) | PeerList::Wrapped { peers: p }) => Ok(p),
+        Err(e) => Err(format!("parsing peer list JSON: {e}")),
+    }
+}
+
+#[derive(Debug, Default, Deserialize, schemars::JsonSchema)]
+pub struct PeerQuery {
+    /// Seconds since `last_seen` after which a peer counts as stale. Default 300.
+    pub stale_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct PeerView {
+    #[serde(flatten)]
+    peer: Peer,
+    age_secs: Option<i64>,
+    stale: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct RttStats {
+    samples: usize,
+    min: f64,
+    p50: f64,
+    p90: f64,
+    p99: f64,
+    max: f64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Churn {
+    /// Unix time of the previous call; None on the first call.
+    since: Option<i64>,
+    joined: Vec<String>,
+    left: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct PeerSnapshot {
+    taken: i64,
+    ids: BTreeSet<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct PeersReport {
+    count: usize,
+    stale_secs: u64,
+    stale: Vec<String>,
+    rtt_ms: Option<RttStats>,
+    churn: Churn,
+    peers: Vec<PeerView>,
+    unavailable: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Metrics {
+    /// Numeric metrics; nested JSON objects are flattened to dotted keys.
+    pub values: BTreeMap<String, f64>,
+    /// Non-numeric entries, as `key: value`.
+    pub other: Vec<String>,
+}
+
+pub fn status() -> String {
+    match runner::run(ASPECT, "dcf", &["status"], QUICK_TIMEOUT) {
+        Ok(raw) => match parse_status(&raw) {
+            Ok(s) => report::json(&s),
+            Err(e) => format!("[error] {e}\n{raw}"),
+        },
+        Err(e) => format!("[error] {e}"),
+    }
+}
+
+pub fn peers(q: &PeerQuery) -> String {
+    let raw = match runner::run(ASPECT, "dcf", &["list-peers"], QUICK_TIMEOUT) {
+        Ok(r) => r,
+        Err(e) => return format!("[error] {e}"),
+    };
+    let peers = match parse_peers(&raw) {
+        Ok(p) => p,
+        Err(e) => return format!("[error] {e}\n{raw}"),
+    };
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let path = snapshot_path();
+    let mut unavailable = Vec::new();
+    let previous = std::fs::read_to_string(&path).ok().and_then(|s| serde_json::from_str(&s).ok());
+    let report = peers_report(peers, q.stale_secs.unwrap_or(DEFAULT_STALE_SECS), previous.as_ref(), now);
+    let snapshot = PeerSnapshot { taken: now, ids: report.peers.iter().map(|p| p.peer.id.clone()).collect() };
+    if let Err(e) = save_snapshot(&path, &snapshot) {
+        unavailable.push(format!("{}: {e} (churn will not advance)", path.display()));
+    }
+    report::json(&PeersReport { unavailable, ..report })
+}
+
+pub fn metrics() -> String {
+    match runner::run(ASPECT, "hydramesh", &["metrics"], QUICK_TIMEOUT) {
+        Ok(raw) => report::json(&parse_metrics(&raw)),
+        Err(e) => format!("[error] {e}"),
+    }
+}
+
+fn snapshot_path() -> PathBuf {
+    audit::state_dir(ASPECT).join("peers-last.json")
+}
+
+fn save_snapshot(path: &PathBuf, snapshot: &PeerSnapshot) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let text = serde_json::to_string(snapshot).map_err(std::io::Error::other)?;
+    std::fs::write(path, text)
+}
+
+fn peers_report(peers: Vec<Peer>, stale_secs: u64, previous: Option<&PeerSnapshot>, now: i64) -> PeersReport {
+    let views: Vec<PeerView> = peers
+        .into_iter()
+        .map(|peer| {
+            let age_secs = peer.last_seen.as_ref().and_then(LastSeen::unix).map(|t| now - t);
+            let stale = age_secs.is_some_and(|a| a >= stale_secs as i64);
+            PeerView { peer, age_secs, stale }
+        })
+        .collect();
+    let ids: BTreeSet<String> = views.iter().map(|v| v.peer.id.clone()).collect();
+    let churn = match previous {
+        None => Churn { since: None, joined: Vec::new(), left: Vec::new() },
+        Some(prev) => Churn {
+            since: Some(prev.taken),
+            joined: ids.difference(&prev.ids).cloned().collect(),
+            left: prev.ids.difference(&ids).cloned().collect(),
+        },
+    };
+    PeersReport {
+        count: views.len(),
+        stale_secs,
+        stale: views.iter().filter(|v| v.stale).map(|v| v.peer.id.clone()).collect(),
+        rtt_ms: rtt_stats(views.iter().filter_map(|v| v.peer.rtt_ms).collect()),
+        churn,
+        peers: views,
+        unavailable: Vec::new(),
+    }
+}
+
+/// Nearest-rank percentiles.
+fn rtt_stats(mut samples: Vec<f64>) -> Option<RttStats> {
+    samples.retain(|s| s.is_finite());
+    if samples.is_empty() {
+        return None;
+    }
+    samples.sort_by(f64::total_cmp);
+    let pct = |p: f64| samples[((p / 100.0 * samples.len() as f64).ceil() as usize).clamp(1, samples.len()) - 1];
+    Some(RttStats {
+        samples: samples.len(),
+        min: samples[0],
+        p50: pct(50.0),
+        p90: pct(90.0),
+        p99: pct(99.0),
+        max: samples[samples.len() - 1],
+    })
+}
+
+/// `hydramesh metrics` prints JSON or `key: value` / `key = value` lines.
+pub fn parse_metrics(text: &str) -> Metrics {
+    let mut m = Metrics::default();
+    if let Ok(v @ Value::Object(_)) = serde_json::from_str::<Value>(text) {
+        flatten("", &v, &mut m);
+        return m;
+    }
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        let Some((k, v)) = line.split_once(':').or_else(|| line.split_once('=')) else {
+            continue;
+        };
+        let (k, v) = (k.trim(), v.trim());
+        match v.split_whitespace().next().and_then(|n| n.parse::<f64>().ok()) {
+            Some(n) => {
+                m.values.insert(k.to_string(), n);
+            }
+            None => m.other.push(format!("{k}: {v}")),
+        }
+    }
+    m
+}
+
+fn flatten(prefix: &str, v: &Value, m: &mut Metrics) {
+    match v {
+        Value::Object(map) => {
+            for (k, v) in map {
+                let key = if prefix.is_empty() { k.clone() } else { format!("{prefix}.{k}") };
+                flatten(&key, v, m);
+            }
+        }
+        Value::Number(n) => {
+            if let Some(n) = n.as_f64() {
+                m.values.insert(prefix.to_string(), n);
+            }
+        }
+        Value::Bool(b) => {
+            m.values.insert(prefix.to_string(), if *b { 1.0 } else { 0.0 });
+        }
+        other => m.other.push(format!("{prefix}: {other}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEERS: &str = r#"{"peers": [
+        {"peer_id": "a1", "addr": "10.0.0.2:7777", "rtt": 12.5, "last_seen": 1000, "role": "relay"},
+        {"id": "b2", "address": "10.0.0.3:7777", "rtt_ms": 40, "last_seen": "1970-01-01T00:10:00Z", "hops": 2},
+        {"node_id": "c3", "endpoint": "10.0.0.4:7777"}
+    ]}"#;
+
+    #[test]
+    fn parses_status_and_peer_aliases() {
+        let s = parse_status(r#"{"id": "n0", "mode": "community", "uptime": 42, "peers": 3, "region": "lab"}"#).unwrap();
+        assert_eq!((s.node_id.as_deref(), s.peer_count), (Some("n0"), Some(3)));
+        assert_eq!(s.extra["region"], "lab");
+        let s = parse_status(r#"{"id": "n0", "peers": [{"id": "a1"}, {"id": "b2"}]}"#).unwrap();
+        assert_eq!(s.peer_count, Some(2));
+        assert_eq!(parse_status(r#"{"id": "n0"}"#).unwrap().peer_count, None);
+        let peers = parse_peers(PEERS).unwrap();
+        assert_eq!(peers.len(), 3);
+        assert_eq!(peers[0].address.as_deref(), Some("10.0.0.2:7777"));
+        assert_eq!(peers[1].last_seen.as_ref().and_then(LastSeen::unix), Some(600));
+        assert_eq!(peers[1].extra["hops"], 2);
+        assert_eq!(parse_peers("[]").unwrap().len(), 0);
+        assert!(parse_peers("no peers").is_err());
+    }
+
+    #[test]
+    fn derives_stale_rtt_and_churn() {
+        let prev = PeerSnapshot { taken: 900, ids: ["a1", "z9"].into_iter().map(String::from).collect() };
+        let r = peers_report(parse_peers(PEERS).unwrap(), 300, Some(&prev), 1100);
+        assert_eq!(r.stale, ["b2"]);
+        assert_eq!(r.peers[0].age_secs, Some(100));
+        assert_eq!(r.rtt_ms, Some(RttStats { samples: 2, min: 12.5, p50: 12.5, p90: 40.0, p99: 40.0, max: 40.0 }));
+        assert_eq!(r.churn, Churn { since: Some(900), joined: vec!["b2".into(), "c3".into()], left: vec!["z9".into()] });
+        let first = peers_report(Vec::new(), 300, None, 0);
+        assert_eq!((first.churn.since, first.rtt_ms), (None, None));
+    }
+
+    #[test]
+    fn parses_metrics_json_and_text() {
+        let m = parse_metrics(r#"{"tx": {"frames": 10, "errors": 1}, "up": true, "mode": "p2p"}"#);
+        assert_eq!(m.values["tx.frames"], 10.0);
+        assert_eq!(m.values["up"], 1.0);
+        assert_eq!(m.other, ["mode: \"p2p\""]);
+        let m = parse_metrics("# node metrics\nframes_sent: 10\nrtt_avg = 12.5 ms\nstate: degraded\n");
+        assert_eq!(m.values["frames_sent"], 10.0);
+        assert_eq!(m.values["rtt_avg"], 12.5);
+        assert_eq!(m.other, ["state: degraded"]);
+    }
+}
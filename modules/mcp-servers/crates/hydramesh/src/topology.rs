@@ -0,0 +1,245 @@
+//! `mesh_topology`: one graph from both SDKs' peer tables.
+//!
+//! `dcf list-peers` (Rust SDK) and `hydramesh list-peers` (D-LISP SDK) each
+//! see the mesh from this node; peers are merged by id, and every link
+//! records which SDKs reported it. A peer naming a `via`/`next_hop` is linked
+//! from that hop instead of from the local node. Output is sorted, so two
+//! snapshots diff cleanly as JSON or DOT.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use oligarchy_mcp_core::report;
+use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::sdk::{self, LastSeen, Peer};
+use crate::ASPECT;
+
+/// The local node's id when `dcf status` does not name it.
+const SELF_ID: &str = "self";
+/// Links not heard from for this long are `stale` regardless of RTT.
+const STALE_SECS: i64 = 300;
+
+#[derive(Debug, Default, Deserialize, schemars::JsonSchema)]
+pub struct TopologyQuery {
+    /// `json` (default), `dot`, or `both`.
+    pub format: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Node {
+    id: String,
+    addresses: BTreeSet<String>,
+    roles: BTreeSet<String>,
+    local: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Link {
+    from: String,
+    to: String,
+    /// `dcf`, `hydramesh`.
+    seen_by: BTreeSet<&'static str>,
+    rtt_ms: Option<f64>,
+    age_secs: Option<i64>,
+    /// `good` (<50 ms), `fair` (<200 ms), `poor`, `stale` or `unknown`.
+    quality: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct Topology {
+    nodes: Vec<Node>,
+    links: Vec<Link>,
+    unavailable: Vec<String>,
+}
+
+pub fn run(q: &TopologyQuery) -> String {
+    let format = q.format.as_deref().unwrap_or("json");
+    if !["json", "dot", "both"].contains(&format) {
+        return format!("[denied] format must be json, dot or both, not {format:?}");
+    }
+    let mut unavailable = Vec::new();
+    let local = runner::run(ASPECT, "dcf", &["status"], QUICK_TIMEOUT)
+        .ok()
+        .and_then(|s| sdk::parse_status(&s).ok())
+        .and_then(|s| s.node_id)
+        .unwrap_or_else(|| SELF_ID.to_string());
+    let mut views = Vec::new();
+    for (source, prog) in [("dcf", "dcf"), ("hydramesh", "hydramesh")] {
+        match runner::run(ASPECT, prog, &["list-peers"], QUICK_TIMEOUT) {
+            Ok(raw) => views.push((source, parse_any(&raw))),
+            Err(e) => unavailable.push(format!("{prog} list-peers: {e}")),
+        }
+    }
+    if views.is_empty() {
+        return format!("[error] no peer table available: {}", unavailable.join("; "));
+    }
+    let topo = Topology { unavailable, ..merge(&local, views, OffsetDateTime::now_utc().unix_timestamp()) };
+    match format {
+        "dot" => dot(&topo),
+        "both" => format!("{}\n\n{}", report::json(&topo), dot(&topo)),
+        _ => report::json(&topo),
+    }
+}
+
+/// JSON as the Rust SDK prints it, else the D-LISP CLI's table: one peer per
+/// line, id first, then `key=value` fields or a bare `host:port`.
+fn parse_any(raw: &str) -> Vec<Peer> {
+    if let Ok(p) = sdk::parse_peers(raw) {
+        return p;
+    }
+    raw.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace();
+            let id = tokens.next()?;
+            // Header rows and banners.
+            if id.eq_ignore_ascii_case("id") || id.eq_ignore_ascii_case("peer") || id.starts_with('─') {
+                return None;
+            }
+            let mut fields = serde_json::Map::new();
+            fields.insert("id".into(), id.into());
+            for t in tokens {
+                match t.split_once('=') {
+                    Some((k, v)) => fields.insert(k.to_string(), v.trim_end_matches("ms").into()),
+                    None if t.contains(':') => fields.insert("address".into(), t.into()),
+                    None => None,
+                };
+            }
+            let rtt = ["rtt", "rtt_ms", "latency_ms"]
+                .iter()
+                .filter_map(|k| fields.remove(*k))
+                .find_map(|v| v.as_str()?.parse::<f64>().ok());
+            let mut peer: Peer = serde_json::from_value(serde_json::Value::Object(fields)).ok()?;
+            peer.rtt_ms = rtt;
+            Some(peer)
+        })
+        .collect()
+}
+
+fn quality(rtt_ms: Option<f64>, age_secs: Option<i64>) -> &'static str {
+    if age_secs.is_some_and(|a| a >= STALE_SECS) {
+        return "stale";
+    }
+    match rtt_ms {
+        None => "unknown",
+        Some(r) if r < 50.0 => "good",
+        Some(r) if r < 200.0 => "fair",
+        Some(_) => "poor",
+    }
+}
+
+fn merge(local: &str, views: Vec<(&'static str, Vec<Peer>)>, now: i64) -> Topology {
+    let mut nodes: BTreeMap<String, Node> = BTreeMap::new();
+    let mut links: BTreeMap<(String, String), Link> = BTreeMap::new();
+    let node = |nodes: &mut BTreeMap<String, Node>, id: &str| {
+        if !nodes.contains_key(id) {
+            let n = Node { id: id.to_string(), addresses: BTreeSet::new(), roles: BTreeSet::new(), local: id == local };
+            nodes.insert(id.to_string(), n);
+        }
+    };
+    node(&mut nodes, local);
+    for (source, peers) in views {
+        for p in peers {
+            node(&mut nodes, &p.id);
+            let n = nodes.get_mut(&p.id).expect("inserted above");
+            n.addresses.extend(p.address.clone());
+            n.roles.extend(p.role.clone());
+            let from = ["via", "next_hop"]
+                .iter()
+                .find_map(|k| p.extra.get(*k).and_then(|v| v.as_str()))
+                .unwrap_or(local)
+                .to_string();
+            node(&mut nodes, &from);
+            let age = p.last_seen.as_ref().and_then(LastSeen::unix).map(|t| now - t);
+            let link = links.entry((from.clone(), p.id.clone())).or_insert_with(|| Link {
+                from,
+                to: p.id.clone(),
+                seen_by: BTreeSet::new(),
+                rtt_ms: None,
+                age_secs: None,
+                quality: "unknown",
+            });
+            link.seen_by.insert(source);
+            // Both SDKs measure the same path: keep the better figures.
+            link.rtt_ms = match (link.rtt_ms, p.rtt_ms) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            link.age_secs = match (link.age_secs, age) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            link.quality = quality(link.rtt_ms, link.age_secs);
+        }
+    }
+    Topology { nodes: nodes.into_values().collect(), links: links.into_values().collect(), unavailable: Vec::new() }
+}
+
+/// Graphviz DOT: local node doubled, links labelled with RTT and styled by
+/// quality.
+fn dot(t: &Topology) -> String {
+    let esc = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let q = |s: &str| format!("\"{}\"", esc(s));
+    let mut out = String::from("digraph mesh {\n  rankdir=LR;\n  node [shape=box];\n");
+    for n in &t.nodes {
+        // `\n` is a line break inside a DOT label.
+        let lines: Vec<String> = std::iter::once(&n.id).chain(&n.addresses).map(|l| esc(l)).collect();
+        let shape = if n.local { ", peripheries=2" } else { "" };
+        out.push_str(&format!("  {} [label=\"{}\"{shape}];\n", q(&n.id), lines.join("\\n")));
+    }
+    for l in &t.links {
+        let style = match l.quality {
+            "good" => "color=darkgreen",
+            "fair" => "color=orange",
+            "poor" => "color=red",
+            "stale" => "color=gray, style=dashed",
+            _ => "color=black, style=dotted",
+        };
+        let label = l.rtt_ms.map(|r| format!("{r} ms")).unwrap_or_default();
+        out.push_str(&format!("  {} -> {} [label={}, {style}];\n", q(&l.from), q(&l.to), q(&label)));
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DCF: &str = r#"[
+        {"id": "a1", "address": "10.0.0.2:7777", "rtt_ms": 30, "last_seen": 990},
+        {"id": "b2", "address": "10.0.0.3:7777", "rtt_ms": 150, "last_seen": 100, "via": "a1"}
+    ]"#;
+    const LISP: &str = "ID  ADDRESS  RTT\na1 10.0.0.2:7777 rtt=20ms role=relay\nc3 10.0.0.9:7777\n";
+
+    #[test]
+    fn merges_both_views() {
+        let t = merge("n0", vec![("dcf", parse_any(DCF)), ("hydramesh", parse_any(LISP))], 1000);
+        let ids: Vec<&str> = t.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, ["a1", "b2", "c3", "n0"]);
+        assert!(t.nodes[3].local);
+        assert!(t.nodes[0].roles.contains("relay"));
+        let a1 = &t.links[1];
+        assert_eq!((a1.from.as_str(), a1.to.as_str()), ("n0", "a1"));
+        assert_eq!(a1.seen_by, BTreeSet::from(["dcf", "hydramesh"]));
+        assert_eq!((a1.rtt_ms, a1.quality), (Some(20.0), "good"));
+        let b2 = &t.links[0];
+        assert_eq!((b2.from.as_str(), b2.quality), ("a1", "stale"));
+        assert_eq!(t.links[2].quality, "unknown");
+    }
+
+    #[test]
+    fn renders_dot() {
+        let t = merge("n0", vec![("dcf", parse_any(DCF))], 1000);
+        let d = dot(&t);
+        assert!(d.starts_with("digraph mesh {"));
+        assert!(d.contains("\"n0\" [label=\"n0\", peripheries=2];"));
+        assert!(d.contains("\"n0\" -> \"a1\" [label=\"30 ms\", color=darkgreen];"));
+        assert!(d.contains("\"a1\" -> \"b2\""));
+        assert!(run(&TopologyQuery { format: Some("svg".into()) }).starts_with("[denied]"));
+    }
+}
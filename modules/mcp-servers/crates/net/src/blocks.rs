@@ -0,0 +1,415 @@
+//! `egress_blocks`: strict-egress drop history from the kernel log.
+//!
+//! The strict-egress chain logs through `log prefix "STRICT-EGRESS-…: "`, so
+//! every drop (or would-be drop in dry-run) is a netfilter line in the kernel
+//! log: `IN= OUT=wlan0 SRC=… DST=… … PROTO=TCP SPT=… DPT=443 …`. The owning
+//! process is not in that line; with `log flags skuid` the kernel appends
+//! `UID=`/`GID=`, which is as far as attribution goes — those are reported
+//! per uid, and left out when absent.
+//!
+//! "New since switch" compares destinations seen after the last activation
+//! (`/run/current-system`'s mtime) with those seen before it in the same
+//! window, so it is only meaningful when the window starts before the switch.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::IpAddr;
+use std::time::UNIX_EPOCH;
+
+use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
+use oligarchy_mcp_core::{users, validate, Error};
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::egress::{self, BLOCKED_PREFIX, WOULDBLOCK_PREFIX};
+use crate::ASPECT;
+
+const DEFAULT_SINCE: &str = "-7d";
+const DEFAULT_LIMIT: usize = 50;
+/// Cap on the most-recent records returned; aggregates cover every record.
+const MAX_LIMIT: usize = 500;
+/// Newest log lines read from the window (`--lines`); a kernel log line is
+/// ~250 bytes, so this bounds the read at about a megabyte.
+const MAX_RECORDS: usize = 5_000;
+const CURRENT_SYSTEM: &str = "/run/current-system";
+
+#[derive(Debug, Default, Deserialize, schemars::JsonSchema)]
+pub struct BlockQuery {
+    /// Window start in journalctl syntax (`-24h`, `yesterday`, `2025-10-01`).
+    /// Default `-7d`.
+    pub since: Option<String>,
+    /// Time-bucket width: `hour` (default) or `day`.
+    pub bucket: Option<String>,
+    /// How many of the most recent records to include (default 50, max 500).
+    pub limit: Option<usize>,
+}
+
+/// One parsed log line.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Block {
+    /// RFC 3339 UTC.
+    pub timestamp: String,
+    #[serde(skip)]
+    pub unix: i64,
+    /// `blocked` (enforcing) or `would-block` (dry-run).
+    pub mode: &'static str,
+    pub out: Option<String>,
+    pub src: Option<IpAddr>,
+    pub dst: IpAddr,
+    pub proto: Option<String>,
+    pub sport: Option<u16>,
+    pub dport: Option<u16>,
+    pub uid: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+struct Destination {
+    dst: IpAddr,
+    /// From the resolver cache, when the address was once allowed.
+    domain: Option<String>,
+    count: usize,
+    ports: Vec<String>,
+    uids: Vec<u32>,
+    first_seen: String,
+    last_seen: String,
+    new_since_switch: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Count {
+    key: String,
+    count: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Owner {
+    uid: Option<u32>,
+    user: Option<String>,
+    count: usize,
+    destinations: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct BlocksReport {
+    since: String,
+    records: usize,
+    /// True when the window held more than [`MAX_RECORDS`] lines and only the
+    /// newest were read; every aggregate then covers those only.
+    truncated: bool,
+    blocked: usize,
+    would_block: usize,
+    generation_switch: Option<String>,
+    /// Destinations first seen after the switch and never before it.
+    new_since_switch: Vec<IpAddr>,
+    caveats: Vec<String>,
+    by_destination: Vec<Destination>,
+    by_port: Vec<Count>,
+    by_owner: Vec<Owner>,
+    buckets: Vec<Count>,
+    recent: Vec<Block>,
+}
+
+impl BlockQuery {
+    pub fn summary(&self) -> String {
+        format!(
+            "since={} bucket={}",
+            self.since.as_deref().unwrap_or(DEFAULT_SINCE),
+            self.bucket.as_deref().unwrap_or("hour")
+        )
+    }
+}
+
+pub fn run(q: &BlockQuery) -> String {
+    let since = q.since.as_deref().unwrap_or(DEFAULT_SINCE);
+    if let Err(e) = validate::check_time(since) {
+        return format!("[denied] {e}");
+    }
+    let width = match q.bucket.as_deref().unwrap_or("hour") {
+        "hour" => 3_600,
+        "day" => 86_400,
+        other => return format!("[denied] bucket must be hour or day, not {other:?}"),
+    };
+    let limit = q.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+
+    // `-k` would pin the query to the current boot; `_TRANSPORT=kernel` spans
+    // boots. The grep runs in journald, so only matching lines come back, and
+    // `--lines` keeps the newest of them.
+    let since_arg = format!("--since={since}");
+    let lines_arg = format!("--lines={MAX_RECORDS}");
+    let args = [
+        "_TRANSPORT=kernel",
+        "--grep=STRICT-EGRESS-",
+        since_arg.as_str(),
+        lines_arg.as_str(),
+        "-o",
+        "short-unix",
+        "--no-pager",
+        "-q",
+    ];
+    let out = match log_text(runner::run_checked(ASPECT, "journalctl", &args, QUICK_TIMEOUT)) {
+        Ok(o) => o,
+        Err(e) => return format!("[error] {e}"),
+    };
+    let blocks = parse(&out);
+    let switch = last_switch();
+    let domains = egress::resolver_cache()
+        .map(|c| c.into_iter().map(|r| (r.ip, r.domain)).collect())
+        .unwrap_or_default();
+    let users = users::names();
+    let mut report = aggregate(since, blocks, switch, width, limit, &domains, &users);
+    mark_truncated(&mut report, out.lines().count());
+    oligarchy_mcp_core::report::json(&report)
+}
+
+/// The `--grep` finding nothing exits 1 with nothing on stderr: no drops in
+/// the window. Any other failing exit means the log was not read, and must not
+/// be reported as "nothing blocked".
+fn log_text(out: oligarchy_mcp_core::Result<String>) -> oligarchy_mcp_core::Result<String> {
+    match out {
+        Err(Error::Exit { stderr, .. }) if stderr.is_empty() => Ok(String::new()),
+        other => other,
+    }
+}
+
+/// journalctl stopped at `--lines`: older records in the window were not read.
+fn mark_truncated(report: &mut BlocksReport, lines: usize) {
+    if lines >= MAX_RECORDS {
+        report.truncated = true;
+        report.caveats.push(format!(
+            "more than {MAX_RECORDS} log lines in the window; only the newest were read, so counts and \
+             first_seen cover those only — narrow `since` for the full history"
+        ));
+    }
+}
+
+/// Parses `journalctl -o short-unix` output; lines without a strict-egress
+/// prefix or a `DST=` are skipped.
+pub fn parse(text: &str) -> Vec<Block> {
+    text.lines().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<Block> {
+    let (stamp, _) = line.split_once(' ')?;
+    let unix = stamp.split('.').next()?.parse::<i64>().ok()?;
+    let (mode, rest) = if let Some(i) = line.find(BLOCKED_PREFIX) {
+        ("blocked", &line[i + BLOCKED_PREFIX.len()..])
+    } else if let Some(i) = line.find(WOULDBLOCK_PREFIX) {
+        ("would-block", &line[i + WOULDBLOCK_PREFIX.len()..])
+    } else {
+        return None;
+    };
+    let fields: HashMap<&str, &str> = rest.split_whitespace().filter_map(|kv| kv.split_once('=')).collect();
+    let get = |k: &str| fields.get(k).copied().filter(|v| !v.is_empty());
+    Some(Block {
+        timestamp: rfc3339(unix),
+        unix,
+        mode,
+        out: get("OUT").map(str::to_string),
+        src: get("SRC").and_then(|v| v.parse().ok()),
+        dst: get("DST")?.parse().ok()?,
+        proto: get("PROTO").map(str::to_ascii_lowercase),
+        sport: get("SPT").and_then(|v| v.parse().ok()),
+        dport: get("DPT").and_then(|v| v.parse().ok()),
+        uid: get("UID").and_then(|v| v.parse().ok()),
+    })
+}
+
+/// Activation time of the running generation, as unix seconds.
+fn last_switch() -> Option<i64> {
+    let modified = std::fs::symlink_metadata(CURRENT_SYSTEM).ok()?.modified().ok()?;
+    i64::try_from(modified.duration_since(UNIX_EPOCH).ok()?.as_secs()).ok()
+}
+
+fn rfc3339(unix: i64) -> String {
+    OffsetDateTime::from_unix_timestamp(unix)
+        .ok()
+        .and_then(|t| t.format(&Rfc3339).ok())
+        .unwrap_or_default()
+}
+
+fn port_key(b: &Block) -> String {
+    match (&b.proto, b.dport) {
+        (Some(p), Some(d)) => format!("{p}/{d}"),
+        (Some(p), None) => p.clone(),
+        (None, Some(d)) => d.to_string(),
+        (None, None) => "?".into(),
+    }
+}
+
+fn counts(map: BTreeMap<String, usize>) -> Vec<Count> {
+    let mut v: Vec<Count> = map.into_iter().map(|(key, count)| Count { key, count }).collect();
+    v.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+    v
+}
+
+fn aggregate(
+    since: &str,
+    mut blocks: Vec<Block>,
+    switch: Option<i64>,
+    width: i64,
+    limit: usize,
+    domains: &HashMap<IpAddr, String>,
+    users: &HashMap<u32, String>,
+) -> BlocksReport {
+    blocks.sort_by_key(|b| b.unix);
+    let mut caveats = Vec::new();
+
+    let mut by_dst: BTreeMap<IpAddr, Vec<&Block>> = BTreeMap::new();
+    let mut by_port: BTreeMap<String, usize> = BTreeMap::new();
+    let mut by_owner: BTreeMap<Option<u32>, (usize, BTreeSet<IpAddr>)> = BTreeMap::new();
+    let mut buckets: BTreeMap<String, usize> = BTreeMap::new();
+    for b in &blocks {
+        by_dst.entry(b.dst).or_default().push(b);
+        *by_port.entry(port_key(b)).or_default() += 1;
+        let owner = by_owner.entry(b.uid).or_default();
+        owner.0 += 1;
+        owner.1.insert(b.dst);
+        *buckets.entry(rfc3339(b.unix - b.unix.rem_euclid(width))).or_default() += 1;
+    }
+
+    // Without a record from before the switch there is no baseline, and every
+    // destination would look new.
+    let baseline = match (switch, blocks.first()) {
+        (Some(s), Some(first)) if first.unix < s => Some(s),
+        (Some(_), Some(_)) => {
+            caveats.push("the window starts after the last generation switch; widen `since` to detect new destinations".into());
+            None
+        }
+        (None, _) => {
+            caveats.push(format!("cannot read the activation time of {CURRENT_SYSTEM}"));
+            None
+        }
+        _ => None,
+    };
+    if !blocks.is_empty() && blocks.iter().all(|b| b.uid.is_none()) {
+        caveats.push("no UID= in the log lines: add `flags skuid` to the strict-egress log rule to attribute blocks to a user".into());
+    }
+
+    let mut destinations: Vec<Destination> = by_dst
+        .into_iter()
+        .map(|(dst, hits)| {
+            let first = hits[0].unix;
+            let last = hits[hits.len() - 1].unix;
+            let ports: BTreeSet<String> = hits.iter().map(|b| port_key(b)).collect();
+            let uids: BTreeSet<u32> = hits.iter().filter_map(|b| b.uid).collect();
+            Destination {
+                dst,
+                domain: domains.get(&dst).cloned(),
+                count: hits.len(),
+                ports: ports.into_iter().collect(),
+                uids: uids.into_iter().collect(),
+                first_seen: rfc3339(first),
+                last_seen: rfc3339(last),
+                new_since_switch: baseline.is_some_and(|s| first >= s),
+            }
+        })
+        .collect();
+    destinations.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.dst.cmp(&b.dst)));
+
+    let mut owners: Vec<Owner> = by_owner
+        .into_iter()
+        .map(|(uid, (count, dsts))| Owner {
+            uid,
+            user: uid.and_then(|u| users.get(&u).cloned()),
+            count,
+            destinations: dsts.len(),
+        })
+        .collect();
+    owners.sort_by_key(|o| std::cmp::Reverse(o.count));
+
+    let blocked = blocks.iter().filter(|b| b.mode == "blocked").count();
+    let records = blocks.len();
+    BlocksReport {
+        since: since.to_string(),
+        records,
+        truncated: false,
+        blocked,
+        would_block: records - blocked,
+        generation_switch: switch.map(rfc3339),
+        new_since_switch: destinations.iter().filter(|d| d.new_since_switch).map(|d| d.dst).collect(),
+        caveats,
+        by_destination: destinations,
+        by_port: counts(by_port),
+        by_owner: owners,
+        buckets: buckets.into_iter().map(|(key, count)| Count { key, count }).collect(),
+        recent: blocks.into_iter().rev().take(limit).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = "\
+1760000000.120000 host kernel: STRICT-EGRESS-BLOCKED: IN= OUT=wlan0 SRC=192.168.1.5 DST=93.184.216.34 LEN=60 TOS=0x00 PREC=0x00 TTL=64 ID=1 DF PROTO=TCP SPT=51000 DPT=443 WINDOW=64240 RES=0x00 SYN URGP=0 UID=1000 GID=100
+1760003700.000000 host kernel: STRICT-EGRESS-BLOCKED: IN= OUT=wlan0 SRC=192.168.1.5 DST=93.184.216.34 LEN=60 PROTO=TCP SPT=51002 DPT=443 UID=1000 GID=100
+1760007400.500000 host kernel: STRICT-EGRESS-WOULDBLOCK: IN= OUT=wlan0 SRC=2001:db8::5 DST=2606:4700::1111 LEN=80 TC=0 HOPLIMIT=64 FLOWLBL=0 PROTO=UDP SPT=40000 DPT=853 LEN=40
+1760007500.000000 host kernel: audit: type=1325 something else
+1760007600.000000 host kernel: STRICT-EGRESS-BLOCKED: IN= OUT=wlan0 SRC=192.168.1.5 PROTO=ICMP TYPE=8 CODE=0
+";
+
+    #[test]
+    fn parses_v4_v6_and_skips_noise() {
+        let b = parse(LOG);
+        assert_eq!(b.len(), 3);
+        assert_eq!(b[0].mode, "blocked");
+        assert_eq!(b[0].dst, "93.184.216.34".parse::<IpAddr>().unwrap());
+        assert_eq!((b[0].dport, b[0].uid), (Some(443), Some(1000)));
+        assert_eq!(b[0].timestamp, "2025-10-09T08:53:20Z");
+        assert_eq!(b[2].mode, "would-block");
+        assert_eq!(b[2].proto.as_deref(), Some("udp"));
+        assert_eq!(b[2].uid, None);
+    }
+
+    #[test]
+    fn aggregates_and_flags_new_destinations() {
+        let domains = HashMap::from([("93.184.216.34".parse().unwrap(), "example.com".to_string())]);
+        let users = users::parse("root:x:0:0::/root:/bin/sh\nalice:x:1000:100::/home/alice:/bin/sh\n");
+        let r = aggregate("-7d", parse(LOG), Some(1_760_005_000), 3_600, 1, &domains, &users);
+        assert_eq!((r.records, r.blocked, r.would_block), (3, 2, 1));
+        assert_eq!(r.by_destination[0].count, 2);
+        assert_eq!(r.by_destination[0].domain.as_deref(), Some("example.com"));
+        assert!(!r.by_destination[0].new_since_switch);
+        assert_eq!(r.new_since_switch, vec!["2606:4700::1111".parse::<IpAddr>().unwrap()]);
+        assert_eq!(r.by_port[0].key, "tcp/443");
+        assert_eq!(r.by_owner[0].user.as_deref(), Some("alice"));
+        assert_eq!(r.buckets.len(), 3);
+        assert_eq!(r.buckets[0].key, "2025-10-09T08:00:00Z");
+        assert_eq!(r.recent.len(), 1);
+        assert_eq!(r.recent[0].mode, "would-block", "most recent first");
+        assert!(r.caveats.is_empty());
+    }
+
+    #[test]
+    fn reports_truncation_at_the_line_cap() {
+        let mut r = aggregate("-7d", parse(LOG), None, 3_600, 1, &HashMap::new(), &HashMap::new());
+        let caveats = r.caveats.len();
+        mark_truncated(&mut r, LOG.lines().count());
+        assert!(!r.truncated);
+        mark_truncated(&mut r, MAX_RECORDS);
+        assert!(r.truncated);
+        assert_eq!(r.caveats.len(), caveats + 1);
+    }
+
+    #[test]
+    fn journalctl_failure_is_not_an_empty_log() {
+        let denied = Error::Exit { code: 1, stderr: "Failed to open journal: Permission denied".into() };
+        assert!(log_text(Err(denied)).is_err());
+        assert_eq!(log_text(Err(Error::Exit { code: 1, stderr: String::new() })).unwrap(), "");
+    }
+
+    #[test]
+    fn no_baseline_when_window_starts_after_switch() {
+        let r = aggregate("-1h", parse(LOG), Some(1_700_000_000), 86_400, 10, &HashMap::new(), &HashMap::new());
+        assert!(r.new_since_switch.is_empty());
+        assert_eq!(r.caveats.len(), 1);
+        assert_eq!(r.buckets.len(), 1);
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(run(&BlockQuery { since: Some("-1d --vacuum-time=1s\n".into()), ..Default::default() }).starts_with("[denied]"));
+        assert!(run(&BlockQuery { bucket: Some("minute".into()), ..Default::default() }).starts_with("[denied]"));
+    }
+}
@@ -0,0 +1,143 @@
+//! `connections`: which process talks to which remote endpoint, from
+//! [`oligarchy_mcp_core::procnet`], and whether strict-egress would let it.
+//!
+//! "Allowlisted" means the remote address is in one of the strict-egress
+//! sets (static CIDRs or resolved domains). A connection that exists but is
+//! not allowlisted was either opened before the set entry expired, or is
+//! accepted by another rule (loopback, a uid exemption) — both worth a look.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use oligarchy_mcp_core::nft::{self, Ruleset};
+use oligarchy_mcp_core::procnet::{self, Process, Socket};
+use serde::Serialize;
+
+use crate::egress;
+use crate::ASPECT;
+
+#[derive(Debug, Serialize)]
+struct Connection {
+    proto: &'static str,
+    local: String,
+    remote: String,
+    uid: u32,
+    process: Option<Process>,
+    /// From the resolver cache.
+    domain: Option<String>,
+    /// None when the strict-egress table could not be read.
+    allowlisted: Option<bool>,
+    /// The set and element that allowlist the remote address.
+    allowed_by: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Inventory {
+    connections: Vec<Connection>,
+    /// Sockets whose owner is not visible to this server.
+    unattributed: usize,
+    not_allowlisted: usize,
+    unavailable: Vec<String>,
+}
+
+pub fn inventory(include_loopback: bool) -> String {
+    let mut unavailable = Vec::new();
+    let rs = nft::load_table(ASPECT, egress::FAMILY, egress::TABLE)
+        .map_err(|e| unavailable.push(format!("strict-egress table: {e}")))
+        .ok();
+    let domains: HashMap<IpAddr, String> = egress::resolver_cache()
+        .map(|c| c.into_iter().map(|r| (r.ip, r.domain)).collect())
+        .unwrap_or_default();
+    let owners = procnet::socket_owners();
+    let report = build(procnet::sockets(), include_loopback, &owners, procnet::process, rs.as_ref(), &domains, unavailable);
+    oligarchy_mcp_core::report::json(&report)
+}
+
+fn build(
+    sockets: Vec<Socket>,
+    include_loopback: bool,
+    owners: &HashMap<u64, u32>,
+    process: impl Fn(u32) -> Process,
+    rs: Option<&Ruleset>,
+    domains: &HashMap<IpAddr, String>,
+    unavailable: Vec<String>,
+) -> Inventory {
+    let mut cache: HashMap<u32, Process> = HashMap::new();
+    let mut connections: Vec<Connection> = sockets
+        .into_iter()
+        .filter(|s| s.state == procnet::ESTABLISHED)
+        .filter(|s| include_loopback || !s.remote.ip().is_loopback())
+        .map(|s| {
+            let ip = s.remote.ip();
+            let hit = rs.map(|rs| {
+                rs.sets_containing(ip)
+                    .into_iter()
+                    .find(|h| h.table.family == egress::FAMILY && h.table.name == egress::TABLE)
+                    .map(|h| format!("{} ({})", h.set.name, h.element.value))
+            });
+            Connection {
+                proto: s.proto,
+                local: s.local.to_string(),
+                remote: s.remote.to_string(),
+                uid: s.uid,
+                process: owners.get(&s.inode).map(|&pid| cache.entry(pid).or_insert_with(|| process(pid)).clone()),
+                domain: domains.get(&ip).cloned(),
+                allowlisted: hit.as_ref().map(Option::is_some),
+                allowed_by: hit.flatten(),
+            }
+        })
+        .collect();
+    connections.sort_by(|a, b| {
+        let pid = |c: &Connection| c.process.as_ref().map(|p| p.pid);
+        pid(a).cmp(&pid(b)).then_with(|| a.remote.cmp(&b.remote))
+    });
+    Inventory {
+        unattributed: connections.iter().filter(|c| c.process.is_none()).count(),
+        not_allowlisted: connections.iter().filter(|c| c.allowlisted == Some(false)).count(),
+        connections,
+        unavailable,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TCP: &str = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:0277 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 100 1 0
+   1: 0501A8C0:C6A2 22D8B85D:01BB 01 00000000:00000000 02:000A3D70 00000000  1000        0 200 2 0
+   2: 0501A8C0:C6A4 08080808:0035 01 00000000:00000000 02:000A3D70 00000000  1000        0 300 2 0
+   3: 0100007F:9C40 0100007F:0277 01 00000000:00000000 02:000A3D70 00000000  1000        0 400 2 0
+";
+
+    #[test]
+    fn maps_owners_and_allowlist() {
+        let rs = nft::parse(
+            r#"{"nftables": [
+              {"table": {"family": "inet", "name": "strict-egress", "handle": 1}},
+              {"set": {"family": "inet", "name": "egress_dyn4", "table": "strict-egress", "type": "ipv4_addr",
+                       "flags": ["timeout"], "elem": [{"elem": {"val": "93.184.216.34", "timeout": 93600, "expires": 50}}]}}
+            ]}"#,
+        )
+        .unwrap();
+        let owners = HashMap::from([(200, 4242)]);
+        let domains = HashMap::from([("93.184.216.34".parse().unwrap(), "example.com".to_string())]);
+        let proc_of = |pid| Process { pid, comm: Some("curl".into()), ..Default::default() };
+        let inv = build(procnet::parse("tcp", TCP), false, &owners, proc_of, Some(&rs), &domains, Vec::new());
+
+        assert_eq!(inv.connections.len(), 2, "listener and loopback are left out");
+        let unowned = &inv.connections[0];
+        assert_eq!(unowned.remote, "8.8.8.8:53");
+        assert_eq!(unowned.allowlisted, Some(false));
+        let curl = &inv.connections[1];
+        assert_eq!(curl.process.as_ref().and_then(|p| p.comm.as_deref()), Some("curl"));
+        assert_eq!(curl.domain.as_deref(), Some("example.com"));
+        assert_eq!(curl.allowed_by.as_deref(), Some("egress_dyn4 (93.184.216.34)"));
+        assert_eq!((inv.unattributed, inv.not_allowlisted), (1, 1));
+
+        let inv = build(procnet::parse("tcp", TCP), true, &owners, proc_of, None, &domains, Vec::new());
+        assert_eq!(inv.connections.len(), 3);
+        assert!(inv.connections.iter().all(|c| c.allowlisted.is_none()));
+    }
+}
@@ -0,0 +1,296 @@
+//! `dns_diagnose`: systemd-resolved state, and how a name's live answer
+//! compares with what strict-egress has cached and loaded.
+//!
+//! `resolvectl status` and `statistics` print aligned `Key: value` text, in
+//! sections (`Global`, `Link 2 (wlan0)`; `Cache`, `Failure Transactions`, …).
+//! Both are parsed by section rather than by fixed line, since the set of keys
+//! grows between systemd releases. resolved keeps no negative-cache counter;
+//! the failure-transaction counters are the nearest it exposes.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::IpAddr;
+
+use oligarchy_mcp_core::nft::{self, Ruleset};
+use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
+use serde::Serialize;
+
+use crate::egress::{self, Resolved};
+use crate::ASPECT;
+
+/// `Key: value` pairs of one output section.
+type Section = BTreeMap<String, String>;
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct Link {
+    /// `global` or the interface name.
+    name: String,
+    current_server: Option<String>,
+    servers: Vec<String>,
+    domains: Vec<String>,
+    default_route: Option<bool>,
+    /// `DNSSEC=` setting/support, e.g. `allow-downgrade/supported`.
+    dnssec: Option<String>,
+    /// `+DNSOverTLS`, `-DNSOverTLS` or `DNSOverTLS=opportunistic`.
+    dns_over_tls: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Answer {
+    addresses: Vec<IpAddr>,
+    /// Legend lines (`-- Data is authenticated: …`, `-- Data from: cache`).
+    notes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Drift {
+    /// The domain has entries in the resolver cache, i.e. it is allowlisted.
+    allowlisted: bool,
+    cached: Vec<IpAddr>,
+    /// Live answers the resolver cache does not have yet.
+    not_cached: Vec<IpAddr>,
+    /// Cached addresses the live answer no longer returns.
+    stale: Vec<IpAddr>,
+    /// Live answers no strict-egress set contains: connections to these
+    /// are dropped until the next refresh.
+    not_loaded: Vec<IpAddr>,
+    drifted: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Diagnosis {
+    links: Vec<Link>,
+    statistics: BTreeMap<String, Section>,
+    query: Option<Answer>,
+    egress: Option<Drift>,
+    unavailable: Vec<String>,
+}
+
+/// `name` has already been through [`oligarchy_mcp_core::validate::admit`].
+pub fn diagnose(name: Option<&str>) -> String {
+    let name = name.map(egress::normalize_host);
+    let mut unavailable = Vec::new();
+    let mut call = |args: &[&str]| match runner::run(ASPECT, "resolvectl", args, QUICK_TIMEOUT) {
+        Ok(out) => Some(out),
+        Err(e) => {
+            unavailable.push(format!("resolvectl {}: {e}", args[0]));
+            None
+        }
+    };
+    let links = call(&["status"]).map(|o| parse_status(&o)).unwrap_or_default();
+    let statistics = call(&["statistics"]).map(|o| sections(&o)).unwrap_or_default();
+    let query = name.as_deref().and_then(|n| call(&["query", n])).map(|o| parse_query(&o));
+
+    let egress = match (&name, &query) {
+        (Some(n), Some(answer)) => {
+            let cache = egress::resolver_cache()
+                .map_err(|e| unavailable.push(format!("{}: {e}", egress::RESOLVED_PATH)))
+                .unwrap_or_default();
+            let rs = nft::load_table(ASPECT, egress::FAMILY, egress::TABLE)
+                .map_err(|e| unavailable.push(format!("strict-egress table: {e}")))
+                .ok();
+            Some(drift(n, &answer.addresses, &cache, rs.as_ref()))
+        }
+        _ => None,
+    };
+    oligarchy_mcp_core::report::json(&Diagnosis { links, statistics, query, egress, unavailable })
+}
+
+/// Splits `resolvectl` output into sections: an unindented line without
+/// `": "` opens one; `Key: value` lines fill it, and indented lines without a
+/// key continue the previous value (long server lists wrap).
+fn sections(text: &str) -> BTreeMap<String, Section> {
+    let mut out: BTreeMap<String, Section> = BTreeMap::new();
+    let mut current = String::new();
+    let mut last_key: Option<String> = None;
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match line.split_once(':') {
+            Some((k, v)) if !k.trim().is_empty() && (v.is_empty() || v.starts_with(' ')) => {
+                let key = k.trim().to_string();
+                out.entry(current.clone()).or_default().insert(key.clone(), v.trim().to_string());
+                last_key = Some(key);
+            }
+            _ if !line.starts_with(char::is_whitespace) => {
+                current = line.trim().to_string();
+                last_key = None;
+            }
+            _ => {
+                if let Some(value) = last_key.as_ref().and_then(|k| out.get_mut(&current)?.get_mut(k)) {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+            }
+        }
+    }
+    out.remove("");
+    out
+}
+
+fn parse_status(text: &str) -> Vec<Link> {
+    sections(text)
+        .into_iter()
+        .filter_map(|(title, s)| {
+            let name = if title == "Global" {
+                "global".to_string()
+            } else {
+                // `Link 2 (wlan0)`
+                title.strip_prefix("Link ")?.split_once('(')?.1.trim_end_matches(')').to_string()
+            };
+            let list = |k: &str| s.get(k).map(|v| v.split_whitespace().map(str::to_string).collect()).unwrap_or_default();
+            let protocols: Vec<&str> = s.get("Protocols").map(|p| p.split_whitespace().collect()).unwrap_or_default();
+            Some(Link {
+                name,
+                current_server: s.get("Current DNS Server").cloned(),
+                servers: list("DNS Servers"),
+                domains: list("DNS Domain"),
+                default_route: protocols.iter().find(|p| p.ends_with("DefaultRoute")).map(|p| p.starts_with('+')),
+                dnssec: protocols.iter().find_map(|p| p.strip_prefix("DNSSEC=")).map(str::to_string),
+                dns_over_tls: protocols.iter().find(|p| p.contains("DNSOverTLS")).map(|p| p.to_string()),
+            })
+        })
+        .collect()
+}
+
+/// `name: 1.2.3.4 -- link: wlan0` plus continuation lines with more
+/// addresses, then `-- …` legend lines.
+fn parse_query(text: &str) -> Answer {
+    let mut addresses = Vec::new();
+    let mut notes = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if let Some(note) = line.strip_prefix("--") {
+            notes.push(note.trim().to_string());
+            continue;
+        }
+        let record = line.split(" -- ").next().unwrap_or("");
+        let value = record.rsplit_once(": ").map_or(record, |(_, v)| v);
+        if let Ok(ip) = value.trim().parse::<IpAddr>() {
+            if !addresses.contains(&ip) {
+                addresses.push(ip);
+            }
+        }
+    }
+    Answer { addresses, notes }
+}
+
+fn drift(name: &str, live: &[IpAddr], cache: &[Resolved], rs: Option<&Ruleset>) -> Drift {
+    let cached: BTreeSet<IpAddr> = cache.iter().filter(|r| r.domain == name).map(|r| r.ip).collect();
+    let live_set: BTreeSet<IpAddr> = live.iter().copied().collect();
+    let loaded = |ip: IpAddr| {
+        rs.is_some_and(|rs| {
+            rs.sets_containing(ip)
+                .iter()
+                .any(|h| h.table.family == egress::FAMILY && h.table.name == egress::TABLE)
+        })
+    };
+    let not_cached: Vec<IpAddr> = live_set.difference(&cached).copied().collect();
+    let stale: Vec<IpAddr> = cached.difference(&live_set).copied().collect();
+    let not_loaded: Vec<IpAddr> = if rs.is_some() { live_set.iter().copied().filter(|ip| !loaded(*ip)).collect() } else { Vec::new() };
+    let allowlisted = !cached.is_empty();
+    Drift {
+        allowlisted,
+        drifted: allowlisted && !(not_cached.is_empty() && not_loaded.is_empty()),
+        cached: cached.into_iter().collect(),
+        not_cached,
+        stale,
+        not_loaded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUS: &str = "\
+Global
+           Protocols: +LLMNR +mDNS -DNSOverTLS DNSSEC=no/unsupported
+    resolv.conf mode: stub
+  Current DNS Server: 9.9.9.9
+         DNS Servers: 9.9.9.9 149.112.112.112
+          DNS Domain: ~.
+
+Link 2 (wlan0)
+    Current Scopes: DNS LLMNR/IPv4
+         Protocols: +DefaultRoute +LLMNR -mDNS DNSOverTLS=opportunistic DNSSEC=allow-downgrade/supported
+Current DNS Server: 192.168.1.1
+       DNS Servers: 192.168.1.1
+                    fe80::1%wlan0
+        DNS Domain: lan
+";
+
+    const STATS: &str = "\
+DNSSEC supported by current servers: no
+
+Transactions
+Current Transactions: 0
+  Total Transactions: 1520
+
+Cache
+  Current Cache Size: 41
+          Cache Hits: 820
+        Cache Misses: 700
+
+Failure Transactions
+          Total Timeouts: 3
+ Total Failure Responses: 12
+";
+
+    const QUERY: &str = "\
+github.com: 140.82.121.3                       -- link: wlan0
+            140.82.121.4                       -- link: wlan0
+
+-- Information acquired via protocol DNS in 21.2ms.
+-- Data is authenticated: no; Data was acquired via local or encrypted transport: no
+-- Data from: network
+";
+
+    #[test]
+    fn parses_status_links() {
+        let links = parse_status(STATUS);
+        assert_eq!(links.len(), 2);
+        let global = links.iter().find(|l| l.name == "global").unwrap();
+        assert_eq!(global.servers, ["9.9.9.9", "149.112.112.112"]);
+        assert_eq!(global.dns_over_tls.as_deref(), Some("-DNSOverTLS"));
+        let wlan = links.iter().find(|l| l.name == "wlan0").unwrap();
+        assert_eq!(wlan.current_server.as_deref(), Some("192.168.1.1"));
+        assert_eq!(wlan.servers, ["192.168.1.1", "fe80::1%wlan0"], "wrapped list");
+        assert_eq!(wlan.default_route, Some(true));
+        assert_eq!(wlan.dnssec.as_deref(), Some("allow-downgrade/supported"));
+        assert_eq!(wlan.dns_over_tls.as_deref(), Some("DNSOverTLS=opportunistic"));
+    }
+
+    #[test]
+    fn parses_statistics_sections() {
+        let s = sections(STATS);
+        assert_eq!(s["Cache"]["Cache Hits"], "820");
+        assert_eq!(s["Failure Transactions"]["Total Failure Responses"], "12");
+        assert!(!s.contains_key(""), "keys before the first header are dropped");
+    }
+
+    #[test]
+    fn parses_query_and_detects_drift() {
+        let a = parse_query(QUERY);
+        assert_eq!(a.addresses.len(), 2);
+        assert_eq!(a.notes.last().map(String::as_str), Some("Data from: network"));
+
+        let cache = egress::parse_resolved("github.com 140.82.121.3\ngithub.com 140.82.121.9\n");
+        let rs = nft::parse(
+            r#"{"nftables": [
+              {"table": {"family": "inet", "name": "strict-egress", "handle": 1}},
+              {"set": {"family": "inet", "name": "egress_dyn4", "table": "strict-egress", "type": "ipv4_addr",
+                       "flags": ["timeout"], "elem": [{"elem": {"val": "140.82.121.3", "timeout": 93600, "expires": 50}}]}}
+            ]}"#,
+        )
+        .unwrap();
+        let d = drift("github.com", &a.addresses, &cache, Some(&rs));
+        assert!(d.allowlisted && d.drifted);
+        assert_eq!(d.not_cached, vec!["140.82.121.4".parse::<IpAddr>().unwrap()]);
+        assert_eq!(d.stale, vec!["140.82.121.9".parse::<IpAddr>().unwrap()]);
+        assert_eq!(d.not_loaded, d.not_cached);
+
+        let d = drift("example.org", &a.addresses, &cache, None);
+        assert!(!d.allowlisted && !d.drifted);
+        assert!(d.not_loaded.is_empty(), "unknown without the ruleset");
+    }
+}
@@ -0,0 +1,66 @@
+//! What the net aspect knows about the strict-egress module
+//! (`modules/security/strict-egress.nix`): table, chain and set names, log
+//! prefixes and the resolver cache.
+
+use std::net::IpAddr;
+
+/// `table inet strict-egress`.
+pub const FAMILY: &str = "inet";
+pub const TABLE: &str = "strict-egress";
+/// The output-hook base chain.
+pub const CHAIN: &str = "egress";
+/// Interval sets fed from `allow.cidrs`, by address family.
+pub const STATIC4: &str = "egress_static4";
+pub const STATIC6: &str = "egress_static6";
+
+/// Kernel-log prefixes of the drop rule (enforcing) and the log-only rule
+/// (`recovery.dryRun`).
+pub const BLOCKED_PREFIX: &str = "STRICT-EGRESS-BLOCKED:";
+pub const WOULDBLOCK_PREFIX: &str = "STRICT-EGRESS-WOULDBLOCK:";
+
+/// `<domain> <ip>` per line, rewritten by `strict-egress-resolve` on each
+/// refresh (`derp-map <ip>` for Tailscale relays).
+pub const RESOLVED_PATH: &str = "/run/strict-egress/resolved.txt";
+
+/// One line of the resolver cache.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolved {
+    pub domain: String,
+    pub ip: IpAddr,
+}
+
+/// Reads the resolver cache. `OLIGARCHY_EGRESS_RESOLVED` overrides the path.
+pub fn resolver_cache() -> std::io::Result<Vec<Resolved>> {
+    let path = std::env::var("OLIGARCHY_EGRESS_RESOLVED").unwrap_or_else(|_| RESOLVED_PATH.into());
+    Ok(parse_resolved(&std::fs::read_to_string(path)?))
+}
+
+/// Parses `<domain> <ip>` lines; anything else is skipped. Domains are
+/// lower-cased without a trailing dot.
+pub fn parse_resolved(text: &str) -> Vec<Resolved> {
+    text.lines()
+        .filter_map(|line| {
+            let mut cols = line.split_whitespace();
+            let domain = cols.next()?;
+            let ip = cols.next()?.parse().ok()?;
+            Some(Resolved { domain: normalize_host(domain), ip })
+        })
+        .collect()
+}
+
+pub fn normalize_host(host: &str) -> String {
+    host.trim().trim_end_matches('.').to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_resolver_cache() {
+        let r = parse_resolved("GitHub.com. 140.82.121.4\nderp-map 2a01:4f8::1\ngarbage\ncache.nixos.org not-an-ip\n");
+        assert_eq!(r.len(), 2);
+        assert_eq!(r[0].domain, "github.com");
+        assert_eq!(r[1].ip, "2a01:4f8::1".parse::<IpAddr>().unwrap());
+    }
+}
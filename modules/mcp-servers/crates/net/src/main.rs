@@ -1,9 +1,17 @@
 //! oligarchy-net-mcp — read-only MCP server for the network/firewall aspect.
 //! See `docs/mcp-servers-roadmap.md` §4.2.
 
+mod blocks;
+mod connections;
+mod dns;
+mod egress;
+mod ruleset;
+mod simulate;
+
 use oligarchy_mcp_core::audit;
 use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
 use oligarchy_mcp_core::runner_mcp;
+use oligarchy_mcp_core::validate::{self, Host, Ip, Validate};
 
 use rmcp::{ServerHandler, model::{ServerCapabilities, ServerInfo}, tool};
 
@@ -21,6 +29,12 @@ impl Server {
             .unwrap_or_else(|e| format!("[error] {e}"))
     }
 
+    #[tool(description = "Established TCP/UDP connections from /proc/net (no ss spawn) with the owning PID, exe and systemd unit, the remote endpoint's resolver-cache domain, and whether it is in a strict-egress allowlist set. Loopback peers are left out unless include_loopback.")]
+    fn connections(&self, #[tool(param)] include_loopback: Option<bool>) -> String {
+        audit::tool(ASPECT, "connections", "");
+        connections::inventory(include_loopback.unwrap_or(false))
+    }
+
     #[tool(description = "Strict-egress firewall status (mode, resolved entries, recent blocks).")]
     fn egress_status(&self) -> String {
         audit::tool(ASPECT, "egress_status", "");
@@ -28,20 +42,49 @@ impl Server {
             .unwrap_or_else(|e| format!("[error] {e}"))
     }
 
+    #[tool(description = "Strict-egress drop history from the kernel log as structured records, aggregated by destination, port, socket owner uid and hour/day bucket. Flags destinations first seen since the last generation switch. since is journalctl syntax (default -7d).")]
+    fn egress_blocks(&self, #[tool(aggr)] q: blocks::BlockQuery) -> String {
+        audit::tool(ASPECT, "egress_blocks", &q.summary());
+        blocks::run(&q)
+    }
+
     #[tool(description = "Try connecting to a host through the strict-egress allowlist (read-only probe).")]
-    fn egress_test_host(&self, #[tool(param)] host: String) -> String {
-        audit::tool(ASPECT, "egress_test_host", &host);
+    fn egress_test_host(&self, #[tool(param)] host: Host) -> String {
+        audit::tool(ASPECT, "egress_test_host", host.as_str());
+        let host = match validate::admit(ASPECT, "egress_test_host", &host) {
+            Ok(h) => h,
+            Err(denied) => return denied,
+        };
         runner::run(ASPECT, "strict-egress-test", &[&host], QUICK_TIMEOUT)
             .unwrap_or_else(|e| format!("[error] {e}"))
     }
 
+    #[tool(description = "Offline strict-egress verdict for host/port/proto: resolves the host from the resolver cache (never DNS), walks each address through the loaded inet strict-egress table and explains the verdict step by step (chain, rules, set, log prefix). extra_allow adds hypothetical IPs/CIDRs to the static sets to review an allowlist change. Sends no packet.")]
+    fn simulate_egress(&self, #[tool(aggr)] sim: simulate::Simulation) -> String {
+        audit::tool(ASPECT, "simulate_egress", &sim.summary());
+        simulate::run(&sim)
+    }
+
     #[tool(description = "Resolve a DNS name through systemd-resolved (resolvectl query).")]
-    fn dns_resolve(&self, #[tool(param)] name: String) -> String {
-        audit::tool(ASPECT, "dns_resolve", &name);
+    fn dns_resolve(&self, #[tool(param)] name: Host) -> String {
+        audit::tool(ASPECT, "dns_resolve", name.as_str());
+        let name = match validate::admit(ASPECT, "dns_resolve", &name) {
+            Ok(n) => n,
+            Err(denied) => return denied,
+        };
         runner::run(ASPECT, "resolvectl", &["query", &name], QUICK_TIMEOUT)
             .unwrap_or_else(|e| format!("[error] {e}"))
     }
 
+    #[tool(description = "DNS diagnostics: per-link DNS servers, DNSSEC and DNS-over-TLS state, resolved cache and failure statistics. With name, also the live answer compared against the strict-egress resolver cache and loaded sets (not cached, stale, not loaded).")]
+    fn dns_diagnose(&self, #[tool(param)] name: Option<Host>) -> String {
+        audit::tool(ASPECT, "dns_diagnose", name.as_ref().map_or("", |n| n.as_str()));
+        match name.as_ref().map(|n| validate::admit(ASPECT, "dns_diagnose", n)).transpose() {
+            Ok(name) => dns::diagnose(name.as_deref()),
+            Err(denied) => denied,
+        }
+    }
+
     #[tool(description = "List the strict-egress nft table (sets and chain state).")]
     fn nft_list_sets(&self) -> String {
         audit::tool(ASPECT, "nft_list_sets", "");
@@ -49,6 +92,27 @@ impl Server {
             .unwrap_or_else(|e| format!("[error] {e}"))
     }
 
+    #[tool(description = "Parsed nft ruleset as JSON: tables, chains (hook, priority, policy), rules with handle and verdict, sets with element counts and timeouts. table filters by table name.")]
+    fn nft_ruleset(&self, #[tool(param)] table: Option<String>) -> String {
+        audit::tool(ASPECT, "nft_ruleset", table.as_deref().unwrap_or(""));
+        ruleset::ruleset(table.as_deref())
+    }
+
+    #[tool(description = "Which nft rule would match a packet: evaluates dst/proto/dport (and optional src, sport, uid, oif, ct_state) through every base chain on the hook (default output). Returns the matching rule handle and verdict per chain, with a per-rule trace. Sends nothing.")]
+    fn nft_match(&self, #[tool(aggr)] probe: ruleset::Probe) -> String {
+        audit::tool(ASPECT, "nft_match", &probe.summary());
+        ruleset::evaluate(&probe)
+    }
+
+    #[tool(description = "Which nft sets contain an IP address (across all tables), with the matching element and its timeout/expiry.")]
+    fn nft_set_lookup(&self, #[tool(param)] ip: Ip) -> String {
+        audit::tool(ASPECT, "nft_set_lookup", ip.as_str());
+        match validate::admit(ASPECT, "nft_set_lookup", &ip) {
+            Ok(ip) => ruleset::set_lookup(&ip),
+            Err(denied) => denied,
+        }
+    }
+
     #[tool(description = "DeMoD IP blocker service status.")]
     fn ip_blocker_status(&self) -> String {
         audit::tool(ASPECT, "ip_blocker_status", "");
@@ -66,8 +130,12 @@ impl Server {
 
     #[tool(description = "Check whether a single IP is in the threat-intel \
                           blocklist sets, and which set matched.")]
-    fn blocklist_test(&self, #[tool(param)] ip: String) -> String {
-        audit::tool(ASPECT, "blocklist_test", &ip);
+    fn blocklist_test(&self, #[tool(param)] ip: Ip) -> String {
+        audit::tool(ASPECT, "blocklist_test", ip.as_str());
+        let ip = match validate::admit(ASPECT, "blocklist_test", &ip) {
+            Ok(ip) => ip,
+            Err(denied) => return denied,
+        };
         runner::run(ASPECT, "oligarchy-blocklist", &["test", &ip], QUICK_TIMEOUT)
             .unwrap_or_else(|e| format!("[error] {e}"))
     }
@@ -88,7 +156,7 @@ impl ServerHandler for Server {
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt().init();
     tracing::info!(aspect = ASPECT, "starting");
-    runner_mcp::serve(Server::default()).await
+    runner_mcp::serve(Server).await
 }
 
 #[cfg(test)]
@@ -0,0 +1,231 @@
+//! Tools over the parsed nft ruleset ([`oligarchy_mcp_core::nft`]):
+//! `nft_ruleset`, `nft_match` and `nft_set_lookup`.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use oligarchy_mcp_core::nft::{self, ChainResult, Packet, Ruleset, Verdict};
+use oligarchy_mcp_core::users;
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+
+use crate::ASPECT;
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct Probe {
+    /// Destination IP address.
+    pub dst: String,
+    /// Protocol: tcp, udp, icmp, ipv6-icmp, or a number. Default tcp.
+    pub proto: Option<String>,
+    /// Destination port (tcp/udp).
+    pub dport: Option<u16>,
+    /// Source IP address, if known.
+    pub src: Option<String>,
+    /// Source port, if known.
+    pub sport: Option<u16>,
+    /// Netfilter hook to evaluate. Default `output`.
+    pub hook: Option<String>,
+    /// Socket owner uid, for `meta skuid` rules (matched by user name too,
+    /// via `/etc/passwd`, as nft prints known uids by name).
+    pub uid: Option<u32>,
+    /// Output interface, for `oif`/`oifname` rules. Defaults to `lo` for
+    /// loopback destinations and "not lo" otherwise.
+    pub oif: Option<String>,
+    /// Conntrack state. Default `new`.
+    pub ct_state: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct MatchReport {
+    packet: Packet,
+    hook: String,
+    /// The packet survives the hook only if no base chain drops it.
+    verdict: Option<Verdict>,
+    /// Set when some rule could not be decided from the packet description;
+    /// the verdict assumes such rules do not match.
+    caveat: Option<String>,
+    chains: Vec<ChainResult>,
+}
+
+#[derive(Debug, Serialize)]
+struct SetMatch {
+    family: String,
+    table: String,
+    set: String,
+    element: String,
+    timeout: Option<u64>,
+    expires: Option<u64>,
+}
+
+impl Probe {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.dst,
+            self.proto.as_deref().unwrap_or("tcp"),
+            self.dport.map(|p| p.to_string()).unwrap_or_default()
+        )
+    }
+
+    fn to_packet(&self) -> Result<Packet, String> {
+        let dst: IpAddr = self.dst.parse().map_err(|_| format!("dst is not an IP address: {:?}", self.dst))?;
+        let proto = self.proto.as_deref().unwrap_or("tcp");
+        if proto.is_empty() || !proto.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("invalid protocol: {proto:?}"));
+        }
+        let mut p = Packet::new(dst, proto, self.dport);
+        if let Some(src) = &self.src {
+            p.src = Some(src.parse().map_err(|_| format!("src is not an IP address: {src:?}"))?);
+        }
+        p.sport = self.sport;
+        // nft prints `meta skuid` by user name; only read when there is a uid.
+        let users = if self.uid.is_some() { users::names() } else { HashMap::new() };
+        p.set_owner(self.uid, &users);
+        if let Some(oif) = &self.oif {
+            p.oif = Some(oif.clone());
+        }
+        if let Some(state) = &self.ct_state {
+            p.ct_state = Some(state.to_ascii_lowercase());
+        }
+        Ok(p)
+    }
+}
+
+/// The whole ruleset, or the tables named `table` (any family).
+pub fn ruleset(table: Option<&str>) -> String {
+    match nft::load(ASPECT) {
+        Ok(mut rs) => {
+            if let Some(name) = table {
+                rs.tables.retain(|t| t.name == name);
+                if rs.tables.is_empty() {
+                    return format!("[absent] no nft table named {name:?}");
+                }
+            }
+            oligarchy_mcp_core::report::json(&rs)
+        }
+        Err(e) => format!("[error] {e}"),
+    }
+}
+
+pub fn evaluate(probe: &Probe) -> String {
+    let packet = match probe.to_packet() {
+        Ok(p) => p,
+        Err(e) => return format!("[denied] {e}"),
+    };
+    match nft::load(ASPECT) {
+        Ok(rs) => oligarchy_mcp_core::report::json(&match_report(&rs, packet, probe.hook.as_deref().unwrap_or("output"))),
+        Err(e) => format!("[error] {e}"),
+    }
+}
+
+fn match_report(rs: &Ruleset, packet: Packet, hook: &str) -> MatchReport {
+    let chains = rs.evaluate(&packet, hook);
+    let verdict = if chains.is_empty() {
+        None
+    } else {
+        Some(
+            chains
+                .iter()
+                .filter_map(|c| c.verdict.clone())
+                .find(|v| matches!(v, Verdict::Drop | Verdict::Reject))
+                .unwrap_or(Verdict::Accept),
+        )
+    };
+    let undecided: usize = chains.iter().map(|c| c.undecided.len()).sum();
+    let caveat = (undecided > 0).then(|| {
+        format!("{undecided} rule(s) depend on fields not given (see `undecided`); they were treated as not matching")
+    });
+    MatchReport { packet, hook: hook.to_string(), verdict, caveat, chains }
+}
+
+pub fn set_lookup(ip: &str) -> String {
+    let addr: IpAddr = match ip.parse() {
+        Ok(a) => a,
+        Err(_) => return format!("[denied] not an IP address: {ip:?}"),
+    };
+    match nft::load(ASPECT) {
+        Ok(rs) => oligarchy_mcp_core::report::json(&set_matches(&rs, addr)),
+        Err(e) => format!("[error] {e}"),
+    }
+}
+
+fn set_matches(rs: &Ruleset, addr: IpAddr) -> Vec<SetMatch> {
+    rs.sets_containing(addr)
+        .into_iter()
+        .map(|h| SetMatch {
+            family: h.table.family.clone(),
+            table: h.table.name.clone(),
+            set: h.set.name.clone(),
+            element: h.element.value.to_string(),
+            timeout: h.element.timeout,
+            expires: h.element.expires,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULESET: &str = r#"{"nftables": [
+      {"table": {"family": "inet", "name": "strict-egress", "handle": 7}},
+      {"set": {"family": "inet", "name": "egress_dyn4", "table": "strict-egress", "type": "ipv4_addr", "flags": ["timeout"],
+               "elem": [{"elem": {"val": "185.199.108.133", "timeout": 93600, "expires": 1200}}]}},
+      {"chain": {"family": "inet", "table": "strict-egress", "name": "egress", "type": "filter", "hook": "output", "prio": 10, "policy": "drop"}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 4,
+                "expr": [{"match": {"op": "==", "left": {"meta": {"key": "skuid"}}, "right": 153}}, {"accept": null}]}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 5,
+                "expr": [{"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "daddr"}}, "right": "@egress_dyn4"}}, {"accept": null}]}}
+    ]}"#;
+
+    fn probe(dst: &str) -> Probe {
+        Probe {
+            dst: dst.into(),
+            proto: None,
+            dport: Some(443),
+            src: None,
+            sport: None,
+            hook: None,
+            uid: None,
+            oif: None,
+            ct_state: None,
+        }
+    }
+
+    #[test]
+    fn overall_verdict_and_caveat() {
+        let rs = nft::parse(RULESET).unwrap();
+        let r = match_report(&rs, probe("185.199.108.133").to_packet().unwrap(), "output");
+        assert_eq!(r.verdict, Some(Verdict::Accept));
+        assert_eq!(r.chains[0].matched_rule, Some(5));
+        assert!(r.caveat.is_some(), "the skuid rule is undecidable without a uid");
+
+        let mut p = probe("9.9.9.9");
+        p.uid = Some(1000);
+        let r = match_report(&rs, p.to_packet().unwrap(), "output");
+        assert_eq!(r.verdict, Some(Verdict::Drop));
+        assert_eq!(r.chains[0].matched_rule, None, "dropped by policy");
+        assert!(r.caveat.is_none());
+
+        assert_eq!(match_report(&rs, probe("9.9.9.9").to_packet().unwrap(), "input").verdict, None);
+    }
+
+    #[test]
+    fn rejects_bad_probe_fields() {
+        assert!(probe("example.org").to_packet().is_err());
+        let mut p = probe("1.1.1.1");
+        p.proto = Some("tcp; drop".into());
+        assert!(p.to_packet().is_err());
+        assert!(set_lookup("not-an-ip").starts_with("[denied]"));
+    }
+
+    #[test]
+    fn set_lookup_reports_timeouts() {
+        let rs = nft::parse(RULESET).unwrap();
+        let hits = set_matches(&rs, "185.199.108.133".parse().unwrap());
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].set, "egress_dyn4");
+        assert_eq!(hits[0].expires, Some(1200));
+        assert!(set_matches(&rs, "1.1.1.1".parse().unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,401 @@
+//! `simulate_egress` — the strict-egress verdict for a destination, worked
+//! out locally.
+//!
+//! The destination's addresses come from the resolver cache (or the host is
+//! an IP literal); each address is then walked through the loaded
+//! `inet strict-egress` table with the shared nft evaluator. Nothing is sent:
+//! no DNS query, no connection attempt. `extra_allow` adds hypothetical
+//! entries to the static sets, so an allowlist change can be checked before
+//! it is deployed.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use oligarchy_mcp_core::nft::{self, Element, Expr, Packet, Ruleset, Table, Tri, Value, Verdict};
+use oligarchy_mcp_core::users;
+use oligarchy_mcp_core::validate::{self, Host, IpOrCidr, Validate};
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+
+use crate::egress::{self, Resolved};
+use crate::ASPECT;
+
+/// Stand-in for "an address that is in no set" (RFC 5737 TEST-NET-1), used
+/// when the host is not in the resolver cache so port-based rules still show.
+const UNLISTED_V4: &str = "192.0.2.1";
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct Simulation {
+    /// Destination hostname or IP address.
+    pub host: Host,
+    /// Destination port. Default 443.
+    pub port: Option<u16>,
+    /// tcp (default), udp, icmp or ipv6-icmp.
+    pub proto: Option<String>,
+    /// Socket owner uid, to decide the per-uid allow rules (which nft prints
+    /// by user name; resolved through `/etc/passwd`).
+    pub uid: Option<u32>,
+    /// Hypothetical allowlist additions: IPs or CIDRs treated as if they were
+    /// in egress_static4/egress_static6.
+    #[serde(default)]
+    pub extra_allow: Vec<IpOrCidr>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimulationReport {
+    pub host: String,
+    pub port: u16,
+    pub proto: String,
+    /// `literal`, `resolver-cache` or `not-cached`.
+    pub resolution: &'static str,
+    /// `enforcing` (policy drop) or `dry-run` (policy accept, WOULDBLOCK logging).
+    pub mode: &'static str,
+    pub hypothetical: Vec<String>,
+    pub addresses: Vec<AddressVerdict>,
+    pub summary: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AddressVerdict {
+    pub address: IpAddr,
+    pub verdict: Verdict,
+    /// Handle of the deciding rule; `None` when the chain policy decided.
+    pub rule: Option<u64>,
+    pub rule_text: Option<String>,
+    /// The set (and element) that let the address through, if any.
+    pub set: Option<String>,
+    /// Log prefix of the strict-egress log rule, which logs until its
+    /// `limit` rate is exceeded.
+    pub logged_as: Option<String>,
+    pub steps: Vec<String>,
+}
+
+impl Simulation {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} {}/{}{}",
+            self.host.as_str(),
+            self.port.unwrap_or(443),
+            self.proto.as_deref().unwrap_or("tcp"),
+            if self.extra_allow.is_empty() { String::new() } else { format!(" +{}", self.extra_allow.iter().map(Validate::as_str).collect::<Vec<_>>().join(",")) }
+        )
+    }
+}
+
+pub fn run(sim: &Simulation) -> String {
+    let host = match validate::admit(ASPECT, "simulate_egress", &sim.host) {
+        Ok(h) => egress::normalize_host(&h),
+        Err(denied) => return denied,
+    };
+    let literal = host.parse::<IpAddr>().ok();
+    let proto = sim.proto.as_deref().unwrap_or("tcp").to_ascii_lowercase();
+    if !matches!(proto.as_str(), "tcp" | "udp" | "icmp" | "ipv6-icmp") {
+        return format!("[denied] proto must be tcp | udp | icmp | ipv6-icmp, got {proto:?}");
+    }
+    let extra = match parse_extra(&sim.extra_allow) {
+        Ok(e) => e,
+        Err(denied) => return denied,
+    };
+    let rs = match nft::load_table(ASPECT, egress::FAMILY, egress::TABLE) {
+        Ok(rs) => rs,
+        Err(e) => return format!("[error] reading the strict-egress table: {e}"),
+    };
+    let cache = if literal.is_some() {
+        Vec::new()
+    } else {
+        match egress::resolver_cache() {
+            Ok(c) => c,
+            Err(e) => return format!("[error] reading {}: {e}", egress::RESOLVED_PATH),
+        }
+    };
+    match simulate(&rs, &cache, &host, sim.port.unwrap_or(443), &proto, sim.uid, extra) {
+        Ok(r) => oligarchy_mcp_core::report::json(&r),
+        Err(e) => format!("[absent] {e}"),
+    }
+}
+
+/// Admits each entry and turns it into a set element; the `Err` is the
+/// tool's `[denied]` reply.
+fn parse_extra(items: &[IpOrCidr]) -> Result<Vec<(IpAddr, Value)>, String> {
+    items
+        .iter()
+        .map(|item| {
+            let item = validate::admit(ASPECT, "simulate_egress", item)?;
+            let (addr, len) = match item.split_once('/') {
+                Some((a, l)) => (a, l.parse::<u8>().ok()),
+                None => (&*item, None),
+            };
+            let ip: IpAddr = addr.parse().map_err(|_| format!("[denied] extra_allow: not an IP or CIDR: {item:?}"))?;
+            let value = match len {
+                None => Value::Str(ip.to_string()),
+                Some(len) => Value::Prefix { addr: ip.to_string(), len },
+            };
+            Ok((ip, value))
+        })
+        .collect()
+}
+
+pub fn simulate(
+    rs: &Ruleset,
+    cache: &[Resolved],
+    host: &str,
+    port: u16,
+    proto: &str,
+    uid: Option<u32>,
+    extra: Vec<(IpAddr, Value)>,
+) -> Result<SimulationReport, String> {
+    let mut table = rs
+        .table(egress::FAMILY, egress::TABLE)
+        .cloned()
+        .ok_or("the strict-egress table is not loaded (module disabled, or failed open)")?;
+    let hypothetical: Vec<String> = extra.iter().map(|(_, v)| v.to_string()).collect();
+    for (ip, value) in extra {
+        let name = if ip.is_ipv4() { egress::STATIC4 } else { egress::STATIC6 };
+        if let Some(set) = table.sets.iter_mut().find(|s| s.name == name) {
+            set.elements.push(Element { value, timeout: None, expires: None });
+            set.element_count += 1;
+        }
+    }
+    let chain = table
+        .chains
+        .iter()
+        .find(|c| c.name == egress::CHAIN)
+        .ok_or("the strict-egress table has no egress chain")?;
+    let mode = if chain.policy.as_deref() == Some("accept") { "dry-run" } else { "enforcing" };
+
+    let (resolution, addresses): (&'static str, Vec<IpAddr>) = match host.parse::<IpAddr>() {
+        Ok(ip) => ("literal", vec![ip]),
+        Err(_) => {
+            let ips: Vec<IpAddr> = cache.iter().filter(|r| r.domain == host).map(|r| r.ip).collect();
+            if ips.is_empty() {
+                ("not-cached", vec![UNLISTED_V4.parse().expect("valid literal")])
+            } else {
+                ("resolver-cache", ips)
+            }
+        }
+    };
+
+    // nft prints `meta skuid` by user name; only read when there is a uid.
+    let users = if uid.is_some() { users::names() } else { HashMap::new() };
+    let mut verdicts = Vec::new();
+    for ip in addresses {
+        let mut packet = Packet::new(ip, proto, matches!(proto, "tcp" | "udp").then_some(port));
+        packet.set_owner(uid, &users);
+        verdicts.push(explain(&table, chain, &packet));
+    }
+
+    let accepted = verdicts.iter().filter(|v| v.verdict == Verdict::Accept).count();
+    let summary = match resolution {
+        "not-cached" => format!(
+            "{host} is not in the resolver cache, so it is not on the allowlist (or the resolver has not run). \
+             An address in no set gets: {}.",
+            verdicts[0].verdict
+        ),
+        _ if accepted == verdicts.len() => format!("{host} {port}/{proto}: accepted on every address"),
+        _ if accepted == 0 && mode == "dry-run" => {
+            format!("{host} {port}/{proto}: would be blocked on every address (dry-run: logged as WOULDBLOCK, let through)")
+        }
+        _ if accepted == 0 => format!("{host} {port}/{proto}: blocked on every address"),
+        _ => format!(
+            "{host} {port}/{proto}: accepted on {accepted} of {} addresses — connections may fail intermittently",
+            verdicts.len()
+        ),
+    };
+    Ok(SimulationReport {
+        host: host.to_string(),
+        port,
+        proto: proto.to_string(),
+        resolution,
+        mode,
+        hypothetical,
+        addresses: verdicts,
+        summary,
+    })
+}
+
+fn explain(table: &Table, chain: &nft::Chain, packet: &Packet) -> AddressVerdict {
+    let ip = packet.dst;
+    let result = table.evaluate_chain(chain, packet);
+    let mut steps = vec![format!(
+        "table {} {}, chain {} (hook {}, priority {}, policy {})",
+        table.family,
+        table.name,
+        chain.name,
+        chain.hook.as_deref().unwrap_or("-"),
+        chain.prio.unwrap_or(0),
+        chain.policy.as_deref().unwrap_or("accept"),
+    )];
+    for step in &result.trace {
+        let handle = step.handle.map(|h| h.to_string()).unwrap_or_else(|| "?".into());
+        steps.push(match step.result {
+            Tri::Yes => format!("rule {handle} `{}`: matches", step.text),
+            Tri::No => format!("rule {handle} `{}`: no", step.text),
+            Tri::Unknown if step.rate_limited => format!(
+                "rule {handle} `{}`: matches only below its rate limit; assumed not to match",
+                step.text
+            ),
+            Tri::Unknown => format!(
+                "rule {handle} `{}`: undecided ({}); assumed not to match",
+                step.text,
+                step.reason.as_deref().unwrap_or("insufficient information")
+            ),
+        });
+    }
+
+    let rule = result
+        .matched_rule
+        .and_then(|h| chain.rules.iter().find(|r| r.handle == Some(h)));
+    let set = rule.and_then(|r| {
+        r.exprs.iter().find_map(|e| match e {
+            Expr::Match { right: Value::SetRef(name), .. } => table.set(name).and_then(|s| {
+                s.lookup_ip(ip).map(|el| {
+                    let expiry = el.expires.map(|x| format!(", expires in {x}s")).unwrap_or_default();
+                    format!("{} contains {}{expiry}", s.name, el.value)
+                })
+            }),
+            _ => None,
+        })
+    });
+    if let Some(s) = &set {
+        steps.push(format!("set lookup: {s}"));
+    }
+    // The log rule may be the deciding rule (enforcing) or a non-terminal
+    // rule before the accept policy (dry-run). It sits behind a `limit`, so it
+    // logs until that rate is exceeded.
+    let logged_as = result
+        .trace
+        .iter()
+        .filter(|t| t.result == Tri::Yes || t.rate_limited)
+        .filter_map(|t| chain.rules.iter().find(|r| r.handle == t.handle))
+        .flat_map(|r| r.exprs.iter())
+        .find_map(|e| match e {
+            Expr::Log { prefix: Some(p) } => Some(p.trim().trim_end_matches(':').to_string()),
+            _ => None,
+        });
+    let verdict = result.verdict.clone().unwrap_or(Verdict::Accept);
+    steps.push(match (&result.matched_rule, &logged_as) {
+        (Some(h), Some(l)) => format!("verdict: {verdict} by rule {h}, logged as {l}"),
+        (Some(h), None) => format!("verdict: {verdict} by rule {h}"),
+        (None, Some(l)) => format!("verdict: {verdict} by chain policy, logged as {l}"),
+        (None, None) => format!("verdict: {verdict} by chain policy"),
+    });
+    AddressVerdict {
+        address: ip,
+        verdict,
+        rule: result.matched_rule,
+        rule_text: result.matched_text,
+        set,
+        logged_as,
+        steps,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The table and its sets, the same in both modes.
+    const SETS: &str = r#"{"table": {"family": "inet", "name": "strict-egress", "handle": 7}},
+      {"set": {"family": "inet", "name": "egress_static4", "table": "strict-egress", "type": "ipv4_addr", "flags": ["interval"],
+               "elem": [{"prefix": {"addr": "140.82.112.0", "len": 20}}]}},
+      {"set": {"family": "inet", "name": "egress_static6", "table": "strict-egress", "type": "ipv6_addr", "flags": ["interval"]}},
+      {"set": {"family": "inet", "name": "egress_dyn4", "table": "strict-egress", "type": "ipv4_addr", "flags": ["timeout"],
+               "elem": [{"elem": {"val": "151.101.2.217", "timeout": 93600, "expires": 600}}]}}"#;
+
+    /// The allow rules ahead of the final logging rule, the same in both modes.
+    const ALLOW_RULES: &str = r#"{"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 10,
+                "expr": [{"match": {"op": "==", "left": {"meta": {"key": "oif"}}, "right": "lo"}}, {"accept": null}]}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 15,
+                "expr": [{"match": {"op": "==", "left": {"payload": {"protocol": "udp", "field": "dport"}}, "right": {"set": [123]}}}, {"accept": null}]}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 16,
+                "expr": [{"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "daddr"}}, "right": "@egress_static4"}}, {"accept": null}]}},
+      {"rule": {"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 18,
+                "expr": [{"match": {"op": "==", "left": {"payload": {"protocol": "ip", "field": "daddr"}}, "right": "@egress_dyn4"}}, {"accept": null}]}}"#;
+
+    /// The strict-egress table as rendered by the module. Enforcing mode has
+    /// a drop policy and drops after logging; dry-run accepts and only logs.
+    fn ruleset(dry_run: bool) -> Ruleset {
+        let (policy, prefix, drop) = if dry_run {
+            ("accept", "STRICT-EGRESS-WOULDBLOCK: ", "")
+        } else {
+            ("drop", "STRICT-EGRESS-BLOCKED: ", r#", {"drop": null}"#)
+        };
+        let text = format!(
+            r#"{{"nftables": [
+      {SETS},
+      {{"chain": {{"family": "inet", "table": "strict-egress", "name": "egress", "type": "filter", "hook": "output", "prio": 10, "policy": "{policy}"}}}},
+      {ALLOW_RULES},
+      {{"rule": {{"family": "inet", "table": "strict-egress", "chain": "egress", "handle": 20,
+                "expr": [{{"limit": {{"rate": 10, "per": "minute"}}}}, {{"log": {{"prefix": "{prefix}", "level": "info"}}}}, {{"counter": null}}{drop}]}}}}
+    ]}}"#
+        );
+        nft::parse(&text).unwrap()
+    }
+
+    fn cache() -> Vec<Resolved> {
+        egress::parse_resolved("cache.nixos.org 151.101.2.217\ncache.nixos.org 151.101.66.217\n")
+    }
+
+    #[test]
+    fn explains_set_accept_step_by_step() {
+        let r = simulate(&ruleset(false), &cache(), "cache.nixos.org", 443, "tcp", None, vec![]).unwrap();
+        assert_eq!(r.resolution, "resolver-cache");
+        assert_eq!(r.mode, "enforcing");
+        let first = &r.addresses[0];
+        assert_eq!(first.verdict, Verdict::Accept);
+        assert_eq!(first.rule, Some(18));
+        assert_eq!(first.set.as_deref(), Some("egress_dyn4 contains 151.101.2.217, expires in 600s"));
+        assert!(first.steps[0].starts_with("table inet strict-egress, chain egress (hook output, priority 10, policy drop)"));
+        // The second address aged out of the set: blocked and logged. The
+        // logging drop is rate-limited, so the policy is what decides.
+        let second = &r.addresses[1];
+        assert_eq!(second.verdict, Verdict::Drop);
+        assert_eq!(second.rule, None);
+        assert_eq!(second.logged_as.as_deref(), Some("STRICT-EGRESS-BLOCKED"));
+        assert!(second.steps.iter().any(|s| s.starts_with("rule 20 ") && s.contains("below its rate limit")));
+        assert!(r.summary.contains("accepted on 1 of 2"));
+    }
+
+    #[test]
+    fn hypothetical_allow_and_literal_ips() {
+        let rs = ruleset(false);
+        let extra = parse_extra(&[IpOrCidr::new("151.101.64.0/18")]).unwrap();
+        let r = simulate(&rs, &cache(), "cache.nixos.org", 443, "tcp", None, extra).unwrap();
+        assert!(r.addresses.iter().all(|a| a.verdict == Verdict::Accept));
+        assert_eq!(r.hypothetical, ["151.101.64.0/18"]);
+
+        let r = simulate(&rs, &[], "140.82.121.4", 22, "tcp", None, vec![]).unwrap();
+        assert_eq!(r.resolution, "literal");
+        assert_eq!(r.addresses[0].rule, Some(16));
+        assert!(parse_extra(&[IpOrCidr::new("10.0.0.0/33")]).unwrap_err().starts_with("[denied]"));
+        assert!(parse_extra(&[IpOrCidr::new("example.org")]).is_err());
+    }
+
+    #[test]
+    fn uncached_hosts_and_dry_run() {
+        let r = simulate(&ruleset(false), &cache(), "telemetry.example.com", 123, "udp", None, vec![]).unwrap();
+        assert_eq!(r.resolution, "not-cached");
+        assert_eq!(r.addresses[0].verdict, Verdict::Accept, "NTP is allowed by port for any address");
+        assert!(r.summary.contains("not in the resolver cache"));
+
+        let r = simulate(&ruleset(true), &cache(), "example.net", 443, "tcp", None, vec![]).unwrap();
+        assert_eq!(r.mode, "dry-run");
+        let a = &r.addresses[0];
+        assert_eq!(a.verdict, Verdict::Accept);
+        assert_eq!(a.rule, None);
+        assert_eq!(a.logged_as.as_deref(), Some("STRICT-EGRESS-WOULDBLOCK"));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let sim = |host: &str, proto: Option<&str>| Simulation {
+            host: Host::new(host),
+            port: None,
+            proto: proto.map(String::from),
+            uid: None,
+            extra_allow: vec![],
+        };
+        assert!(run(&sim("exa mple.org", None)).starts_with("[denied]"));
+        assert!(run(&sim("example.org", Some("gre"))).starts_with("[denied]"));
+    }
+}
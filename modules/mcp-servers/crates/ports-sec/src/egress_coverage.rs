@@ -3,9 +3,11 @@
 //! whose remote endpoints aren't on the allowlist) and **proposes** exact
 //! `nft add element …` lines as text — never applies them.
 
-use crate::known_endpoints::{KNOWN, summary};
+use oligarchy_mcp_core::known_endpoints::{KNOWN, summary};
+use oligarchy_mcp_core::nft;
 use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
 use std::collections::HashSet;
+use std::net::IpAddr;
 
 pub fn report() -> String {
     // Pull the live ruleset so we can diff our expected remotes against it.
@@ -67,18 +69,26 @@ pub fn report() -> String {
 
 /// Reduce the live ruleset JSON + resolver log into the set of hostnames
 /// already covered. The nft JSON contains set elements with the resolved IP
-/// strings but not the originating hostnames — so we use the resolver log
-/// (which lists "<host> <ip>" pairs) as the source of hostnames.
-fn allowed_tokens(_live: &str, resolved: &str) -> HashSet<&'static str> {
-    // The resolver log is line-oriented `<hostname> <ip>`; we hash the
-    // hostname string and compare to the static table. We turn the log
-    // hostname into a &'static str by matching against `KNOWN` (the static
-    // set drives the comparison anyway, so this gives us set intersection
-    // semantics for free).
+/// strings but not the originating hostnames — so the resolver log (which
+/// lists "<host> <ip>" pairs) supplies the hostnames, and the parsed ruleset
+/// confirms at least one of each host's IPs is actually loaded in a set. A
+/// resolved IP that has aged out of the sets does not count. When the ruleset
+/// cannot be read (no table, no privileges) the resolver log alone decides.
+fn allowed_tokens(live: &str, resolved: &str) -> HashSet<&'static str> {
+    let ruleset = nft::parse(live).ok();
+    let loaded = |ip: Option<&str>| match (&ruleset, ip.and_then(|i| i.parse::<IpAddr>().ok())) {
+        (None, _) => true,
+        (Some(rs), Some(addr)) => !rs.sets_containing(addr).is_empty(),
+        (Some(_), None) => false,
+    };
     let mut allowed: HashSet<&'static str> = HashSet::new();
     for ep in KNOWN {
         for remote in ep.remote_endpoints {
-            if resolved.lines().any(|line| line.split_whitespace().next() == Some(*remote)) {
+            let covered = resolved.lines().any(|line| {
+                let mut cols = line.split_whitespace();
+                cols.next() == Some(*remote) && loaded(cols.next())
+            });
+            if covered {
                 allowed.insert(remote);
             }
         }
@@ -96,4 +106,22 @@ mod tests {
         let r = report();
         assert!(r.contains("egress coverage"));
     }
+
+    #[test]
+    fn host_counts_only_if_an_ip_is_loaded() {
+        let Some(remote) = KNOWN.iter().flat_map(|ep| ep.remote_endpoints).next() else {
+            return;
+        };
+        let live = r#"{"nftables": [
+          {"table": {"family": "inet", "name": "strict-egress"}},
+          {"set": {"family": "inet", "table": "strict-egress", "name": "egress_dyn4", "type": "ipv4_addr",
+                   "elem": [{"elem": {"val": "198.51.100.7", "timeout": 93600, "expires": 600}}]}}
+        ]}"#;
+        let fresh = format!("{remote} 198.51.100.7\n");
+        let stale = format!("{remote} 203.0.113.9\n");
+        assert!(allowed_tokens(live, &fresh).contains(remote));
+        assert!(!allowed_tokens(live, &stale).contains(remote), "aged-out IP must not count");
+        // Without a readable ruleset the resolver log decides on its own.
+        assert!(allowed_tokens("[no strict-egress table: denied]", &stale).contains(remote));
+    }
 }
\ No newline at end of file
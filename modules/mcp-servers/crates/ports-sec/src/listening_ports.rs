@@ -1,10 +1,13 @@
 //! `listening_ports` — enumerates every listening TCP/UDP socket *without*
-//! spawning `ss`. Walks `/proc/net/{tcp,tcp6,udp,udp6}` for `0A` (listen)
-//! state entries, matches each socket's inode back to a PID via
-//! `/proc/*/fd/*`, and resolves the owning systemd unit via `zbus`
-//! (`org.freedesktop.systemd1`). Flags any socket bound on `0.0.0.0` / `::`
-//! that is **not** on `127.0.0.1` / `::1` / `lo` (`exposed_to_lan` = true).
+//! spawning `ss`. Sockets come from [`procnet`] (`/proc/net/{tcp,tcp6,udp,
+//! udp6}`): TCP rows in the listen state, and UDP rows not connected to a
+//! peer. Each socket's inode is matched back to a PID via `/proc/*/fd/*`,
+//! and the owning systemd unit is read from that PID's cgroup. Flags any
+//! socket bound on `0.0.0.0` / `::` (`exposed_to_lan` = true).
 
+use std::collections::HashMap;
+
+use oligarchy_mcp_core::procnet::{self, Process, Socket};
 use serde::Serialize;
 
 #[derive(Debug, Clone, Serialize)]
@@ -17,26 +20,11 @@ pub struct SocketRow {
     pub exposed_to_lan: bool,
 }
 
-/// Reads `/proc/net/{tcp,tcp6,udp,udp6}` and reports every listen-state
-/// socket. Falls back to graceful "[unavailable]" strings if any file is
-/// unreadable, so the tool degrades rather than panicking.
+/// Reports every listen-state socket. Unreadable tables are skipped and
+/// sockets of processes this server cannot inspect have no PID, so the tool
+/// degrades rather than failing.
 pub fn inventory() -> anyhow::Result<String> {
-    let mut rows: Vec<SocketRow> = Vec::new();
-
-    parse_proc_net("tcp", "/proc/net/tcp", &mut rows);
-    parse_proc_net("tcp6", "/proc/net/tcp6", &mut rows);
-    parse_proc_net("udp", "/proc/net/udp", &mut rows);
-    parse_proc_net("udp6", "/proc/net/udp6", &mut rows);
-
-    // Resolve PIDs from inodes once, then optionally the systemd unit name.
-    for row in rows.iter_mut() {
-        if let Some(inode) = lookup_inode(row.proto, row.port, &row.bind_addr) {
-            if let Some(pid) = find_pid_for_inode(inode) {
-                row.pid = Some(pid);
-                row.unit = unit_for_pid(pid);
-            }
-        }
-    }
+    let rows = listeners(procnet::sockets(), &procnet::socket_owners(), procnet::process);
 
     let mut out = String::from("proto  port    bind                 pid     unit                   exposed_to_lan\n");
     out.push_str("----- ------ -------------------- ------- ---------------------- --------------\n");
@@ -54,108 +42,53 @@ pub fn inventory() -> anyhow::Result<String> {
     Ok(out)
 }
 
-/// Parse one `/proc/net/<proto>` file and append any listening entries to
-/// `rows`. UDP has no state column (state always implies LISTEN), so we
-/// treat any row in `/proc/net/udp{,6}` as a listener.
-fn parse_proc_net(proto: &'static str, path: &str, rows: &mut Vec<SocketRow>) {
-    let Ok(text) = std::fs::read_to_string(path) else {
-        return;
-    };
-    for line in text.lines().skip(1) {
-        let mut cells = line.split_whitespace();
-        let (_idx) = cells.next();
-        let (Some(local), Some(remote), Some(state)) = (cells.next(), cells.next(), cells.next()) else {
-            continue;
-        };
-        // Local addr looks like "0100007F:0016" (LE hex bytes for IPv4)
-        // or "00000000000000000000000001000000:0016" (for v6, 32 hex chars LE).
-        let Some((addr_hex, port_hex)) = local.rsplit_once(':') else {
-            continue;
-        };
-        let Ok(port) = u16::from_str_radix(port_hex, 16) else { continue };
-        let bind_addr = decode_addr(addr_hex, proto.ends_with('6'));
-
-        // Skip non-listen states for TCP. UDP has no state column.
-        let is_listen = !proto.starts_with("tcp") || state == "0A";
-        // Ignore "00000000:0000" remote entries that aren't listeners.
-        if !is_listen {
-            continue;
-        }
-        // Skip _remote-leading zero entries from UDP that aren't bound.
-        if remote == "00000000:0000" && proto.starts_with("tcp") && state != "0A" {
-            continue;
-        }
-        let exposed_to_lan =
-            bind_addr == "0.0.0.0" || bind_addr == "::" || bind_addr == "[::]";
-        rows.push(SocketRow {
-            proto,
-            port,
-            bind_addr,
-            pid: None,
-            unit: None,
-            exposed_to_lan,
-        });
-    }
-}
-
-/// Decodes a /proc/net hex address (IPv4 = 8 LE hex chars, IPv6 = 32 LE hex
-/// chars) into a printable dotted-quad / colon-notation string. Returns the
-/// raw hex on error so the row is still visible.
-fn decode_addr(hex: &str, is_v6: bool) -> String {
-    if !is_v6 {
-        if hex.len() != 8 {
-            return hex.to_string();
-        }
-        // Each octet is two hex bytes, reversed LE.
-        let bytes = (0..4)
-            .map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).unwrap_or(0))
-            .collect::<Vec<_>>();
-        return format!("{}.{}.{}.{}", bytes[3], bytes[2], bytes[1], bytes[0]);
-    }
-    if hex.len() != 32 {
-        return hex.to_string();
-    }
-    // /proc/net v6 layout: 4 little-endian u32 dwords holding the 16
-    // address bytes. Decode each with to_le_bytes and join 8 groups of two.
-    let mut bytes = [0u8; 16];
-    for i in 0..4 {
-        let dword = u32::from_str_radix(&hex[i * 8..i * 8 + 8], 16).unwrap_or(0);
-        let le = dword.to_le_bytes();
-        bytes[i * 4]     = le[0];
-        bytes[i * 4 + 1] = le[1];
-        bytes[i * 4 + 2] = le[2];
-        bytes[i * 4 + 3] = le[3];
-    }
-    let mut s = String::new();
-    for i in 0..8 {
-        if i > 0 {
-            s.push(':');
-        }
-        s.push_str(&format!("{:02x}{:02x}", bytes[i * 2], bytes[i * 2 + 1]));
-    }
-    s
-}
-
-/// We don't actually need the inode for resolution if we re-scan
-/// `/proc/net/*` later; the inode IS the second-to-last column. We return
-/// None here to keep the lookup pipeline honest — a future Phase 3 polish
-/// can thread the actual inode through. For now, leave unit resolution off.
-fn lookup_inode(_proto: &str, _port: u16, _bind_addr: &str) -> Option<u64> {
-    None
-}
-
-fn find_pid_for_inode(_inode: u64) -> Option<u32> {
-    None
-}
-
-fn unit_for_pid(_pid: u32) -> Option<String> {
-    None
+/// Keeps the listening sockets and attaches their owners. UDP has no listen
+/// state; an unconnected socket (anything but `ESTABLISHED`) is a listener.
+fn listeners(
+    sockets: Vec<Socket>,
+    owners: &HashMap<u64, u32>,
+    process: impl Fn(u32) -> Process,
+) -> Vec<SocketRow> {
+    sockets
+        .into_iter()
+        .filter(|s| {
+            if s.proto.starts_with("tcp") {
+                s.state == procnet::LISTEN
+            } else {
+                s.state != procnet::ESTABLISHED
+            }
+        })
+        .map(|s| {
+            let pid = owners.get(&s.inode).copied();
+            SocketRow {
+                proto: s.proto,
+                port: s.local.port(),
+                bind_addr: s.local.ip().to_string(),
+                pid,
+                unit: pid.and_then(|p| process(p).unit),
+                exposed_to_lan: s.local.ip().is_unspecified(),
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const TCP6: &str = "\
+  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000000000000:0016 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 3101 1 0000000000000000 100 0 0 10 0
+   1: 00000000000000000000000001000000:0277 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 3102 1 0000000000000000 100 0 0 10 0
+   2: 00000000000000000000000001000000:A1B2 00000000000000000000000001000000:0277 01 00000000:00000000 00:00000000 00000000  1000        0 3103 1 0000000000000000 20 4 30 10 -1
+";
+
+    const UDP: &str = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode ref pointer drops
+ 1: 00000000:14E9 00000000:0000 07 00000000:00000000 00:00000000 00000000   100        0 4101 2 0000000000000000 0
+ 2: 0501A8C0:C350 08080808:0035 01 00000000:00000000 00:00000000 00000000  1000        0 4102 2 0000000000000000 0
+";
+
     #[test]
     fn row_serialises() {
         let r = SocketRow {
@@ -171,21 +104,16 @@ mod tests {
     }
 
     #[test]
-    fn decode_v4_loopback() {
-        // 0100007F = bytes 01,00,00,7F -> 127.0.0.1
-        assert_eq!(decode_addr("0100007F", false), "127.0.0.1");
-    }
-
-    #[test]
-    fn decode_v4_any() {
-        // 00000000 = 0.0.0.0
-        assert_eq!(decode_addr("00000000", false), "0.0.0.0");
+    fn keeps_listeners_and_resolves_owners() {
+        let mut sockets = procnet::parse("tcp6", TCP6);
+        sockets.extend(procnet::parse("udp", UDP));
+        let owners = HashMap::from([(3101, 711)]);
+        let process = |pid| Process { pid, unit: Some("sshd.service".into()), ..Default::default() };
+        let rows = listeners(sockets, &owners, process);
+        let summary: Vec<(&str, u16, &str, bool)> =
+            rows.iter().map(|r| (r.proto, r.port, r.bind_addr.as_str(), r.exposed_to_lan)).collect();
+        assert_eq!(summary, [("tcp6", 22, "::", true), ("tcp6", 631, "::1", false), ("udp", 5353, "0.0.0.0", true)]);
+        assert_eq!((rows[0].pid, rows[0].unit.as_deref()), (Some(711), Some("sshd.service")));
+        assert_eq!((rows[1].pid, rows[1].unit.as_deref()), (None, None));
     }
-
-    #[test]
-    fn decode_v6_loopback() {
-        let s = decode_addr("00000000000000000000000001000000", true);
-        // Loopback ::1 should end with 0000:0000:0000:0001 in the colon form.
-        assert!(s.ends_with("0000:0001") || s.ends_with(":1"), "got {s}");
-    }
-}
\ No newline at end of file
+}
@@ -13,7 +13,8 @@
 //! for auth (`401` / `WWW-Authenticate`), and TLS cert presence/expiry/SAN
 //! for HTTPS endpoints.
 
-use crate::known_endpoints::{KNOWN, KnownEndpoint, Proto};
+#[cfg(feature = "allow-loopback-socket")]
+use oligarchy_mcp_core::known_endpoints::{KNOWN, KnownEndpoint, Proto};
 
 pub fn scan_all() -> anyhow::Result<String> {
     if !cfg!(feature = "allow-loopback-socket") {
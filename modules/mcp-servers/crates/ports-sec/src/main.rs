@@ -13,7 +13,6 @@ use oligarchy_mcp_core::runner_mcp;
 
 use rmcp::{ServerHandler, model::{ServerCapabilities, ServerInfo}, tool};
 
-mod known_endpoints;
 mod listening_ports;
 mod egress_coverage;
 mod local_api_scan;
@@ -113,7 +112,7 @@ async fn main() -> anyhow::Result<()> {
 
     tracing_subscriber::fmt().init();
     tracing::info!(aspect = ASPECT, "starting");
-    runner_mcp::serve(Server::default()).await
+    runner_mcp::serve(Server).await
 }
 
 #[cfg(test)]
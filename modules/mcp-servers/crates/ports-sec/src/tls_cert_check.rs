@@ -9,7 +9,7 @@
 pub fn report() -> anyhow::Result<String> {
     let acme = std::path::Path::new("/var/lib/acme");
     if !acme.is_dir() {
-        return Ok(format!("[absent] /var/lib/acme not present on this host"));
+        return Ok("[absent] /var/lib/acme not present on this host".into());
     }
     let mut out = String::from("── TLS cert check (/var/lib/acme) ──────────────────\n");
 
@@ -0,0 +1,176 @@
+//! `sops_files`: every sops-encrypted file in the flake, its metadata, and
+//! whether its recipients still match the `.sops.yaml` creation rule for its
+//! path. Nothing is decrypted; see [`oligarchy_mcp_core::sops`] for what is read.
+
+use std::path::Path;
+
+use oligarchy_mcp_core::sandbox;
+use oligarchy_mcp_core::sops::{self, Metadata, Recipient};
+use serde::Serialize;
+
+use crate::rules::{self, CreationRule};
+
+/// Files above this size are not sops documents anyone edits by hand.
+pub const MAX_FILE_BYTES: u64 = 1 << 20;
+/// Directories never worth descending into.
+const SKIP_DIRS: &[&str] = &[".git", "target", "node_modules", ".direnv"];
+
+#[derive(Debug, Serialize)]
+pub struct FileReport {
+    pub path: String,
+    #[serde(flatten)]
+    pub metadata: Metadata,
+    /// Index into `.sops.yaml` `creation_rules`.
+    pub rule: Option<usize>,
+    /// `match`, `mismatch`, `no-rule` or `no-config`.
+    pub rule_status: &'static str,
+    /// In the rule but not in the file: cannot decrypt it until re-keyed.
+    pub missing: Vec<Recipient>,
+    /// In the file but not in the rule: still can decrypt it.
+    pub extra: Vec<Recipient>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub flake: String,
+    pub config: Option<String>,
+    pub rules: usize,
+    pub files: Vec<FileReport>,
+    /// Files whose recipients differ from their creation rule.
+    pub mismatched: usize,
+    /// Files no creation rule covers; not compared.
+    pub no_rule: usize,
+    /// Files found while there is no `.sops.yaml`; not compared.
+    pub no_config: usize,
+    pub skipped: Vec<String>,
+}
+
+pub fn report() -> String {
+    match scan() {
+        Ok(r) => oligarchy_mcp_core::report::json(&r),
+        Err(e) => format!("[error] {e}"),
+    }
+}
+
+/// Inspects every candidate file under the flake directory.
+pub fn scan() -> Result<Report, String> {
+    let flake = sandbox::flake_dir();
+    let rules = rules::load(&flake)?;
+    let config = rules.is_some().then(|| ".sops.yaml".to_string());
+    let mut candidates = Vec::new();
+    walk(&flake, &flake, &mut candidates);
+    candidates.sort();
+
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    for rel in candidates {
+        match sandbox::read_file(&flake, &rel, MAX_FILE_BYTES as usize + 1) {
+            Ok(text) => {
+                if let Some(f) = inspect_file(&rel, &text, rules.as_deref()) {
+                    files.push(f);
+                }
+            }
+            Err(e) => skipped.push(format!("{rel}: {e}")),
+        }
+    }
+    Ok(Report {
+        flake: flake.display().to_string(),
+        config,
+        rules: rules.as_ref().map_or(0, Vec::len),
+        mismatched: count(&files, "mismatch"),
+        no_rule: count(&files, "no-rule"),
+        no_config: count(&files, "no-config"),
+        files,
+        skipped,
+    })
+}
+
+fn count(files: &[FileReport], status: &str) -> usize {
+    files.iter().filter(|f| f.rule_status == status).count()
+}
+
+/// Relative paths of files whose extension sops could have written, without
+/// following symlinks (a `result` link would lead into the store).
+fn walk(base: &Path, dir: &Path, out: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for e in entries.flatten() {
+        let Ok(ft) = e.file_type() else { continue };
+        let name = e.file_name().to_string_lossy().into_owned();
+        if ft.is_dir() && !SKIP_DIRS.contains(&name.as_str()) {
+            walk(base, &e.path(), out);
+        } else if ft.is_file()
+            && name != ".sops.yaml"
+            && sops::format_for(&name).is_some()
+            && e.metadata().is_ok_and(|m| m.len() <= MAX_FILE_BYTES)
+        {
+            if let Ok(rel) = e.path().strip_prefix(base) {
+                out.push(rel.to_string_lossy().into_owned());
+            }
+        }
+    }
+}
+
+/// The report for one file, or None if it is not sops-encrypted.
+pub fn inspect_file(rel: &str, text: &str, rules: Option<&[CreationRule]>) -> Option<FileReport> {
+    let metadata = sops::inspect(sops::format_for(rel)?, text)?;
+    let (rule, rule_status, missing, extra) = match rules {
+        None => (None, "no-config", Vec::new(), Vec::new()),
+        Some(rules) => match rules::rule_for(rules, rel) {
+            None => (None, "no-rule", Vec::new(), Vec::new()),
+            Some(i) => {
+                let want = &rules[i].recipients;
+                let missing: Vec<Recipient> = want.difference(&metadata.recipients).cloned().collect();
+                let extra: Vec<Recipient> = metadata.recipients.difference(want).cloned().collect();
+                let status = if missing.is_empty() && extra.is_empty() { "match" } else { "mismatch" };
+                (Some(i), status, missing, extra)
+            }
+        },
+    };
+    Some(FileReport { path: rel.to_string(), metadata, rule, rule_status, missing, extra })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = "\
+creation_rules:
+  - path_regex: secrets/.*
+    age: age1host
+";
+
+    #[test]
+    fn compares_recipients_with_rule() {
+        let rules = rules::parse(RULES).unwrap();
+        let env = |recipient: &str| {
+            format!("A=ENC[AES256_GCM,data:eA==,type:str]\nsops_age__list_0__map_recipient={recipient}\nsops_version=3.9.0\n")
+        };
+        let ok = inspect_file("secrets/a.env", &env("age1host"), Some(&rules)).unwrap();
+        assert_eq!(ok.rule_status, "match");
+        let stale = inspect_file("secrets/b.env", &env("age1old"), Some(&rules)).unwrap();
+        assert_eq!(stale.rule_status, "mismatch");
+        assert_eq!(stale.missing, [Recipient::new("age", "age1host")]);
+        assert_eq!(stale.extra, [Recipient::new("age", "age1old")]);
+        assert_eq!(inspect_file("other/c.env", &env("age1host"), Some(&rules)).unwrap().rule_status, "no-rule");
+        assert_eq!(inspect_file("secrets/d.env", &env("age1host"), None).unwrap().rule_status, "no-config");
+        assert!(inspect_file("secrets/plain.env", "A=1\n", Some(&rules)).is_none());
+        let files = [ok, stale, inspect_file("other/c.env", &env("age1host"), Some(&rules)).unwrap()];
+        assert_eq!((count(&files, "mismatch"), count(&files, "no-rule"), count(&files, "no-config")), (1, 1, 0));
+    }
+
+    #[test]
+    fn walks_without_following_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("secrets")).unwrap();
+        std::fs::create_dir_all(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join("secrets/a.enc.yaml"), "sops: {}\n").unwrap();
+        std::fs::write(dir.path().join(".git/config.json"), "{}").unwrap();
+        std::fs::write(dir.path().join("flake.nix"), "{}").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("secrets"), dir.path().join("result")).unwrap();
+        let mut out = Vec::new();
+        walk(dir.path(), dir.path(), &mut out);
+        assert_eq!(out, ["secrets/a.enc.yaml"]);
+    }
+}
@@ -0,0 +1,334 @@
+//! `age_identities`: which `.sops.yaml` rules and which encrypted files the
+//! local age identities can decrypt.
+//!
+//! Identities are read from where sops looks for them (`SOPS_AGE_KEY_FILE`,
+//! else `$XDG_CONFIG_HOME/sops/age/keys.txt`). Their recipients are derived
+//! with `age-keygen -y <file>`, so the secret key is handled by age itself;
+//! when that is unavailable (or the file holds only plugin identities) the
+//! `# public key:` / `# Recipient:` comments age-keygen and the plugins write
+//! are used instead. This module reads key files only to count identities and
+//! collect those comments: no non-comment line is kept, and only `age1…`
+//! strings ever reach the report.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use oligarchy_mcp_core::report;
+use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
+use oligarchy_mcp_core::sandbox;
+use oligarchy_mcp_core::sops::Recipient;
+use serde::Serialize;
+
+use crate::files::{self, FileReport};
+use crate::rules::{self, CreationRule};
+use crate::ASPECT;
+
+/// An identity file larger than this is not one anyone wrote by hand.
+const MAX_KEY_FILE_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Serialize)]
+struct KeyFile {
+    path: String,
+    /// `env` (`SOPS_AGE_KEY_FILE`) or `default`.
+    source: &'static str,
+    /// `ok`, `absent`, `unreadable` or `too-large`.
+    status: &'static str,
+    /// Native `AGE-SECRET-KEY-1…` identities.
+    identities: usize,
+    /// `AGE-PLUGIN-…` identities (YubiKey, TPM, …).
+    plugin_identities: usize,
+    recipients: Vec<String>,
+    /// `age-keygen` or `comments`; None when nothing was derived.
+    derived_by: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+struct RuleAccess {
+    index: usize,
+    path_regex: Option<String>,
+    key_groups: usize,
+    /// Local recipients among the rule's.
+    holds: Vec<String>,
+    access: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct FileAccess {
+    path: String,
+    key_groups: usize,
+    shamir_threshold: Option<u64>,
+    /// Local recipients among the file's.
+    holds: Vec<String>,
+    access: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    key_files: Vec<KeyFile>,
+    recipients: Vec<String>,
+    rules: Vec<RuleAccess>,
+    files: Vec<FileAccess>,
+    decryptable: usize,
+    not_decryptable: usize,
+    notes: Vec<String>,
+}
+
+/// What a key file holds, without its secrets.
+#[derive(Debug, Default, PartialEq)]
+struct Scanned {
+    identities: usize,
+    plugin_identities: usize,
+    /// From `# public key:` and `# Recipient:` comments.
+    commented: Vec<String>,
+}
+
+pub fn run() -> String {
+    let key_files: Vec<KeyFile> = key_file_paths().into_iter().map(|(source, path)| key_file(source, path)).collect();
+    let scan = match files::scan() {
+        Ok(s) => s,
+        Err(e) => return format!("[error] {e}"),
+    };
+    let rules = match rules::load(&sandbox::flake_dir()) {
+        Ok(r) => r.unwrap_or_default(),
+        Err(e) => return format!("[error] {e}"),
+    };
+    let mut notes = Vec::new();
+    if std::env::var_os("SOPS_AGE_KEY").is_some() {
+        notes.push("SOPS_AGE_KEY is set; identities given inline are not inspected".to_string());
+    }
+    if scan.config.is_none() {
+        notes.push("no .sops.yaml in the flake; only files are checked".to_string());
+    }
+    report::json(&build(key_files, &rules, &scan.files, notes))
+}
+
+/// Where sops looks for age identities, in its order.
+fn key_file_paths() -> Vec<(&'static str, PathBuf)> {
+    if let Some(p) = std::env::var_os("SOPS_AGE_KEY_FILE").filter(|p| !p.is_empty()) {
+        return vec![("env", PathBuf::from(p))];
+    }
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| "/home".into())).join(".config"));
+    vec![("default", config.join("sops/age/keys.txt"))]
+}
+
+fn key_file(source: &'static str, path: PathBuf) -> KeyFile {
+    let mut kf = KeyFile {
+        path: path.display().to_string(),
+        source,
+        status: "ok",
+        identities: 0,
+        plugin_identities: 0,
+        recipients: Vec::new(),
+        derived_by: None,
+    };
+    let text = match std::fs::metadata(&path) {
+        Err(_) => {
+            kf.status = "absent";
+            return kf;
+        }
+        Ok(m) if m.len() > MAX_KEY_FILE_BYTES => {
+            kf.status = "too-large";
+            return kf;
+        }
+        Ok(_) => match std::fs::read_to_string(&path) {
+            Ok(t) => t,
+            Err(_) => {
+                kf.status = "unreadable";
+                return kf;
+            }
+        },
+    };
+    let scanned = scan_identities(&text);
+    kf.identities = scanned.identities;
+    kf.plugin_identities = scanned.plugin_identities;
+    let derived = if scanned.identities > 0 {
+        runner::run(ASPECT, "age-keygen", &["-y", &kf.path], QUICK_TIMEOUT)
+            .map(|out| recipients_from_keygen(&out))
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    if !derived.is_empty() {
+        kf.derived_by = Some("age-keygen");
+        kf.recipients = derived;
+    }
+    // Plugin identities are not understood by `age-keygen -y`; their
+    // recipients only exist as comments.
+    let missing: Vec<String> =
+        scanned.commented.into_iter().filter(|r| !kf.recipients.contains(r)).collect();
+    if !missing.is_empty() && (kf.derived_by.is_none() || scanned.plugin_identities > 0) {
+        kf.derived_by.get_or_insert("comments");
+        kf.recipients.extend(missing);
+    }
+    kf
+}
+
+/// Counts identities and collects recipient comments. Identity lines are
+/// matched by prefix and dropped.
+fn scan_identities(text: &str) -> Scanned {
+    let mut s = Scanned::default();
+    for line in text.lines().map(str::trim) {
+        if let Some(comment) = line.strip_prefix('#') {
+            let comment = comment.trim();
+            let value = comment
+                .split_once(':')
+                .filter(|(k, _)| k.trim().eq_ignore_ascii_case("public key") || k.trim().eq_ignore_ascii_case("recipient"))
+                .map(|(_, v)| v.trim());
+            if let Some(r) = value.filter(|v| is_recipient(v)) {
+                if !s.commented.iter().any(|c| c == r) {
+                    s.commented.push(r.to_string());
+                }
+            }
+        } else if line.starts_with("AGE-SECRET-KEY-") {
+            s.identities += 1;
+        } else if line.starts_with("AGE-PLUGIN-") {
+            s.plugin_identities += 1;
+        }
+    }
+    s
+}
+
+/// Keeps only well-formed recipient lines from `age-keygen -y`, so an error
+/// message can never carry anything else into the report.
+fn recipients_from_keygen(out: &str) -> Vec<String> {
+    let mut v: Vec<String> = Vec::new();
+    for r in out.lines().map(str::trim).filter(|l| is_recipient(l)) {
+        if !v.iter().any(|x| x == r) {
+            v.push(r.to_string());
+        }
+    }
+    v
+}
+
+/// An `age1…` (or plugin `age1<name>1…`) bech32 recipient.
+fn is_recipient(s: &str) -> bool {
+    s.starts_with("age1") && s.len() <= 256 && s.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+}
+
+/// A document needs keys from `shamir_threshold` of its key groups (sops'
+/// default: all of them). `yes` when the local keys cover that many groups,
+/// `share` when they cover some but the other groups' holders are needed
+/// too, else `no`.
+fn access(mine: &BTreeSet<Recipient>, groups: &[BTreeSet<Recipient>], threshold: Option<u64>) -> (Vec<String>, &'static str) {
+    let theirs: BTreeSet<&Recipient> = groups.iter().flatten().collect();
+    let holds: Vec<String> = theirs.into_iter().filter(|r| mine.contains(r)).map(|r| r.id.clone()).collect();
+    let covered = groups.iter().filter(|g| !g.is_disjoint(mine)).count();
+    let needed = threshold
+        .and_then(|t| usize::try_from(t).ok())
+        .unwrap_or(groups.len())
+        .clamp(1, groups.len().max(1));
+    let access = match covered {
+        0 => "no",
+        n if n >= needed => "yes",
+        _ => "share",
+    };
+    (holds, access)
+}
+
+fn build(key_files: Vec<KeyFile>, rules: &[CreationRule], files: &[FileReport], mut notes: Vec<String>) -> Report {
+    let mine: BTreeSet<Recipient> =
+        key_files.iter().flat_map(|k| &k.recipients).map(|r| Recipient::new("age", r)).collect();
+    if mine.is_empty() {
+        notes.push("no local age recipients found; nothing is decryptable with age".to_string());
+    }
+    let rules: Vec<RuleAccess> = rules
+        .iter()
+        .enumerate()
+        .map(|(index, r)| {
+            let (holds, access) = access(&mine, &r.groups, r.shamir_threshold);
+            RuleAccess {
+                index,
+                path_regex: r.path_regex.as_ref().map(|re| re.as_str().to_string()),
+                key_groups: r.groups.len(),
+                holds,
+                access,
+            }
+        })
+        .collect();
+    let files: Vec<FileAccess> = files
+        .iter()
+        .map(|f| {
+            let (holds, access) = access(&mine, &f.metadata.groups, f.metadata.shamir_threshold);
+            FileAccess {
+                path: f.path.clone(),
+                key_groups: f.metadata.key_groups,
+                shamir_threshold: f.metadata.shamir_threshold,
+                holds,
+                access,
+            }
+        })
+        .collect();
+    Report {
+        recipients: mine.iter().map(|r| r.id.clone()).collect(),
+        decryptable: files.iter().filter(|f| f.access == "yes").count(),
+        not_decryptable: files.iter().filter(|f| f.access == "no").count(),
+        key_files,
+        rules,
+        files,
+        notes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ME: &str = "age1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqq";
+    const YUBI: &str = "age1yubikey1qwertyqwertyqwertyqwertyqwertyqwertyqwerty";
+
+    #[test]
+    fn scans_without_keeping_secrets() {
+        let text = format!(
+            "# created: 2026-01-01T00:00:00Z\n# public key: {ME}\nAGE-SECRET-KEY-1LEAKLEAKLEAK\n\
+             #       Serial: 123, Slot: 1\n#    Recipient: {YUBI}\nAGE-PLUGIN-YUBIKEY-1LEAK\n# public key: not-a-key\n"
+        );
+        let s = scan_identities(&text);
+        assert_eq!(s, Scanned { identities: 1, plugin_identities: 1, commented: vec![ME.into(), YUBI.into()] });
+        assert_eq!(recipients_from_keygen(&format!("{ME}\nError: AGE-SECRET-KEY-1LEAK\n")), [ME]);
+    }
+
+    #[test]
+    fn reports_decryptable_rules_and_files() {
+        let rules = rules::parse(&format!(
+            "creation_rules:\n  - path_regex: mine/.*\n    age: {ME}\n  - path_regex: split/.*\n    key_groups:\n      - age: [{ME}]\n      - age: [age1other]\n\
+             \x20 - path_regex: both/.*\n    key_groups:\n      - age: [{ME}]\n      - age: [{ME}, age1other]\n\
+             \x20 - path_regex: any/.*\n    shamir_threshold: 1\n    key_groups:\n      - age: [{ME}]\n      - age: [age1other]\n\
+             \x20 - age: age1other\n"
+        ))
+        .unwrap();
+        let env = |r: &str| format!("A=ENC[AES256_GCM,data:eA==,type:str]\nsops_age__list_0__map_recipient={r}\n");
+        let grouped = |threshold: u64| {
+            format!(
+                "A=ENC[AES256_GCM,data:eA==,type:str]\nsops_shamir_threshold={threshold}\n\
+                 sops_key_groups__list_0__map_age__list_0__map_recipient={ME}\n\
+                 sops_key_groups__list_1__map_age__list_0__map_recipient=age1other\n"
+            )
+        };
+        let files = vec![
+            files::inspect_file("mine/a.env", &env(ME), Some(&rules)).unwrap(),
+            files::inspect_file("other/b.env", &env("age1other"), Some(&rules)).unwrap(),
+            files::inspect_file("split/c.env", &grouped(2), Some(&rules)).unwrap(),
+            files::inspect_file("any/d.env", &grouped(1), Some(&rules)).unwrap(),
+        ];
+        let kf = KeyFile {
+            path: "keys.txt".into(),
+            source: "default",
+            status: "ok",
+            identities: 1,
+            plugin_identities: 0,
+            recipients: vec![ME.into()],
+            derived_by: Some("age-keygen"),
+        };
+        let r = build(vec![kf], &rules, &files, Vec::new());
+        let rule_access: Vec<&str> = r.rules.iter().map(|r| r.access).collect();
+        assert_eq!(rule_access, ["yes", "share", "yes", "yes", "no"]);
+        assert_eq!(r.files[0].holds, [ME]);
+        let file_access: Vec<&str> = r.files.iter().map(|f| f.access).collect();
+        assert_eq!(file_access, ["yes", "no", "share", "yes"]);
+        assert_eq!((r.decryptable, r.not_decryptable), (2, 1));
+        assert!(!report::json(&r).contains("LEAK"));
+    }
+}
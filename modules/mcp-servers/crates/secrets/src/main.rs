@@ -3,10 +3,14 @@
 //! no CLI or code path that ever writes decrypted material anywhere.
 //! See `docs/mcp-servers-roadmap.md` §4.6.
 
+mod files;
+mod identity;
+mod rotation;
+mod rules;
+
 use oligarchy_mcp_core::audit;
 use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
 use oligarchy_mcp_core::runner_mcp;
-use oligarchy_mcp_core::sandbox;
 
 use rmcp::{ServerHandler, model::{ServerCapabilities, ServerInfo}, tool};
 
@@ -17,33 +21,22 @@ struct Server;
 
 #[tool(tool_box)]
 impl Server {
-    #[tool(description = "List declared sops secret PATHS (redacted metadata; never decrypts). Shows stanzas + key paths from .sops.yaml without revealing secret material.")]
+    #[tool(description = "Redacted .sops.yaml as YAML: creation rules (path_regex, encrypted_regex/suffixes, shamir_threshold) with key group sizes and shortened recipient ids. Parsed structurally; comments and unknown fields are never echoed. Never decrypts.")]
     fn secrets_inventory(&self) -> String {
         audit::tool(ASPECT, "secrets_inventory", "");
-        let flake = sandbox::flake_dir();
-        let sops_yaml = flake.join(".sops.yaml");
-        if !sops_yaml.is_file() {
-            return format!("[absent] no .sops.yaml in flake dir {}", flake.display());
-        }
-        let body = sandbox::read_file(&flake, ".sops.yaml", 8_192).unwrap_or_else(|e| format!("[error] {e}"));
-        // Redact actual key fingerprints; we only return stanzas + key paths.
-        let mut redacted = String::new();
-        for line in body.lines() {
-            let trimmed = line.trim_start();
-            if trimmed.starts_with("path_regex")
-                || trimmed.starts_with("age")
-                || trimmed.starts_with("pgp")
-                || trimmed.starts_with("creation_rules")
-                || trimmed.starts_with('-')
-                || trimmed.starts_with("encrypted_regex")
-            {
-                redacted.push_str(line);
-                redacted.push('\n');
-            } else {
-                redacted.push_str("# (redacted)\n");
-            }
-        }
-        redacted
+        rules::inventory()
+    }
+
+    #[tool(description = "Walk the flake for sops-encrypted YAML/JSON/ENV files and report their sops metadata (recipients, key groups, lastmodified, MAC presence, which key paths are encrypted) and whether the recipients match the .sops.yaml creation rule for the path. Never decrypts; values are not read out.")]
+    fn sops_files(&self) -> String {
+        audit::tool(ASPECT, "sops_files", "");
+        files::report()
+    }
+
+    #[tool(description = "Secret rotation dashboard: per sops file, lastmodified age (fresh/aging/stale against warn_days/stale_days, default 90/180), who can decrypt it, recipients in .sops.yaml missing from the file (needs updatekeys) and recipients to revoke. Never decrypts.")]
+    fn secrets_rotation(&self, #[tool(aggr)] t: rotation::Thresholds) -> String {
+        audit::tool(ASPECT, "secrets_rotation", &t.summary());
+        rotation::run(&t)
     }
 
     #[tool(description = "sops-blackbox inventory (sops-blackbox-ls). Lists sops-encrypted files in the repo.")]
@@ -53,6 +46,12 @@ impl Server {
             .unwrap_or_else(|e| format!("[error] {e}"))
     }
 
+    #[tool(description = "Local age identities (SOPS_AGE_KEY_FILE or ~/.config/sops/age/keys.txt): their recipients, derived with `age-keygen -y` or read from public-key comments, and which .sops.yaml creation rules and sops files they can decrypt (yes / share of a multi-group file / no). Private key material is never returned. Does NOT decrypt.")]
+    fn age_identities(&self) -> String {
+        audit::tool(ASPECT, "age_identities", "");
+        identity::run()
+    }
+
     #[tool(description = "Reports whether the `age` binary is installed and the sops age key directory is present. Does NOT decrypt.")]
     fn age_keys_present(&self) -> String {
         audit::tool(ASPECT, "age_keys_present", "");
@@ -83,7 +82,7 @@ impl ServerHandler for Server {
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt().init();
     tracing::info!(aspect = ASPECT, "starting");
-    runner_mcp::serve(Server::default()).await
+    runner_mcp::serve(Server).await
 }
 
 #[cfg(test)]
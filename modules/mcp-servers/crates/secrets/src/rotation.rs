@@ -0,0 +1,158 @@
+//! `secrets_rotation`: a rotation dashboard over [`crate::files::scan`].
+//!
+//! A file's age is the time since sops last wrote it (`lastmodified`), which
+//! `sops edit`, `rotate` and `updatekeys` all bump — so it measures "last
+//! touched", the closest thing to "last rotated" the metadata records.
+
+use oligarchy_mcp_core::report;
+use oligarchy_mcp_core::sops::Recipient;
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::files::{self, FileReport};
+
+const DEFAULT_WARN_DAYS: u32 = 90;
+const DEFAULT_STALE_DAYS: u32 = 180;
+
+#[derive(Debug, Default, Deserialize, schemars::JsonSchema)]
+pub struct Thresholds {
+    /// Age in days from which a file is reported as `aging`. Default 90.
+    pub warn_days: Option<u32>,
+    /// Age in days from which a file is reported as `stale`. Default 180.
+    pub stale_days: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+struct Entry {
+    path: String,
+    lastmodified: Option<String>,
+    age_days: Option<i64>,
+    /// `fresh`, `aging`, `stale`, or `unknown` without a parseable
+    /// `lastmodified`.
+    status: &'static str,
+    /// Who can decrypt the file today.
+    recipients: Vec<Recipient>,
+    /// In `.sops.yaml` but not in the file: run `sops updatekeys`.
+    needs_updatekeys: Vec<Recipient>,
+    /// In the file but no longer in `.sops.yaml`: rotate the data key after
+    /// `updatekeys`, since they held it.
+    revoke: Vec<Recipient>,
+}
+
+#[derive(Debug, Serialize)]
+struct Dashboard {
+    warn_days: u32,
+    stale_days: u32,
+    files: Vec<Entry>,
+    stale: usize,
+    aging: usize,
+    needs_updatekeys: usize,
+    skipped: Vec<String>,
+}
+
+impl Thresholds {
+    pub fn summary(&self) -> String {
+        format!(
+            "warn={} stale={}",
+            self.warn_days.unwrap_or(DEFAULT_WARN_DAYS),
+            self.stale_days.unwrap_or(DEFAULT_STALE_DAYS)
+        )
+    }
+}
+
+pub fn run(t: &Thresholds) -> String {
+    let warn = t.warn_days.unwrap_or(DEFAULT_WARN_DAYS);
+    let stale = t.stale_days.unwrap_or(DEFAULT_STALE_DAYS);
+    if warn > stale {
+        return format!("[denied] warn_days ({warn}) is above stale_days ({stale})");
+    }
+    match files::scan() {
+        Ok(scan) => report::json(&dashboard(scan.files, scan.skipped, warn, stale, OffsetDateTime::now_utc())),
+        Err(e) => format!("[error] {e}"),
+    }
+}
+
+fn dashboard(files: Vec<FileReport>, skipped: Vec<String>, warn: u32, stale: u32, now: OffsetDateTime) -> Dashboard {
+    let mut entries: Vec<Entry> = files
+        .into_iter()
+        .map(|f| {
+            let age_days = f
+                .metadata
+                .lastmodified
+                .as_deref()
+                .and_then(|l| OffsetDateTime::parse(l, &Rfc3339).ok())
+                .map(|t| (now - t).whole_days());
+            let status = match age_days {
+                None => "unknown",
+                Some(d) if d >= i64::from(stale) => "stale",
+                Some(d) if d >= i64::from(warn) => "aging",
+                Some(_) => "fresh",
+            };
+            Entry {
+                path: f.path,
+                lastmodified: f.metadata.lastmodified,
+                age_days,
+                status,
+                recipients: f.metadata.recipients.into_iter().collect(),
+                needs_updatekeys: f.missing,
+                revoke: f.extra,
+            }
+        })
+        .collect();
+    // Oldest first; unknown ages sort as oldest.
+    entries.sort_by_key(|e| (e.age_days.map_or(i64::MIN, |d| -d), e.path.clone()));
+    Dashboard {
+        warn_days: warn,
+        stale_days: stale,
+        stale: entries.iter().filter(|e| e.status == "stale").count(),
+        aging: entries.iter().filter(|e| e.status == "aging").count(),
+        needs_updatekeys: entries.iter().filter(|e| !e.needs_updatekeys.is_empty()).count(),
+        files: entries,
+        skipped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules;
+
+    fn file(path: &str, lastmodified: &str, recipient: &str) -> FileReport {
+        let rules = rules::parse("creation_rules:\n  - age: age1new\n").unwrap();
+        let text = format!(
+            "A=ENC[AES256_GCM,data:eA==,type:str]\nsops_age__list_0__map_recipient={recipient}\nsops_lastmodified={lastmodified}\n"
+        );
+        files::inspect_file(path, &text, Some(&rules)).unwrap()
+    }
+
+    #[test]
+    fn classifies_by_age_and_lists_rekeys() {
+        let now = OffsetDateTime::parse("2026-01-01T00:00:00Z", &Rfc3339).unwrap();
+        let d = dashboard(
+            vec![
+                file("a.env", "2025-12-20T00:00:00Z", "age1new"),
+                file("b.env", "2025-09-01T00:00:00Z", "age1new"),
+                file("c.env", "2025-03-01T00:00:00Z", "age1old"),
+                file("d.env", "not a date", "age1new"),
+            ],
+            Vec::new(),
+            90,
+            180,
+            now,
+        );
+        let order: Vec<(&str, &str)> = d.files.iter().map(|e| (e.path.as_str(), e.status)).collect();
+        assert_eq!(order, [("d.env", "unknown"), ("c.env", "stale"), ("b.env", "aging"), ("a.env", "fresh")]);
+        assert_eq!(d.files[1].age_days, Some(306));
+        assert_eq!(d.files[1].needs_updatekeys, [Recipient::new("age", "age1new")]);
+        assert_eq!(d.files[1].revoke, [Recipient::new("age", "age1old")]);
+        assert_eq!((d.stale, d.aging, d.needs_updatekeys), (1, 1, 1));
+    }
+
+    #[test]
+    fn rejects_inverted_thresholds() {
+        let t = Thresholds { warn_days: Some(200), stale_days: Some(100) };
+        assert!(run(&t).starts_with("[denied]"));
+    }
+}
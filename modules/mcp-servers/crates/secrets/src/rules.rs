@@ -0,0 +1,376 @@
+//! `.sops.yaml` creation rules: which recipients a new file at a given path
+//! would be encrypted to, and a redacted view of the file for inventory.
+//!
+//! The file is deserialized into [`Config`], which names only the fields
+//! this crate uses; comments, the `keys:` anchor list and unknown fields are
+//! dropped by the parser, so nothing reaches a report unless a field below
+//! puts it there. YAML anchors and aliases (`&host` / `*host`) are resolved
+//! first.
+//!
+//! sops takes the first rule whose `path_regex` matches (unanchored, like
+//! Go's `MatchString`); a rule without `path_regex` matches everything.
+//! Recipients come either from `key_groups` or from the flat `age`/`pgp`
+//! fields, which may be a list or one comma-separated string.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use oligarchy_mcp_core::sandbox;
+use oligarchy_mcp_core::sops::Recipient;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+
+/// A `.sops.yaml` larger than this is not a config anyone maintains by hand;
+/// it is refused rather than truncated into different YAML.
+const MAX_CONFIG_BYTES: u64 = 256 * 1024;
+const CONFIG: &str = ".sops.yaml";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub creation_rules: Vec<RawRule>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RawRule {
+    pub path_regex: Option<String>,
+    pub encrypted_regex: Option<String>,
+    pub unencrypted_regex: Option<String>,
+    pub encrypted_suffix: Option<String>,
+    pub unencrypted_suffix: Option<String>,
+    pub shamir_threshold: Option<u64>,
+    #[serde(default)]
+    pub key_groups: Vec<KeyGroup>,
+    /// The flat form: keys given directly on the rule.
+    #[serde(flatten)]
+    pub flat: KeyGroup,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct KeyGroup {
+    #[serde(default)]
+    pub age: Keys,
+    #[serde(default)]
+    pub pgp: Keys,
+    /// Cloud and Vault keys: counted, not listed.
+    pub kms: Option<Value>,
+    pub gcp_kms: Option<Value>,
+    pub azure_keyvault: Option<Value>,
+    pub hc_vault: Option<Value>,
+    pub hc_vault_transit_uri: Option<Value>,
+}
+
+/// A list of keys, or one comma-separated string of them.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Keys {
+    List(Vec<String>),
+    Joined(String),
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Keys::List(Vec::new())
+    }
+}
+
+impl Keys {
+    pub fn ids(&self) -> Vec<&str> {
+        match self {
+            Keys::List(l) => l.iter().map(|s| s.trim()).filter(|s| !s.is_empty()).collect(),
+            Keys::Joined(s) => s.split(',').map(str::trim).filter(|s| !s.is_empty()).collect(),
+        }
+    }
+}
+
+impl KeyGroup {
+    fn recipients(&self) -> impl Iterator<Item = Recipient> + '_ {
+        let age = self.age.ids().into_iter().map(|id| Recipient::new("age", id));
+        let pgp = self.pgp.ids().into_iter().map(|id| Recipient::new("pgp", id));
+        age.chain(pgp)
+    }
+
+    fn other_keys(&self) -> usize {
+        [&self.kms, &self.gcp_kms, &self.azure_keyvault, &self.hc_vault, &self.hc_vault_transit_uri]
+            .into_iter()
+            .flatten()
+            .map(|v| match v {
+                Value::String(s) => s.split(',').filter(|s| !s.trim().is_empty()).count(),
+                Value::Sequence(items) => items.len(),
+                Value::Null => 0,
+                _ => 1,
+            })
+            .sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.age.ids().is_empty() && self.pgp.ids().is_empty() && self.other_keys() == 0
+    }
+}
+
+impl RawRule {
+    /// The rule's key groups; the flat form is one group.
+    fn groups(&self) -> Vec<&KeyGroup> {
+        if self.key_groups.is_empty() {
+            if self.flat.is_empty() { Vec::new() } else { vec![&self.flat] }
+        } else {
+            self.key_groups.iter().collect()
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CreationRule {
+    pub path_regex: Option<Regex>,
+    pub recipients: BTreeSet<Recipient>,
+    /// `recipients` split by key group; the flat form is one group.
+    pub groups: Vec<BTreeSet<Recipient>>,
+    /// Groups needed to decrypt; sops defaults to all of them.
+    pub shamir_threshold: Option<u64>,
+}
+
+pub fn parse_config(text: &str) -> Result<Config, String> {
+    serde_yaml::from_str::<Option<Config>>(text)
+        .map(Option::unwrap_or_default)
+        .map_err(|e| format!("parsing .sops.yaml: {e}"))
+}
+
+/// Parses the `creation_rules` of a `.sops.yaml`.
+pub fn parse(text: &str) -> Result<Vec<CreationRule>, String> {
+    parse_config(text)?
+        .creation_rules
+        .iter()
+        .enumerate()
+        .map(|(i, rule)| {
+            let path_regex = match &rule.path_regex {
+                Some(re) => Some(Regex::new(re).map_err(|e| format!("creation rule {i}: path_regex: {e}"))?),
+                None => None,
+            };
+            let groups: Vec<BTreeSet<Recipient>> =
+                rule.groups().into_iter().map(|g| g.recipients().collect()).collect();
+            let recipients = groups.iter().flatten().cloned().collect();
+            Ok(CreationRule { path_regex, recipients, groups, shamir_threshold: rule.shamir_threshold })
+        })
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct RedactedConfig {
+    pub creation_rules: Vec<RedactedRule>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RedactedRule {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_regex: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypted_regex: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unencrypted_regex: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypted_suffix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unencrypted_suffix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shamir_threshold: Option<u64>,
+    pub key_groups: Vec<RedactedGroup>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RedactedGroup {
+    pub size: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub age: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pgp: Vec<String>,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub other: usize,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// The inventory view of a config: rule patterns, key group sizes and
+/// shortened recipient ids.
+pub fn redact(config: &Config) -> RedactedConfig {
+    RedactedConfig {
+        creation_rules: config
+            .creation_rules
+            .iter()
+            .map(|r| RedactedRule {
+                path_regex: r.path_regex.clone(),
+                encrypted_regex: r.encrypted_regex.clone(),
+                unencrypted_regex: r.unencrypted_regex.clone(),
+                encrypted_suffix: r.encrypted_suffix.clone(),
+                unencrypted_suffix: r.unencrypted_suffix.clone(),
+                shamir_threshold: r.shamir_threshold,
+                key_groups: r
+                    .groups()
+                    .into_iter()
+                    .map(|g| {
+                        let age: Vec<String> = g.age.ids().into_iter().map(shorten).collect();
+                        let pgp: Vec<String> = g.pgp.ids().into_iter().map(shorten).collect();
+                        let other = g.other_keys();
+                        RedactedGroup { size: age.len() + pgp.len() + other, age, pgp, other }
+                    })
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+/// `age1qyqszq…pq3f2k` style: enough to tell keys apart, not to
+/// copy one. Short ids are kept as they are.
+pub fn shorten(id: &str) -> String {
+    let id: String = id.chars().filter(|c| !c.is_whitespace()).collect();
+    let n = id.chars().count();
+    if n <= 16 {
+        return id;
+    }
+    let head: String = id.chars().take(10).collect();
+    let tail: String = id.chars().skip(n - 6).collect();
+    format!("{head}…{tail}")
+}
+
+/// The text of `flake`'s `.sops.yaml`, read through the sandbox; `Ok(None)`
+/// when there is none. Every reader of the config goes through here, so the
+/// size cap is checked before anything is read.
+pub fn read_config(flake: &Path) -> Result<Option<String>, String> {
+    match std::fs::metadata(flake.join(CONFIG)) {
+        Err(_) => return Ok(None),
+        Ok(m) if m.len() > MAX_CONFIG_BYTES => {
+            return Err(format!("{CONFIG} is {} bytes, over the {MAX_CONFIG_BYTES}-byte limit", m.len()))
+        }
+        Ok(_) => {}
+    }
+    sandbox::read_file(flake, CONFIG, MAX_CONFIG_BYTES as usize).map(Some).map_err(|e| e.to_string())
+}
+
+/// The creation rules of `flake`'s `.sops.yaml`; `Ok(None)` when there is none.
+pub fn load(flake: &Path) -> Result<Option<Vec<CreationRule>>, String> {
+    read_config(flake)?.map(|text| parse(&text)).transpose()
+}
+
+/// `secrets_inventory`: the flake's `.sops.yaml`, redacted, as YAML.
+pub fn inventory() -> String {
+    let flake = sandbox::flake_dir();
+    let text = match read_config(&flake) {
+        Ok(Some(t)) => t,
+        Ok(None) => return format!("[absent] no .sops.yaml in flake dir {}", flake.display()),
+        Err(e) => return format!("[error] {e}"),
+    };
+    match parse_config(&text) {
+        Ok(config) => render(&config),
+        Err(e) => format!("[error] {e}"),
+    }
+}
+
+fn render(config: &Config) -> String {
+    serde_yaml::to_string(&redact(config)).unwrap_or_else(|e| format!("[error] {e}"))
+}
+
+/// Index of the rule that applies to `rel_path` (relative to the directory
+/// holding `.sops.yaml`, `/`-separated).
+pub fn rule_for(rules: &[CreationRule], rel_path: &str) -> Option<usize> {
+    rules
+        .iter()
+        .position(|r| r.path_regex.as_ref().is_none_or(|re| re.is_match(rel_path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Same shape as the flake's own `.sops.yaml`.
+    const SOPS_YAML: &str = "\
+keys:
+  - &host_framework16 age1hosthosthosthosthosthosthosthosthosthosthosthosthost
+  - &admin 85D7 7543 B3D6 24B6 3CEA 9E6D BC17 301B 491B 3F21
+creation_rules:
+  - path_regex: modules/secrets/.*\\.enc\\.(env|yaml|json)$
+    key_groups:
+      - age:
+          - *host_framework16
+        pgp:
+          - *admin
+  - path_regex: secrets/.*
+    age: age1one, age1two
+  - age: age1fallback
+";
+
+    #[test]
+    fn resolves_aliases_and_flat_keys() {
+        let rules = parse(SOPS_YAML).unwrap();
+        assert_eq!(rules.len(), 3);
+        assert!(rules[0].recipients.contains(&Recipient::new("age", "age1hosthosthosthosthosthosthosthosthosthosthosthosthost")));
+        assert!(rules[0].recipients.contains(&Recipient::new("pgp", "85d77543b3d624b63cea9e6dbc17301b491b3f21")));
+        assert_eq!(rules[1].recipients.len(), 2);
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = parse(SOPS_YAML).unwrap();
+        assert_eq!(rule_for(&rules, "modules/secrets/dcf-id.enc.env"), Some(0));
+        assert_eq!(rule_for(&rules, "secrets/wifi.yaml"), Some(1));
+        assert_eq!(rule_for(&rules, "elsewhere/x.yaml"), Some(2));
+        assert_eq!(rule_for(&rules[..2], "elsewhere/x.yaml"), None);
+    }
+
+    #[test]
+    fn reports_bad_regex() {
+        assert!(parse("creation_rules:\n  - path_regex: '('\n").unwrap_err().contains("creation rule 0"));
+        assert!(parse("keys: []\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn inventory_of_the_flake_config() {
+        let out = render(&parse_config(include_str!("../tests/fixtures/flake.sops.yaml")).unwrap());
+        assert_eq!(
+            out,
+            "creation_rules:\n\
+             - path_regex: modules/secrets/.*\\.enc\\.(env|yaml|json)$\n  key_groups:\n  - size: 1\n    age:\n    - age1ql3z7h…mcac8p\n\
+             - path_regex: secrets/.*\n  key_groups:\n  - size: 1\n    age:\n    - age1ql3z7h…mcac8p\n"
+        );
+    }
+
+    #[test]
+    fn inventory_is_structural_not_textual() {
+        let config = parse_config(include_str!("../tests/fixtures/hostile.sops.yaml")).unwrap();
+        let out = render(&config);
+        assert!(!out.contains("LEAK"), "leaked:\n{out}");
+        assert!(!out.contains("AGE-SECRET-KEY"));
+        assert!(!out.contains("ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p"), "full recipient");
+
+        let r = redact(&config);
+        assert_eq!(r.creation_rules.len(), 3);
+        let prod = &r.creation_rules[0];
+        assert_eq!(prod.shamir_threshold, Some(2));
+        assert_eq!(prod.encrypted_regex.as_deref(), Some("^(data|stringData)$"));
+        let sizes: Vec<usize> = prod.key_groups.iter().map(|g| g.size).collect();
+        assert_eq!(sizes, [2, 2]);
+        assert_eq!(prod.key_groups[0].pgp, ["85D77543B3…1B3F21"]);
+        assert_eq!(prod.key_groups[1].other, 2);
+        let dev = &r.creation_rules[1];
+        assert_eq!((dev.key_groups.len(), dev.key_groups[0].size), (1, 3), "flat keys are one group");
+        assert!(r.creation_rules[2].key_groups.is_empty());
+        assert_eq!(r.creation_rules[2].unencrypted_suffix.as_deref(), Some("_plain"));
+    }
+
+    #[test]
+    fn shortens_long_ids_only() {
+        assert_eq!(shorten("age1short"), "age1short");
+        assert_eq!(shorten("85D7 7543 B3D6 24B6 3CEA 9E6D BC17 301B 491B 3F21"), "85D77543B3…1B3F21");
+    }
+
+    #[test]
+    fn loads_config_through_the_cap() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(read_config(dir.path()), Ok(None));
+        std::fs::write(dir.path().join(CONFIG), SOPS_YAML).unwrap();
+        assert_eq!(load(dir.path()).unwrap().map(|r| r.len()), Some(parse(SOPS_YAML).unwrap().len()));
+        std::fs::write(dir.path().join(CONFIG), "#".repeat(MAX_CONFIG_BYTES as usize + 1)).unwrap();
+        assert!(read_config(dir.path()).unwrap_err().contains("limit"));
+    }
+}
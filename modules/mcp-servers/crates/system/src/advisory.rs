@@ -0,0 +1,286 @@
+//! Offline advisory check for `flake_inputs`: package versions in the current
+//! system closure against a vendored advisory list.
+//!
+//! The closure comes from `nix path-info --recursive /run/current-system`;
+//! each store path name is split into pname and version the way
+//! `builtins.parseDrvName` does, and versions are ordered with Nix's own
+//! `compareVersions` rules so the result matches what `nix` would say. The
+//! database is a JSON file inside the flake (sandboxed like `read_module`):
+//!
+//! ```json
+//! { "generated": "2026-10-01",
+//!   "advisories": [ { "id": "CVE-2024-6387", "package": "openssh",
+//!                     "introduced": "8.5p1", "fixed": "9.8p1",
+//!                     "severity": "high", "summary": "regreSSHion" } ] }
+//! ```
+//!
+//! An advisory applies when `introduced <= version < fixed`; either bound may
+//! be omitted.
+
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use oligarchy_mcp_core::runner::{self, HEAVY_TIMEOUT};
+use oligarchy_mcp_core::sandbox;
+use serde::{Deserialize, Serialize};
+
+use crate::ASPECT;
+
+const MAX_DB_BYTES: usize = 16 * 1024 * 1024;
+
+/// Output names Nix appends to a store path name (`openssl-3.0.13-bin`);
+/// stripped so every output of a package is checked as one version.
+const OUTPUT_SUFFIXES: &[&str] = &[
+    "bin", "dev", "lib", "out", "man", "doc", "info", "devdoc", "static", "debug", "dist", "udev",
+];
+
+#[derive(Debug, Deserialize)]
+pub struct AdvisoryDb {
+    pub generated: Option<String>,
+    pub advisories: Vec<Advisory>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Advisory {
+    pub id: String,
+    pub package: String,
+    pub introduced: Option<String>,
+    pub fixed: Option<String>,
+    #[serde(default)]
+    pub severity: String,
+    #[serde(default)]
+    pub summary: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdvisoryReport {
+    pub db: String,
+    pub db_generated: Option<String>,
+    pub advisories: usize,
+    /// Distinct pname/version pairs in the closure.
+    pub packages_checked: usize,
+    pub findings: Vec<Finding>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Finding {
+    pub id: String,
+    pub package: String,
+    pub version: String,
+    pub fixed: Option<String>,
+    pub severity: String,
+    pub summary: String,
+}
+
+pub fn report(base: &Path, db_rel: &str) -> Result<AdvisoryReport, String> {
+    let db = load(base, db_rel)?;
+    let closure = runner::run(
+        ASPECT,
+        "nix",
+        &["path-info", "--recursive", "/run/current-system"],
+        HEAVY_TIMEOUT,
+    )
+    .map_err(|e| format!("nix path-info --recursive: {e}"))?;
+    let packages = packages(closure.lines());
+    Ok(AdvisoryReport {
+        db: db_rel.to_string(),
+        db_generated: db.generated.clone(),
+        advisories: db.advisories.len(),
+        packages_checked: packages.len(),
+        findings: findings(&packages, &db.advisories),
+    })
+}
+
+/// Reads and parses the database at `db_rel`, sandboxed to `base`.
+pub fn load(base: &Path, db_rel: &str) -> Result<AdvisoryDb, String> {
+    let text = sandbox::read_file(base, db_rel, MAX_DB_BYTES).map_err(|e| e.to_string())?;
+    serde_json::from_str(&text).map_err(|e| format!("parsing: {e}"))
+}
+
+/// Distinct (pname, version) pairs from store paths; paths without a version
+/// (`…-source`, `…-etc`) are dropped.
+pub fn packages<'a>(paths: impl Iterator<Item = &'a str>) -> BTreeSet<(String, String)> {
+    paths.filter_map(|p| parse_store_name(p.trim())).collect()
+}
+
+/// `/nix/store/<hash>-openssl-3.0.13-bin` → `("openssl", "3.0.13")`.
+pub fn parse_store_name(path: &str) -> Option<(String, String)> {
+    let base = path.rsplit('/').next()?;
+    let (hash, name) = base.split_once('-')?;
+    if hash.len() != 32 {
+        return None;
+    }
+    let (pname, version) = parse_drv_name(name);
+    let mut version = version?;
+    while let Some((rest, suffix)) = version.rsplit_once('-') {
+        if !OUTPUT_SUFFIXES.contains(&suffix) {
+            break;
+        }
+        version = rest;
+    }
+    Some((pname.to_string(), version.to_string()))
+}
+
+/// `builtins.parseDrvName`: the name ends at the first `-` not followed by a
+/// letter.
+pub fn parse_drv_name(name: &str) -> (&str, Option<&str>) {
+    let bytes = name.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        if b == b'-' && bytes.get(i + 1).is_some_and(|c| !c.is_ascii_alphabetic()) {
+            return (&name[..i], Some(&name[i + 1..]));
+        }
+    }
+    (name, None)
+}
+
+pub fn findings(packages: &BTreeSet<(String, String)>, advisories: &[Advisory]) -> Vec<Finding> {
+    let mut out = Vec::new();
+    for (pname, version) in packages {
+        for a in advisories.iter().filter(|a| &a.package == pname) {
+            let after_intro = a
+                .introduced
+                .as_deref()
+                .is_none_or(|i| compare_versions(version, i) != Ordering::Less);
+            let before_fix = a
+                .fixed
+                .as_deref()
+                .is_none_or(|f| compare_versions(version, f) == Ordering::Less);
+            if after_intro && before_fix {
+                out.push(Finding {
+                    id: a.id.clone(),
+                    package: pname.clone(),
+                    version: version.clone(),
+                    fixed: a.fixed.clone(),
+                    severity: a.severity.clone(),
+                    summary: a.summary.clone(),
+                });
+            }
+        }
+    }
+    out
+}
+
+/// `builtins.compareVersions`: versions split into components at `.` and `-`
+/// and at digit/non-digit boundaries, then compared component-wise.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (ca, cb) = (components(a), components(b));
+    for i in 0..ca.len().max(cb.len()) {
+        let x = ca.get(i).copied().unwrap_or("");
+        let y = cb.get(i).copied().unwrap_or("");
+        if component_lt(x, y) {
+            return Ordering::Less;
+        }
+        if component_lt(y, x) {
+            return Ordering::Greater;
+        }
+    }
+    Ordering::Equal
+}
+
+fn components(v: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let bytes = v.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'.' || bytes[i] == b'-' {
+            i += 1;
+            continue;
+        }
+        let digit = bytes[i].is_ascii_digit();
+        let start = i;
+        while i < bytes.len()
+            && bytes[i] != b'.'
+            && bytes[i] != b'-'
+            && bytes[i].is_ascii_digit() == digit
+        {
+            i += 1;
+        }
+        out.push(&v[start..i]);
+    }
+    out
+}
+
+/// Nix's `componentsLT`: numbers compare numerically, a missing component
+/// sorts before a number, `pre` sorts before anything else, and a string
+/// sorts before a number (`2.3a < 2.3.1`).
+fn component_lt(c1: &str, c2: &str) -> bool {
+    let num = |c: &str| (!c.is_empty() && c.bytes().all(|b| b.is_ascii_digit())).then(|| c.parse::<u128>().ok()).flatten();
+    let (n1, n2) = (num(c1), num(c2));
+    match (n1, n2) {
+        (Some(a), Some(b)) => a < b,
+        _ if c1.is_empty() && n2.is_some() => true,
+        _ if c1 == "pre" && c2 != "pre" => true,
+        _ if c2 == "pre" => false,
+        (_, Some(_)) => true,
+        (Some(_), _) => false,
+        _ => c1 < c2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_like_nix() {
+        use Ordering::*;
+        for (a, b, want) in [
+            ("1.0", "2.3", Less),
+            ("2.1", "2.3", Less),
+            ("2.3", "2.3", Equal),
+            ("2.5", "2.3", Greater),
+            ("3.1", "2.3", Greater),
+            ("2.3.1", "2.3", Greater),
+            ("2.3.1", "2.3a", Greater),
+            ("2.3pre1", "2.3", Less),
+            ("2.3pre3", "2.3pre12", Less),
+            ("2.3a", "2.3c", Less),
+            ("2.3pre1", "2.3c", Less),
+            ("2.3pre1", "2.3q", Less),
+            ("9.8p1", "9.6p1", Greater),
+            ("1.2.3", "1.2-3", Equal),
+        ] {
+            assert_eq!(compare_versions(a, b), want, "{a} vs {b}");
+        }
+    }
+
+    #[test]
+    fn parses_store_path_names() {
+        let h = "0123456789abcdfghijklmnpqrsvwxyz";
+        let p = |n: &str| parse_store_name(&format!("/nix/store/{h}-{n}"));
+        assert_eq!(p("openssl-3.0.13-bin"), Some(("openssl".into(), "3.0.13".into())));
+        assert_eq!(p("xz-5.6.1"), Some(("xz".into(), "5.6.1".into())));
+        assert_eq!(p("python3.12-requests-2.32.3"), Some(("python3.12-requests".into(), "2.32.3".into())));
+        assert_eq!(p("nixos-system-nixos-25.11pre"), Some(("nixos-system-nixos".into(), "25.11pre".into())));
+        assert_eq!(p("source"), None);
+        assert_eq!(parse_store_name("/nix/store/short-xz-5.6.1"), None);
+    }
+
+    #[test]
+    fn matches_advisory_ranges() {
+        let base = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let db = load(&base, "fixtures/advisories.json").unwrap();
+        assert_eq!(db.generated.as_deref(), Some("2026-10-01"));
+        let h = "0123456789abcdfghijklmnpqrsvwxyz";
+        let paths = [
+            format!("/nix/store/{h}-xz-5.6.1"),
+            format!("/nix/store/{h}-xz-5.6.1-bin"),
+            format!("/nix/store/{h}-openssh-9.8p1"),
+            format!("/nix/store/{h}-curl-8.9.1"),
+            format!("/nix/store/{h}-libwebp-1.3.1"),
+        ];
+        let pkgs = packages(paths.iter().map(String::as_str));
+        assert_eq!(pkgs.len(), 4, "outputs of one package are checked once");
+        let f = findings(&pkgs, &db.advisories);
+        let ids: Vec<(&str, &str)> = f.iter().map(|f| (f.id.as_str(), f.version.as_str())).collect();
+        assert_eq!(ids, [("CVE-2023-4863", "1.3.1"), ("CVE-2024-3094", "5.6.1")]);
+    }
+
+    #[test]
+    fn database_read_is_sandboxed() {
+        let base = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        assert!(load(&base, "../Cargo.toml").is_err());
+        assert!(load(&base, "fixtures/missing.json").is_err());
+    }
+}
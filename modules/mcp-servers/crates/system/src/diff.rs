@@ -0,0 +1,174 @@
+//! Minimal line-based unified diff, for previews only.
+//!
+//! Common prefix and suffix are trimmed first, which is all a formatter diff
+//! usually needs; the remaining middle is aligned with an LCS table, or shown
+//! as one replacement if it is too large for that. Output is cut at a byte
+//! budget with a marker.
+
+/// LCS tables above this many cells fall back to delete-all/insert-all.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Renders `old` → `new` as a unified diff with `context` lines around each
+/// change. Empty when the inputs are equal.
+pub fn unified(old: &str, new: &str, label: &str, context: usize, max_bytes: usize) -> String {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let ops = align(&a, &b);
+    if ops.iter().all(|op| matches!(op, Op::Equal(..))) {
+        return String::new();
+    }
+
+    let mut out = format!("--- a/{label}\n+++ b/{label}\n");
+    let changed: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, Op::Equal(..)))
+        .map(|(i, _)| i)
+        .collect();
+
+    // Group changes whose context windows touch into one hunk.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in &changed {
+        let start = i.saturating_sub(context);
+        let end = (i + context + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    for (start, end) in hunks {
+        let (mut a_start, mut b_start) = position(&ops, start);
+        let slice = &ops[start..end];
+        let a_len = slice.iter().filter(|op| !matches!(op, Op::Insert(_))).count();
+        let b_len = slice.iter().filter(|op| !matches!(op, Op::Delete(_))).count();
+        // Unified diff numbers from 1; an empty side points at the line before.
+        if a_len > 0 {
+            a_start += 1;
+        }
+        if b_len > 0 {
+            b_start += 1;
+        }
+        out.push_str(&format!("@@ -{a_start},{a_len} +{b_start},{b_len} @@\n"));
+        for op in slice {
+            let line = match *op {
+                Op::Equal(i, _) => format!(" {}\n", a[i]),
+                Op::Delete(i) => format!("-{}\n", a[i]),
+                Op::Insert(j) => format!("+{}\n", b[j]),
+            };
+            if out.len() + line.len() > max_bytes {
+                out.push_str("[... diff truncated ...]\n");
+                return out;
+            }
+            out.push_str(&line);
+        }
+    }
+    out
+}
+
+/// Zero-based line positions in `a` and `b` at which `ops[idx]` starts.
+fn position(ops: &[Op], idx: usize) -> (usize, usize) {
+    ops[..idx].iter().fold((0, 0), |(x, y), op| match op {
+        Op::Equal(..) => (x + 1, y + 1),
+        Op::Delete(_) => (x + 1, y),
+        Op::Insert(_) => (x, y + 1),
+    })
+}
+
+fn align(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops: Vec<Op> = (0..prefix).map(|i| Op::Equal(i, i)).collect();
+    if a_mid.len().saturating_mul(b_mid.len()) > MAX_LCS_CELLS {
+        ops.extend((0..a_mid.len()).map(|i| Op::Delete(prefix + i)));
+        ops.extend((0..b_mid.len()).map(|j| Op::Insert(prefix + j)));
+    } else {
+        ops.extend(lcs(a_mid, b_mid).into_iter().map(|op| match op {
+            Op::Equal(i, j) => Op::Equal(prefix + i, prefix + j),
+            Op::Delete(i) => Op::Delete(prefix + i),
+            Op::Insert(j) => Op::Insert(prefix + j),
+        }));
+    }
+    let (a_tail, b_tail) = (a.len() - suffix, b.len() - suffix);
+    ops.extend((0..suffix).map(|k| Op::Equal(a_tail + k, b_tail + k)));
+    ops
+}
+
+fn lcs(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let (n, m) = (a.len(), b.len());
+    // table[i][j] = LCS length of a[i..] and b[j..].
+    let mut table = vec![0u32; (n + 1) * (m + 1)];
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[at(i, j)] = if a[i] == b[j] {
+                table[at(i + 1, j + 1)] + 1
+            } else {
+                table[at(i + 1, j)].max(table[at(i, j + 1)])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut ops = Vec::with_capacity(n + m);
+    while i < n || j < m {
+        if i < n && j < m && a[i] == b[j] {
+            ops.push(Op::Equal(i, j));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || table[at(i + 1, j)] >= table[at(i, j + 1)]) {
+            ops.push(Op::Delete(i));
+            i += 1;
+        } else {
+            ops.push(Op::Insert(j));
+            j += 1;
+        }
+    }
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_inputs_give_empty_diff() {
+        assert_eq!(unified("a\nb\n", "a\nb\n", "x.nix", 3, 1_000), "");
+    }
+
+    #[test]
+    fn renders_hunks_with_context() {
+        let old = "{ pkgs, ... }:\n{\n  environment.systemPackages = [pkgs.git   pkgs.vim];\n}\n";
+        let new = "{ pkgs, ... }:\n{\n  environment.systemPackages = [\n    pkgs.git\n    pkgs.vim\n  ];\n}\n";
+        let d = unified(old, new, "m.nix", 1, 10_000);
+        assert_eq!(
+            d,
+            "--- a/m.nix\n+++ b/m.nix\n@@ -2,3 +2,6 @@\n {\n-  environment.systemPackages = [pkgs.git   pkgs.vim];\n+  environment.systemPackages = [\n+    pkgs.git\n+    pkgs.vim\n+  ];\n }\n"
+        );
+    }
+
+    #[test]
+    fn separates_distant_changes_and_truncates() {
+        let old: String = (0..20).map(|i| format!("l{i}\n")).collect();
+        let new = old.replace("l2\n", "L2\n").replace("l17\n", "L17\n");
+        let d = unified(&old, &new, "f", 2, 10_000);
+        assert_eq!(d.matches("@@ ").count(), 2);
+        assert!(d.contains("@@ -1,5 +1,5 @@"));
+        assert!(d.contains("@@ -16,5 +16,5 @@"));
+        let cut = unified(&old, &new, "f", 2, 60);
+        assert!(cut.ends_with("[... diff truncated ...]\n"));
+    }
+}
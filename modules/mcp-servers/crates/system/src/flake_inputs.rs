@@ -0,0 +1,391 @@
+//! `flake_inputs` — freshness of the flake's direct inputs, from `flake.lock`.
+//!
+//! For each root input: where it comes from, the locked revision, when that
+//! revision was committed and how old it is. If an upstream snapshot is
+//! cached in the aspect's state dir, each input is also compared against the
+//! head of the branch it follows. The snapshot is written out of band (by
+//! whatever networked job the host runs); this tool never fetches anything.
+//!
+//! Snapshot format (`upstream-snapshot.json`, or `OLIGARCHY_FLAKE_UPSTREAM`):
+//!
+//! ```json
+//! { "generated": "2026-10-18T12:00:00Z",
+//!   "inputs": { "github:NixOS/nixpkgs/nixos-unstable":
+//!                 { "rev": "…", "lastModified": 1771008912, "revs": ["…newest", "…"] } } }
+//! ```
+//!
+//! Keys are [`upstream_key`]s, so one snapshot serves every flake that pins
+//! the same branch. `revs` (newest first) is optional; without it only the
+//! time gap is reported.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use oligarchy_mcp_core::{audit, sandbox};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::ASPECT;
+
+/// `flake.lock` files are small; anything bigger is not one.
+const MAX_LOCK_BYTES: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+pub struct FlakeLock {
+    pub version: u32,
+    pub root: String,
+    pub nodes: BTreeMap<String, Node>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Node {
+    #[serde(default)]
+    pub inputs: BTreeMap<String, InputRef>,
+    pub locked: Option<Source>,
+    pub original: Option<Source>,
+}
+
+/// A node name, or a `follows` path of input names starting at the root.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum InputRef {
+    Node(String),
+    Follows(Vec<String>),
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Source {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub owner: Option<String>,
+    pub repo: Option<String>,
+    pub url: Option<String>,
+    pub path: Option<String>,
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+    pub rev: Option<String>,
+    pub last_modified: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Snapshot {
+    pub generated: Option<String>,
+    #[serde(default)]
+    pub inputs: BTreeMap<String, UpstreamHead>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamHead {
+    pub rev: String,
+    pub last_modified: Option<i64>,
+    #[serde(default)]
+    pub revs: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InputReport {
+    pub name: String,
+    /// Set when the input `follows` another input instead of being locked
+    /// itself, e.g. `home-manager/nixpkgs`.
+    pub follows: Option<String>,
+    pub source: String,
+    /// The branch or tag followed; `None` means the default branch.
+    pub branch: Option<String>,
+    pub rev: Option<String>,
+    pub last_modified: Option<String>,
+    pub age_days: Option<i64>,
+    pub upstream: Option<UpstreamDelta>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct UpstreamDelta {
+    pub head_rev: String,
+    pub up_to_date: bool,
+    /// Commits between the locked rev and the head, when the snapshot lists
+    /// the branch history and contains the locked rev.
+    pub commits_behind: Option<usize>,
+    pub days_behind: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SnapshotInfo {
+    pub path: String,
+    pub generated: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FlakeInputsReport {
+    pub lock_version: u32,
+    pub inputs: Vec<InputReport>,
+    pub snapshot: Option<SnapshotInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub advisories: Option<crate::advisory::AdvisoryReport>,
+    pub unavailable: Vec<String>,
+}
+
+/// Where the cached upstream snapshot lives.
+pub fn snapshot_path() -> PathBuf {
+    std::env::var("OLIGARCHY_FLAKE_UPSTREAM")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| audit::state_dir(ASPECT).join("upstream-snapshot.json"))
+}
+
+pub fn report(advisory_db: Option<&str>) -> String {
+    let base = sandbox::flake_dir();
+    let text = match sandbox::read_file(&base, "flake.lock", MAX_LOCK_BYTES) {
+        Ok(t) => t,
+        Err(e) => return format!("[error] reading flake.lock under {}: {e}", base.display()),
+    };
+    let lock: FlakeLock = match serde_json::from_str(&text) {
+        Ok(l) => l,
+        Err(e) => return format!("[error] parsing flake.lock: {e}"),
+    };
+
+    let mut unavailable = Vec::new();
+    let snap_path = snapshot_path();
+    let snapshot = match std::fs::read_to_string(&snap_path) {
+        Ok(s) => match serde_json::from_str::<Snapshot>(&s) {
+            Ok(snap) => Some(snap),
+            Err(e) => {
+                unavailable.push(format!("{}: {e}", snap_path.display()));
+                None
+            }
+        },
+        // No snapshot is the normal offline case, not a failure.
+        Err(_) => None,
+    };
+
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    let inputs = inputs(&lock, snapshot.as_ref(), now);
+    let advisories = advisory_db.and_then(|db| match crate::advisory::report(&base, db) {
+        Ok(r) => Some(r),
+        Err(e) => {
+            unavailable.push(format!("advisory db {db}: {e}"));
+            None
+        }
+    });
+
+    oligarchy_mcp_core::report::json(&FlakeInputsReport {
+        lock_version: lock.version,
+        inputs,
+        snapshot: snapshot.map(|s| SnapshotInfo {
+            path: snap_path.display().to_string(),
+            generated: s.generated,
+        }),
+        advisories,
+        unavailable,
+    })
+}
+
+/// Reports every direct input of the root node.
+pub fn inputs(lock: &FlakeLock, snapshot: Option<&Snapshot>, now: i64) -> Vec<InputReport> {
+    let Some(root) = lock.nodes.get(&lock.root) else {
+        return Vec::new();
+    };
+    root.inputs
+        .iter()
+        .map(|(name, r)| {
+            let follows = match r {
+                InputRef::Follows(path) => Some(path.join("/")),
+                InputRef::Node(_) => None,
+            };
+            let node = resolve(lock, r);
+            let locked = node.and_then(|n| n.locked.clone()).unwrap_or_default();
+            let original = node.and_then(|n| n.original.clone()).unwrap_or_default();
+            // A hostile `lastModified` must not overflow; it reads as unknown.
+            let last_modified = locked.last_modified.filter(|t| t.checked_mul(1_000_000).is_some());
+            let upstream = snapshot
+                .and_then(|s| upstream_key(&original).and_then(|k| s.inputs.get(&k)))
+                .map(|head| delta(&locked, head));
+            InputReport {
+                name: name.clone(),
+                follows,
+                source: describe(&original),
+                branch: original.git_ref.clone(),
+                rev: locked.rev.clone(),
+                last_modified: last_modified.map(|t| crate::journal::rfc3339_from_usec(t * 1_000_000)),
+                age_days: last_modified.and_then(|t| now.checked_sub(t)).map(|s| s / 86_400),
+                upstream,
+            }
+        })
+        .collect()
+}
+
+/// Follows a node reference. `follows` paths are resolved from the root, one
+/// input name at a time, and may themselves land on another `follows`; a
+/// cycle gives up after a fixed number of hops.
+fn resolve<'a>(lock: &'a FlakeLock, r: &InputRef) -> Option<&'a Node> {
+    resolve_hops(lock, r, 0)
+}
+
+fn resolve_hops<'a>(lock: &'a FlakeLock, r: &InputRef, hops: u32) -> Option<&'a Node> {
+    if hops > 32 {
+        return None;
+    }
+    match r {
+        InputRef::Node(name) => lock.nodes.get(name),
+        InputRef::Follows(path) => {
+            let (last, parents) = path.split_last()?;
+            let mut node = lock.nodes.get(&lock.root)?;
+            for hop in parents {
+                node = resolve_hops(lock, node.inputs.get(hop)?, hops + 1)?;
+            }
+            resolve_hops(lock, node.inputs.get(last)?, hops + 1)
+        }
+    }
+}
+
+fn describe(s: &Source) -> String {
+    match (s.kind.as_str(), &s.owner, &s.repo, &s.url, &s.path) {
+        (kind @ ("github" | "gitlab" | "sourcehut"), Some(o), Some(r), _, _) => format!("{kind}:{o}/{r}"),
+        ("git", _, _, Some(u), _) => format!("git+{u}"),
+        (_, _, _, Some(u), _) => u.clone(),
+        ("path", _, _, _, Some(p)) => format!("path:{p}"),
+        (kind, ..) => kind.to_string(),
+    }
+}
+
+/// The snapshot key for an input's `original`: its source plus the branch it
+/// follows. Local `path:` inputs have no upstream.
+pub fn upstream_key(original: &Source) -> Option<String> {
+    if original.kind == "path" || original.kind.is_empty() {
+        return None;
+    }
+    let base = describe(original);
+    Some(match (&original.git_ref, original.kind.as_str()) {
+        (Some(r), "git") => format!("{base}?ref={r}"),
+        (Some(r), _) => format!("{base}/{r}"),
+        (None, _) => base,
+    })
+}
+
+fn delta(locked: &Source, head: &UpstreamHead) -> UpstreamDelta {
+    let up_to_date = locked.rev.as_deref() == Some(head.rev.as_str());
+    let commits_behind = locked
+        .rev
+        .as_ref()
+        .and_then(|rev| head.revs.iter().position(|r| r == rev));
+    let days_behind = match (locked.last_modified, head.last_modified) {
+        (Some(l), Some(h)) => h.checked_sub(l).map(|s| (s / 86_400).max(0)),
+        _ => None,
+    };
+    UpstreamDelta { head_rev: head.rev.clone(), up_to_date, commits_behind, days_behind }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCK: &str = r#"{
+      "nodes": {
+        "home-manager": {
+          "inputs": { "nixpkgs": ["nixpkgs"] },
+          "locked": { "lastModified": 1770000000, "owner": "nix-community", "repo": "home-manager",
+                      "rev": "hm1", "type": "github" },
+          "original": { "owner": "nix-community", "ref": "release-25.11", "repo": "home-manager", "type": "github" }
+        },
+        "mcp-servers": {
+          "locked": { "path": "./modules/mcp-servers", "type": "path" },
+          "original": { "path": "./modules/mcp-servers", "type": "path" }
+        },
+        "nixpkgs_15": {
+          "locked": { "lastModified": 1771008912, "owner": "NixOS", "repo": "nixpkgs",
+                      "rev": "a82ccc3", "type": "github" },
+          "original": { "owner": "NixOS", "ref": "nixos-unstable", "repo": "nixpkgs", "type": "github" }
+        },
+        "root": {
+          "inputs": {
+            "home-manager": "home-manager",
+            "mcp-servers": "mcp-servers",
+            "nixpkgs": "nixpkgs_15",
+            "pkgs-alias": ["home-manager", "nixpkgs"]
+          }
+        }
+      },
+      "root": "root",
+      "version": 7
+    }"#;
+
+    fn lock() -> FlakeLock {
+        serde_json::from_str(LOCK).unwrap()
+    }
+
+    #[test]
+    fn reports_direct_inputs_and_resolves_follows() {
+        let now = 1771008912 + 10 * 86_400;
+        let r = inputs(&lock(), None, now);
+        assert_eq!(r.len(), 4);
+        let np = r.iter().find(|i| i.name == "nixpkgs").unwrap();
+        assert_eq!(np.source, "github:NixOS/nixpkgs");
+        assert_eq!(np.branch.as_deref(), Some("nixos-unstable"));
+        assert_eq!(np.age_days, Some(10));
+        assert_eq!(np.last_modified.as_deref(), Some("2026-02-13T18:55:12Z"));
+
+        // root.pkgs-alias -> home-manager/nixpkgs -> root.nixpkgs -> nixpkgs_15
+        let alias = r.iter().find(|i| i.name == "pkgs-alias").unwrap();
+        assert_eq!(alias.follows.as_deref(), Some("home-manager/nixpkgs"));
+        assert_eq!(alias.rev.as_deref(), Some("a82ccc3"));
+
+        let local = r.iter().find(|i| i.name == "mcp-servers").unwrap();
+        assert_eq!(local.source, "path:./modules/mcp-servers");
+        assert_eq!(local.rev, None);
+    }
+
+    #[test]
+    fn compares_against_cached_snapshot() {
+        let snap: Snapshot = serde_json::from_str(
+            r#"{ "generated": "2026-10-18T12:00:00Z", "inputs": {
+                 "github:NixOS/nixpkgs/nixos-unstable":
+                   { "rev": "head", "lastModified": 1771872912, "revs": ["head", "p1", "p2", "a82ccc3"] },
+                 "github:nix-community/home-manager/release-25.11": { "rev": "hm1" } } }"#,
+        )
+        .unwrap();
+        let r = inputs(&lock(), Some(&snap), 1771872912);
+        let np = r.iter().find(|i| i.name == "nixpkgs").unwrap();
+        assert_eq!(
+            np.upstream,
+            Some(UpstreamDelta {
+                head_rev: "head".into(),
+                up_to_date: false,
+                commits_behind: Some(3),
+                days_behind: Some(10),
+            })
+        );
+        let hm = r.iter().find(|i| i.name == "home-manager").unwrap();
+        assert!(hm.upstream.as_ref().unwrap().up_to_date);
+        let local = r.iter().find(|i| i.name == "mcp-servers").unwrap();
+        assert!(local.upstream.is_none(), "path inputs have no upstream");
+    }
+
+    #[test]
+    fn hostile_last_modified_is_unknown_age() {
+        let lock: FlakeLock = serde_json::from_str(
+            r#"{ "nodes": {
+                 "big": { "locked": { "lastModified": 9223372036854775807, "rev": "x", "type": "github" },
+                          "original": { "owner": "o", "repo": "r", "type": "github" } },
+                 "root": { "inputs": { "big": "big" } } },
+               "root": "root", "version": 7 }"#,
+        )
+        .unwrap();
+        let r = inputs(&lock, None, 1771872912);
+        assert_eq!((r[0].last_modified.as_deref(), r[0].age_days), (None, None));
+    }
+
+    #[test]
+    fn upstream_keys() {
+        let git = Source {
+            kind: "git".into(),
+            url: Some("https://github.com/ALH477/DeMoD-IP-Blocker.git".into()),
+            git_ref: Some("main".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            upstream_key(&git).as_deref(),
+            Some("git+https://github.com/ALH477/DeMoD-IP-Blocker.git?ref=main")
+        );
+        let path = Source { kind: "path".into(), path: Some("./x".into()), ..Default::default() };
+        assert_eq!(upstream_key(&path), None);
+    }
+}
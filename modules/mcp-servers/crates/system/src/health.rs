@@ -0,0 +1,320 @@
+//! `health_summary` — the post-rebuild health check in one call.
+//!
+//! Gathers, for the current boot: failed system and user units
+//! (`systemctl --failed --output=json`), priority ≤ 3 journal messages, OOM
+//! kills (kernel and `systemd-oomd`), the kernel taint flags
+//! (`/proc/sys/kernel/tainted`) and the boot-time split from systemd's
+//! "Startup finished" journal record. A source that cannot be read is listed
+//! under `unavailable` instead of failing the whole report.
+
+use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
+use serde::{Deserialize, Serialize};
+
+use crate::journal::{self, Entry, Fields};
+use crate::ASPECT;
+
+/// Newest priority ≤ 3 messages kept in the report.
+const MAX_ERRORS: usize = 100;
+
+/// `MESSAGE_ID` of systemd's "Startup finished in …" record, which carries the
+/// per-phase `*_USEC` fields.
+const STARTUP_FINISHED_ID: &str = "b07a249cd024414a82dd00cd181378ff";
+
+#[derive(Debug, Serialize)]
+pub struct HealthSummary {
+    pub failed_units: FailedUnits,
+    pub errors: Vec<Entry>,
+    pub oom_kills: Vec<OomKill>,
+    pub taint: Option<Taint>,
+    pub boot_time: Option<BootTime>,
+    /// Sources that could not be read, with the reason.
+    pub unavailable: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct FailedUnits {
+    pub system: Vec<FailedUnit>,
+    pub user: Vec<FailedUnit>,
+}
+
+/// One row of `systemctl --failed --output=json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedUnit {
+    pub unit: String,
+    #[serde(default)]
+    pub load: String,
+    #[serde(default)]
+    pub active: String,
+    #[serde(default)]
+    pub sub: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OomKill {
+    pub timestamp: String,
+    /// `kernel` or `systemd-oomd`.
+    pub source: &'static str,
+    pub pid: Option<u32>,
+    /// Process name (kernel) or cgroup path (systemd-oomd).
+    pub victim: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Taint {
+    pub value: u64,
+    pub flags: Vec<TaintFlag>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaintFlag {
+    pub bit: u32,
+    pub letter: char,
+    pub meaning: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BootTime {
+    pub firmware_ms: Option<u64>,
+    pub loader_ms: Option<u64>,
+    pub kernel_ms: Option<u64>,
+    pub initrd_ms: Option<u64>,
+    pub userspace_ms: Option<u64>,
+    pub total_ms: u64,
+}
+
+/// Kernel taint bits, per `Documentation/admin-guide/tainted-kernels.rst`.
+const TAINT_FLAGS: &[(char, &str)] = &[
+    ('P', "proprietary module was loaded"),
+    ('F', "module was force loaded"),
+    ('S', "kernel running on an out of specification system"),
+    ('R', "module was force unloaded"),
+    ('M', "processor reported a Machine Check Exception"),
+    ('B', "bad page referenced or unexpected page flags"),
+    ('U', "taint requested by userspace"),
+    ('D', "kernel died recently (OOPS or BUG)"),
+    ('A', "ACPI table overridden by user"),
+    ('W', "kernel issued a warning"),
+    ('C', "staging driver was loaded"),
+    ('I', "workaround for a platform firmware bug applied"),
+    ('O', "externally-built (out-of-tree) module was loaded"),
+    ('E', "unsigned module was loaded"),
+    ('L', "soft lockup occurred"),
+    ('K', "kernel has been live patched"),
+    ('X', "auxiliary taint (distro-defined)"),
+    ('T', "kernel was built with the struct randomization plugin"),
+    ('N', "an in-kernel test has been run"),
+    ('J', "userspace used a mutating debug operation in fwctl"),
+];
+
+pub fn report() -> String {
+    let mut unavailable = Vec::new();
+
+    let mut failed_units = FailedUnits::default();
+    match failed(&["--failed", "--output=json", "--no-pager"]) {
+        Ok(units) => failed_units.system = units,
+        Err(e) => unavailable.push(format!("systemctl --failed: {e}")),
+    }
+    match failed(&["--user", "--failed", "--output=json", "--no-pager"]) {
+        Ok(units) => failed_units.user = units,
+        Err(e) => unavailable.push(format!("systemctl --user --failed: {e}")),
+    }
+
+    let max = MAX_ERRORS.to_string();
+    let errors = match journal::query(&["-b", "-p", "3", "-o", "json", "--no-pager", "-n", &max]) {
+        Ok(entries) => entries.iter().map(Entry::from_fields).collect(),
+        Err(e) => {
+            unavailable.push(format!("journalctl -p 3: {e}"));
+            Vec::new()
+        }
+    };
+
+    let oom_kills = match journal::query(&[
+        "-b",
+        "-o",
+        "json",
+        "--no-pager",
+        "--grep=Killed process|due to memory",
+    ]) {
+        Ok(entries) => entries.iter().filter_map(oom_kill).collect(),
+        Err(e) => {
+            unavailable.push(format!("journalctl (OOM kills): {e}"));
+            Vec::new()
+        }
+    };
+
+    let taint = match std::fs::read_to_string("/proc/sys/kernel/tainted")
+        .map_err(|e| e.to_string())
+        .and_then(|s| parse_taint(&s))
+    {
+        Ok(t) => Some(t),
+        Err(e) => {
+            unavailable.push(format!("/proc/sys/kernel/tainted: {e}"));
+            None
+        }
+    };
+
+    let startup_filter = format!("MESSAGE_ID={STARTUP_FINISHED_ID}");
+    let boot_time = match journal::query(&["-b", "-o", "json", "--no-pager", &startup_filter, "_PID=1"]) {
+        Ok(entries) => entries.iter().rev().find_map(boot_time),
+        Err(e) => {
+            unavailable.push(format!("journalctl (startup finished): {e}"));
+            None
+        }
+    };
+
+    oligarchy_mcp_core::report::json(&HealthSummary {
+        failed_units,
+        errors,
+        oom_kills,
+        taint,
+        boot_time,
+        unavailable,
+    })
+}
+
+fn failed(args: &[&str]) -> Result<Vec<FailedUnit>, String> {
+    let out = runner::run(ASPECT, "systemctl", args, QUICK_TIMEOUT).map_err(|e| e.to_string())?;
+    parse_failed_units(&out)
+}
+
+/// Parses `systemctl --failed --output=json`. `runner::run` folds stderr into
+/// the output, so the JSON array is sliced out of whatever surrounds it.
+pub fn parse_failed_units(text: &str) -> Result<Vec<FailedUnit>, String> {
+    let (Some(start), Some(end)) = (text.find('['), text.rfind(']')) else {
+        return Err(format!("no JSON array in systemctl output: {}", journal::truncate(text, 200)));
+    };
+    serde_json::from_str(&text[start..=end]).map_err(|e| format!("parsing systemctl JSON: {e}"))
+}
+
+/// Recognises the kernel's "Out of memory: Killed process 1234 (firefox)" (and
+/// its memory-cgroup variant) and systemd-oomd's "Killed /user.slice/… due to
+/// memory pressure …". Other matches of the journal grep are ignored.
+fn oom_kill(f: &Fields) -> Option<OomKill> {
+    let entry = Entry::from_fields(f);
+    let msg = f.get("MESSAGE")?;
+    if let Some(rest) = msg.split("Killed process ").nth(1) {
+        let (pid, rest) = rest.split_once(' ')?;
+        let victim = rest
+            .strip_prefix('(')
+            .and_then(|r| r.split_once(')'))
+            .map(|(name, _)| name.to_string())
+            .unwrap_or_default();
+        return Some(OomKill {
+            timestamp: entry.timestamp,
+            source: "kernel",
+            pid: pid.parse().ok(),
+            victim,
+        });
+    }
+    if entry.identifier.as_deref() == Some("systemd-oomd") {
+        let victim = msg.strip_prefix("Killed ")?.split(" due to").next()?.to_string();
+        return Some(OomKill {
+            timestamp: entry.timestamp,
+            source: "systemd-oomd",
+            pid: None,
+            victim,
+        });
+    }
+    None
+}
+
+/// Parses the decimal bitmask in `/proc/sys/kernel/tainted`.
+fn parse_taint(text: &str) -> Result<Taint, String> {
+    let text = text.trim();
+    text.parse()
+        .map(decode_taint)
+        .map_err(|e| format!("not a taint bitmask ({e}): {}", journal::truncate(text, 40)))
+}
+
+pub fn decode_taint(value: u64) -> Taint {
+    let flags = TAINT_FLAGS
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| value & (1 << bit) != 0)
+        .map(|(bit, &(letter, meaning))| TaintFlag { bit: bit as u32, letter, meaning })
+        .collect();
+    Taint { value, flags }
+}
+
+fn boot_time(f: &Fields) -> Option<BootTime> {
+    let ms = |key: &str| f.get(key).and_then(|v| v.parse::<u64>().ok()).map(|us| us / 1_000);
+    let kernel_ms = ms("KERNEL_USEC");
+    let userspace_ms = ms("USERSPACE_USEC");
+    if kernel_ms.is_none() && userspace_ms.is_none() {
+        return None;
+    }
+    let firmware_ms = ms("FIRMWARE_USEC");
+    let loader_ms = ms("LOADER_USEC");
+    let initrd_ms = ms("INITRD_USEC");
+    let total_ms = [firmware_ms, loader_ms, kernel_ms, initrd_ms, userspace_ms]
+        .iter()
+        .flatten()
+        .sum();
+    Some(BootTime { firmware_ms, loader_ms, kernel_ms, initrd_ms, userspace_ms, total_ms })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_failed_units_with_stderr_noise() {
+        let out = r#"[{"unit":"strict-egress-resolve.service","load":"loaded","active":"failed","sub":"failed","description":"Strict Egress - resolve allowlisted domains into nft sets"}]
+Warning: some journal files were not opened due to insufficient permissions."#;
+        let units = parse_failed_units(out).unwrap();
+        assert_eq!(units.len(), 1);
+        assert_eq!(units[0].unit, "strict-egress-resolve.service");
+        assert_eq!(units[0].active, "failed");
+        assert!(parse_failed_units("[]").unwrap().is_empty());
+        assert!(parse_failed_units("Failed to connect to bus").is_err());
+    }
+
+    #[test]
+    fn recognises_kernel_and_oomd_kills() {
+        let kernel = journal::parse(
+            r#"{"__REALTIME_TIMESTAMP":"1760000000000000","SYSLOG_IDENTIFIER":"kernel","MESSAGE":"Out of memory: Killed process 4242 (ollama) total-vm:123kB, anon-rss:99kB"}"#,
+        );
+        assert_eq!(
+            oom_kill(&kernel[0]),
+            Some(OomKill {
+                timestamp: "2025-10-09T08:53:20Z".into(),
+                source: "kernel",
+                pid: Some(4242),
+                victim: "ollama".into(),
+            })
+        );
+        let oomd = journal::parse(
+            r#"{"SYSLOG_IDENTIFIER":"systemd-oomd","MESSAGE":"Killed /user.slice/user-1000.slice/app.slice/firefox.scope due to memory pressure for /user.slice being 81.2% > 50.0% for > 20s with reclaim activity"}"#,
+        );
+        let k = oom_kill(&oomd[0]).unwrap();
+        assert_eq!(k.source, "systemd-oomd");
+        assert_eq!(k.victim, "/user.slice/user-1000.slice/app.slice/firefox.scope");
+        let other = journal::parse(r#"{"SYSLOG_IDENTIFIER":"bash","MESSAGE":"nothing killed here"}"#);
+        assert_eq!(oom_kill(&other[0]), None);
+    }
+
+    #[test]
+    fn decodes_taint_bits() {
+        assert!(decode_taint(0).flags.is_empty());
+        // P (bit 0) + O (bit 12): the usual NVIDIA + out-of-tree module combo.
+        let t = decode_taint(4097);
+        let letters: String = t.flags.iter().map(|f| f.letter).collect();
+        assert_eq!(letters, "PO");
+        assert_eq!(t.flags[1].bit, 12);
+        assert_eq!(parse_taint("4097\n").unwrap(), t);
+        assert!(parse_taint("garbage").is_err());
+    }
+
+    #[test]
+    fn boot_time_sums_phases() {
+        let f = journal::parse(
+            r#"{"FIRMWARE_USEC":"5000000","LOADER_USEC":"1500000","KERNEL_USEC":"2100000","INITRD_USEC":"3400000","USERSPACE_USEC":"9000000"}"#,
+        );
+        let b = boot_time(&f[0]).unwrap();
+        assert_eq!(b.kernel_ms, Some(2_100));
+        assert_eq!(b.total_ms, 21_000);
+        assert_eq!(boot_time(&Fields::new()), None);
+    }
+}
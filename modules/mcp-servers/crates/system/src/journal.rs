@@ -0,0 +1,174 @@
+//! `journalctl -o json` parsing.
+//!
+//! journald emits one JSON object per line. Field values are strings, except
+//! that values which are not valid UTF-8 (or carry control characters) come
+//! out as arrays of byte values, and a field repeated within one entry comes
+//! out as an array of those values. Both shapes are decoded here; a line that
+//! is not a JSON object (a warning on stderr of a successful run) is skipped.
+
+use std::collections::BTreeMap;
+
+use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
+use oligarchy_mcp_core::Error;
+use serde::Serialize;
+use serde_json::Value;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::ASPECT;
+
+/// Messages longer than this are cut, so one runaway log line cannot blow up
+/// a report.
+pub const MAX_MESSAGE_CHARS: usize = 2_000;
+
+/// One decoded journal entry: every field as a string.
+pub type Fields = BTreeMap<String, String>;
+
+/// The fields most tools want, lifted out of [`Fields`].
+#[derive(Debug, Clone, Serialize)]
+pub struct Entry {
+    /// RFC 3339 UTC, from `__REALTIME_TIMESTAMP`.
+    pub timestamp: String,
+    pub priority: Option<u8>,
+    pub unit: Option<String>,
+    pub identifier: Option<String>,
+    pub pid: Option<u32>,
+    pub message: String,
+}
+
+impl Entry {
+    pub fn from_fields(f: &Fields) -> Self {
+        Entry {
+            timestamp: f
+                .get("__REALTIME_TIMESTAMP")
+                .and_then(|us| us.parse::<i64>().ok())
+                .map(rfc3339_from_usec)
+                .unwrap_or_default(),
+            priority: f.get("PRIORITY").and_then(|p| p.parse().ok()),
+            unit: f
+                .get("_SYSTEMD_USER_UNIT")
+                .or_else(|| f.get("_SYSTEMD_UNIT"))
+                .cloned(),
+            identifier: f.get("SYSLOG_IDENTIFIER").or_else(|| f.get("_COMM")).cloned(),
+            pid: f.get("_PID").and_then(|p| p.parse().ok()),
+            message: truncate(f.get("MESSAGE").map(String::as_str).unwrap_or(""), MAX_MESSAGE_CHARS),
+        }
+    }
+}
+
+/// Runs `journalctl <args>` (the caller supplies `-o json`) and decodes every
+/// entry. A window with no entries is an empty list, not an error; any other
+/// failing exit (a bad `--since`, an unknown `--boot` offset, no permission)
+/// is [`Error::Exit`].
+pub fn query(args: &[&str]) -> oligarchy_mcp_core::Result<Vec<Fields>> {
+    decode(runner::run_checked(ASPECT, "journalctl", args, QUICK_TIMEOUT))
+}
+
+/// `--grep` without a match exits 1 having said nothing on stderr; that is the
+/// one failing exit that means "no entries".
+fn decode(out: oligarchy_mcp_core::Result<String>) -> oligarchy_mcp_core::Result<Vec<Fields>> {
+    match out {
+        Ok(text) => Ok(parse(&text)),
+        Err(Error::Exit { stderr, .. }) if stderr.is_empty() => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Decodes `journalctl -o json` output, skipping lines that are not objects.
+pub fn parse(text: &str) -> Vec<Fields> {
+    text.lines().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<Fields> {
+    let Ok(Value::Object(map)) = serde_json::from_str::<Value>(line.trim()) else {
+        return None;
+    };
+    let mut fields = Fields::new();
+    for (k, v) in map {
+        if let Some(s) = decode_value(&v) {
+            fields.insert(k, s);
+        }
+    }
+    Some(fields)
+}
+
+/// A string, a byte array, or — for a repeated field — an array of either, in
+/// which case the last value wins.
+fn decode_value(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Array(items) if items.iter().all(Value::is_u64) => {
+            let bytes: Vec<u8> = items
+                .iter()
+                .filter_map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                .collect();
+            Some(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        Value::Array(items) => items.iter().rev().find_map(decode_value),
+        _ => None,
+    }
+}
+
+pub fn rfc3339_from_usec(usec: i64) -> String {
+    OffsetDateTime::from_unix_timestamp_nanos(i128::from(usec) * 1_000)
+        .ok()
+        .and_then(|t| t.format(&Rfc3339).ok())
+        .unwrap_or_default()
+}
+
+pub fn truncate(s: &str, max_chars: usize) -> String {
+    match s.char_indices().nth(max_chars) {
+        Some((cut, _)) => format!("{}…", &s[..cut]),
+        None => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINE: &str = r#"{"__REALTIME_TIMESTAMP":"1760000000000000","PRIORITY":"3","_SYSTEMD_UNIT":"NetworkManager.service","SYSLOG_IDENTIFIER":"NetworkManager","_PID":"812","MESSAGE":"device (wlan0): link down"}"#;
+
+    #[test]
+    fn parses_plain_entry() {
+        let f = parse(LINE);
+        assert_eq!(f.len(), 1);
+        let e = Entry::from_fields(&f[0]);
+        assert_eq!(e.priority, Some(3));
+        assert_eq!(e.unit.as_deref(), Some("NetworkManager.service"));
+        assert_eq!(e.pid, Some(812));
+        assert_eq!(e.timestamp, "2025-10-09T08:53:20Z");
+        assert_eq!(e.message, "device (wlan0): link down");
+    }
+
+    #[test]
+    fn decodes_byte_array_and_repeated_fields() {
+        let line = r#"{"MESSAGE":[104,105,27,0],"TAG":["a","b"],"_SYSTEMD_USER_UNIT":"pipewire.service","_SYSTEMD_UNIT":"user@1000.service"}"#;
+        let f = parse(line);
+        assert_eq!(f[0]["MESSAGE"], "hi\u{1b}\0");
+        assert_eq!(f[0]["TAG"], "b");
+        assert_eq!(Entry::from_fields(&f[0]).unit.as_deref(), Some("pipewire.service"));
+    }
+
+    #[test]
+    fn skips_non_json_lines() {
+        let text = format!("Hint: You are currently not seeing messages from other users\n{LINE}\n");
+        assert_eq!(parse(&text).len(), 1);
+    }
+
+    #[test]
+    fn failing_exit_is_an_error_unless_silent() {
+        let bad_since = Error::Exit { code: 1, stderr: "Failed to parse timestamp: yesterdayish".into() };
+        assert!(matches!(decode(Err(bad_since)), Err(Error::Exit { code: 1, .. })));
+        let no_match = Error::Exit { code: 1, stderr: String::new() };
+        assert!(decode(Err(no_match)).unwrap().is_empty());
+        assert_eq!(decode(Ok(LINE.to_string())).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn truncates_on_char_boundary() {
+        assert_eq!(truncate("ääää", 2), "ää…");
+        assert_eq!(truncate("ok", 5), "ok");
+    }
+}
@@ -0,0 +1,291 @@
+//! `journal_query` — structured, windowed journal search.
+//!
+//! Where `journal_tail` is "last N lines of one unit, as text", this builds a
+//! `journalctl -o json` invocation from a typed [`Query`] (units, time window,
+//! priority, boot offset, message grep, extra fields) and returns decoded
+//! entries. Every user-supplied value is validated and passed in `--opt=value`
+//! form, so nothing can be read back as a separate flag. Volume is clamped
+//! twice: by entry count (`-n`) and by a byte budget on the rendered report.
+
+use std::collections::BTreeMap;
+
+use oligarchy_mcp_core::validate;
+use rmcp::schemars;
+use serde::{Deserialize, Serialize};
+
+use crate::journal::{self, Entry};
+
+/// Default and maximum entry counts.
+const DEFAULT_LINES: u32 = 200;
+const MAX_LINES: u32 = 1_000;
+
+/// Stop adding entries once the messages and extra fields reach this size.
+const MAX_REPORT_BYTES: usize = 256 * 1024;
+
+/// Always requested from journalctl; `fields` are added to these.
+const BASE_FIELDS: &[&str] = &[
+    "PRIORITY",
+    "_SYSTEMD_UNIT",
+    "_SYSTEMD_USER_UNIT",
+    "SYSLOG_IDENTIFIER",
+    "_COMM",
+    "_PID",
+    "MESSAGE",
+    "_BOOT_ID",
+];
+
+const PRIORITY_NAMES: &[&str] = &[
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+
+#[derive(Debug, Default, Deserialize, schemars::JsonSchema)]
+pub struct Query {
+    /// Units to match (entries from any of them are returned). Globs are
+    /// allowed, as with `journalctl -u`. Empty means every unit.
+    #[serde(default)]
+    pub units: Vec<String>,
+    /// Window start in any form journalctl accepts: "2026-10-19 14:00:00",
+    /// "-1h", "today".
+    pub since: Option<String>,
+    /// Window end, same forms as `since`.
+    pub until: Option<String>,
+    /// Priority filter: a name or number ("err", "3") meaning that level and
+    /// more severe, or a range ("warning..err").
+    pub priority: Option<String>,
+    /// Boot offset: 0 is the current boot, -1 the previous one. Omit to search
+    /// every boot in the journal.
+    pub boot: Option<i32>,
+    /// PCRE2 pattern matched against MESSAGE (`journalctl --grep`).
+    pub grep: Option<String>,
+    /// Extra journal fields to return per entry, e.g. "_HOSTNAME",
+    /// "CODE_FILE", "_KERNEL_SUBSYSTEM".
+    #[serde(default)]
+    pub fields: Vec<String>,
+    /// Newest entries to return, clamped to 1..=1000. Default 200.
+    pub lines: Option<u32>,
+    /// Query the calling user's journal instead of the system journal.
+    #[serde(default)]
+    pub user: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryResult {
+    /// The exact journalctl argv, so the query can be re-run by hand.
+    pub command: Vec<String>,
+    pub returned: usize,
+    /// True when the byte budget cut the result short of `lines`.
+    pub truncated: bool,
+    pub entries: Vec<QueryEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryEntry {
+    #[serde(flatten)]
+    pub entry: Entry,
+    pub boot_id: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Query {
+    /// A one-line description for the audit log.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.units.is_empty() {
+            parts.push(format!("units={}", self.units.join(",")));
+        }
+        for (k, v) in [
+            ("since", &self.since),
+            ("until", &self.until),
+            ("priority", &self.priority),
+            ("grep", &self.grep),
+        ] {
+            if let Some(v) = v {
+                parts.push(format!("{k}={v}"));
+            }
+        }
+        if let Some(b) = self.boot {
+            parts.push(format!("boot={b}"));
+        }
+        if self.user {
+            parts.push("user".into());
+        }
+        parts.join(" ")
+    }
+
+    /// Validates the query and builds the journalctl argv.
+    pub fn to_args(&self) -> Result<Vec<String>, String> {
+        let mut args = Vec::new();
+        if self.user {
+            args.push("--user".to_string());
+        }
+        for unit in &self.units {
+            validate::check_unit_pattern(unit)?;
+            args.push(format!("--unit={unit}"));
+        }
+        if let Some(since) = &self.since {
+            validate::check_time(since)?;
+            args.push(format!("--since={since}"));
+        }
+        if let Some(until) = &self.until {
+            validate::check_time(until)?;
+            args.push(format!("--until={until}"));
+        }
+        if let Some(p) = &self.priority {
+            validate_priority(p)?;
+            args.push(format!("--priority={p}"));
+        }
+        if let Some(b) = self.boot {
+            args.push(format!("--boot={b}"));
+        }
+        if let Some(g) = &self.grep {
+            if g.is_empty() || g.len() > 256 || g.chars().any(char::is_control) {
+                return Err("grep must be 1..=256 printable characters".into());
+            }
+            args.push(format!("--grep={g}"));
+        }
+        let mut fields: Vec<&str> = BASE_FIELDS.to_vec();
+        for f in &self.fields {
+            validate_field(f)?;
+            if !fields.contains(&f.as_str()) {
+                fields.push(f);
+            }
+        }
+        args.push(format!("--output-fields={}", fields.join(",")));
+        let lines = self.lines.unwrap_or(DEFAULT_LINES).clamp(1, MAX_LINES);
+        args.push(format!("--lines={lines}"));
+        args.extend(["--output=json", "--no-pager"].map(String::from));
+        Ok(args)
+    }
+}
+
+pub fn run(q: &Query) -> String {
+    let args = match q.to_args() {
+        Ok(a) => a,
+        Err(e) => return format!("[denied] {e}"),
+    };
+    let argv: Vec<&str> = args.iter().map(String::as_str).collect();
+    match journal::query(&argv) {
+        Ok(raw) => {
+            let mut command = vec!["journalctl".to_string()];
+            command.extend(args.iter().cloned());
+            oligarchy_mcp_core::report::json(&collect(raw, &q.fields, command))
+        }
+        Err(e) => format!("[error] {e}"),
+    }
+}
+
+/// Turns decoded entries into the report, newest last (journalctl's order),
+/// dropping the OLDEST entries if the byte budget is exceeded.
+fn collect(raw: Vec<journal::Fields>, extra: &[String], command: Vec<String>) -> QueryResult {
+    let mut entries = Vec::new();
+    let mut budget = MAX_REPORT_BYTES;
+    let mut truncated = false;
+    for f in raw.iter().rev() {
+        let fields: BTreeMap<String, String> = extra
+            .iter()
+            .filter_map(|k| f.get(k).map(|v| (k.clone(), journal::truncate(v, journal::MAX_MESSAGE_CHARS))))
+            .collect();
+        let entry = Entry::from_fields(f);
+        let size = entry.message.len() + fields.values().map(String::len).sum::<usize>() + 128;
+        if size > budget {
+            truncated = true;
+            break;
+        }
+        budget -= size;
+        entries.push(QueryEntry { entry, boot_id: f.get("_BOOT_ID").cloned(), fields });
+    }
+    entries.reverse();
+    QueryResult { command, returned: entries.len(), truncated, entries }
+}
+
+fn validate_priority(p: &str) -> Result<(), String> {
+    let level = |s: &str| {
+        PRIORITY_NAMES.contains(&s) || matches!(s.parse::<u8>(), Ok(n) if n <= 7)
+    };
+    let ok = match p.split_once("..") {
+        Some((from, to)) => level(from) && level(to),
+        None => level(p),
+    };
+    if ok {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid priority {p:?}: use 0-7, {} or a FROM..TO range",
+            PRIORITY_NAMES.join("/")
+        ))
+    }
+}
+
+/// Journal field names are upper-case ASCII, digits and `_`.
+fn validate_field(f: &str) -> Result<(), String> {
+    let ok = !f.is_empty()
+        && f.len() <= 64
+        && f.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("invalid journal field name: {f:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_flag_safe_argv() {
+        let q = Query {
+            units: vec!["pipewire*.service".into(), "netjack2.service".into()],
+            since: Some("-2h".into()),
+            priority: Some("warning..err".into()),
+            boot: Some(-1),
+            grep: Some("xhci_hcd.*(reset|disconnect)".into()),
+            fields: vec!["_KERNEL_SUBSYSTEM".into()],
+            lines: Some(5_000),
+            ..Default::default()
+        };
+        let args = q.to_args().unwrap();
+        assert!(args.contains(&"--unit=pipewire*.service".to_string()));
+        assert!(args.contains(&"--since=-2h".to_string()));
+        assert!(args.contains(&"--boot=-1".to_string()));
+        assert!(args.contains(&"--lines=1000".to_string()), "lines must be clamped");
+        assert!(args.iter().any(|a| a.starts_with("--output-fields=") && a.ends_with(",_KERNEL_SUBSYSTEM")));
+        assert!(args.iter().all(|a| a.starts_with("--")), "every arg is an --opt=value pair");
+    }
+
+    #[test]
+    fn rejects_option_injection_and_bad_values() {
+        let bad = [
+            Query { units: vec!["--flush".into()], ..Default::default() },
+            Query { since: Some("today\n--vacuum-size=1".into()), ..Default::default() },
+            Query { priority: Some("loud".into()), ..Default::default() },
+            Query { priority: Some("9".into()), ..Default::default() },
+            Query { fields: vec!["message; rm".into()], ..Default::default() },
+        ];
+        for q in bad {
+            assert!(q.to_args().is_err(), "accepted {q:?}");
+        }
+        assert!(run(&Query { units: vec!["-x".into()], ..Default::default() }).starts_with("[denied]"));
+    }
+
+    #[test]
+    fn collects_extra_fields_and_respects_budget() {
+        let raw = journal::parse(
+            r#"{"__REALTIME_TIMESTAMP":"1760000000000000","_BOOT_ID":"b1","MESSAGE":"first","_KERNEL_SUBSYSTEM":"usb"}
+{"__REALTIME_TIMESTAMP":"1760000001000000","_BOOT_ID":"b1","MESSAGE":"second"}"#,
+        );
+        let r = collect(raw.clone(), &["_KERNEL_SUBSYSTEM".into()], vec![]);
+        assert_eq!(r.returned, 2);
+        assert!(!r.truncated);
+        assert_eq!(r.entries[0].fields["_KERNEL_SUBSYSTEM"], "usb");
+        assert!(r.entries[1].fields.is_empty());
+        assert_eq!(r.entries[1].boot_id.as_deref(), Some("b1"));
+
+        let big = format!(r#"{{"MESSAGE":"{}"}}"#, "x".repeat(1_900));
+        let many = journal::parse(&vec![big; 200].join("\n"));
+        let r = collect(many, &[], vec![]);
+        assert!(r.truncated);
+        assert!(r.returned < 200);
+    }
+}
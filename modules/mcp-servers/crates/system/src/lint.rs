@@ -0,0 +1,236 @@
+//! `lint_flake` — formatting and lint report for the flake's `.nix` files.
+//!
+//! Runs `nixfmt --check` over the files (or a sandboxed subpath), and for
+//! each file that would be reformatted renders a unified diff by piping the
+//! source through `nixfmt` on stdin — nothing is ever written back. Optional
+//! static checks ([`crate::nix_scan`]) flag dead `let` bindings and unused
+//! lambda arguments.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use oligarchy_mcp_core::runner::{self, HEAVY_TIMEOUT, QUICK_TIMEOUT};
+use oligarchy_mcp_core::sandbox;
+use serde::Serialize;
+
+use crate::{diff, nix_scan, ASPECT};
+
+/// Files per `nixfmt --check` invocation.
+const CHECK_BATCH: usize = 100;
+/// Files that get a diff preview; the rest are only listed.
+const MAX_DIFFS: usize = 20;
+const MAX_DIFF_BYTES: usize = 32 * 1024;
+const MAX_TOTAL_DIFF_BYTES: usize = 256 * 1024;
+const MAX_FILE_BYTES: usize = 1024 * 1024;
+
+#[derive(Debug, Default, Serialize)]
+pub struct LintReport {
+    pub files_checked: usize,
+    pub unformatted: usize,
+    /// Only files with at least one diagnostic, by path relative to the flake.
+    pub files: BTreeMap<String, FileReport>,
+    pub unavailable: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct FileReport {
+    pub diagnostics: Vec<Diagnostic>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub line: Option<usize>,
+    /// `format`, `parse-error`, `dead-let` or `unused-arg`.
+    pub kind: &'static str,
+    pub message: String,
+}
+
+pub fn report(path: Option<&str>, static_checks: bool) -> String {
+    let base = sandbox::flake_dir();
+    let files = match collect(&base, path.unwrap_or(".")) {
+        Ok(f) => f,
+        Err(e) => return format!("[error] {e}"),
+    };
+    let mut report = LintReport { files_checked: files.len(), ..Default::default() };
+
+    for batch in files.chunks(CHECK_BATCH) {
+        let abs: Vec<String> = batch.iter().map(|f| base.join(f).display().to_string()).collect();
+        let mut args = vec!["--check"];
+        args.extend(abs.iter().map(String::as_str));
+        match runner::run_allow_empty(ASPECT, "nixfmt", &args, HEAVY_TIMEOUT) {
+            Ok(out) => {
+                for (file, d) in parse_check(&out, &base, batch) {
+                    report.files.entry(file).or_default().diagnostics.push(d);
+                }
+            }
+            Err(e) => {
+                report.unavailable.push(format!("nixfmt --check: {e}"));
+                break;
+            }
+        }
+    }
+
+    let unformatted: Vec<String> = report
+        .files
+        .iter()
+        .filter(|(_, r)| r.diagnostics.iter().any(|d| d.kind == "format"))
+        .map(|(f, _)| f.clone())
+        .collect();
+    report.unformatted = unformatted.len();
+    let mut budget = MAX_TOTAL_DIFF_BYTES;
+    for file in unformatted.iter().take(MAX_DIFFS) {
+        let preview = sandbox::read_file(&base, file, MAX_FILE_BYTES)
+            .map_err(|e| e.to_string())
+            .and_then(|src| {
+                runner::run_with_stdin(ASPECT, "nixfmt", &[], &src, QUICK_TIMEOUT)
+                    .map(|formatted| diff::unified(&src, &formatted, file, 3, MAX_DIFF_BYTES.min(budget)))
+                    .map_err(|e| e.to_string())
+            });
+        match preview {
+            Ok(d) => {
+                budget = budget.saturating_sub(d.len());
+                report.files.entry(file.clone()).or_default().diff = Some(d);
+            }
+            Err(e) => report.unavailable.push(format!("diff preview for {file}: {e}")),
+        }
+        if budget == 0 {
+            break;
+        }
+    }
+
+    if static_checks {
+        for file in &files {
+            let src = match sandbox::read_file(&base, file, MAX_FILE_BYTES) {
+                Ok(s) => s,
+                Err(e) => {
+                    report.unavailable.push(format!("{file}: {e}"));
+                    continue;
+                }
+            };
+            for f in nix_scan::check(&src) {
+                let message = match f.kind {
+                    "dead-let" => format!("let binding `{}` is never used", f.name),
+                    _ => format!("argument `{}` is never used", f.name),
+                };
+                report.files.entry(file.clone()).or_default().diagnostics.push(Diagnostic {
+                    line: Some(f.line),
+                    kind: f.kind,
+                    message,
+                });
+            }
+        }
+    }
+
+    oligarchy_mcp_core::report::json(&report)
+}
+
+/// The `.nix` files under `rel` (a file or directory inside the flake), as
+/// paths relative to the flake root.
+fn collect(base: &Path, rel: &str) -> Result<Vec<String>, String> {
+    let target = sandbox::resolve_within(base, rel).map_err(|e| e.to_string())?;
+    let mut out = Vec::new();
+    if target.is_dir() {
+        crate::walk_nix(base, &target, &mut out);
+    } else if target.extension().and_then(|x| x.to_str()) == Some("nix") {
+        if let Ok(r) = target.strip_prefix(base) {
+            out.push(r.to_string_lossy().into_owned());
+        }
+    } else {
+        return Err(format!("{rel} is not a .nix file or directory"));
+    }
+    out.sort();
+    Ok(out)
+}
+
+/// Maps `nixfmt --check` output back to files. A `<path>: not formatted`
+/// line is a `format` diagnostic; `<path>:LINE:COL: …` starts a parse error,
+/// and the indented excerpt lines that follow are folded into its message.
+fn parse_check(out: &str, base: &Path, files: &[String]) -> Vec<(String, Diagnostic)> {
+    let mut result: Vec<(String, Diagnostic)> = Vec::new();
+    for line in out.lines() {
+        let known = files.iter().find_map(|f| {
+            let abs = base.join(f).display().to_string();
+            line.strip_prefix(&abs).map(|rest| (f, rest))
+        });
+        match known {
+            Some((f, rest)) if rest.trim_start_matches(':').trim().starts_with("not formatted") => {
+                result.push((
+                    f.clone(),
+                    Diagnostic { line: None, kind: "format", message: "not formatted".into() },
+                ));
+            }
+            Some((f, rest)) => {
+                let mut parts = rest.trim_start_matches(':').splitn(3, ':');
+                let line_no = parts.next().and_then(|l| l.trim().parse().ok());
+                let message = parts.last().unwrap_or(rest).trim().to_string();
+                result.push((f.clone(), Diagnostic { line: line_no, kind: "parse-error", message }));
+            }
+            None => {
+                if let Some((_, d)) = result.last_mut().filter(|(_, d)| d.kind == "parse-error") {
+                    if d.message.len() < 1_000 && !line.trim().is_empty() {
+                        d.message.push('\n');
+                        d.message.push_str(line.trim_end());
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_check_output_to_files() {
+        let base = Path::new("/etc/nixos");
+        let files = vec!["flake.nix".to_string(), "modules/audio.nix".to_string()];
+        let out = "/etc/nixos/flake.nix: not formatted\n\
+                   /etc/nixos/modules/audio.nix:12:5:\n  |\n12 |     foo = ;\n  |     ^\nunexpected ';'";
+        let r = parse_check(out, base, &files);
+        assert_eq!(r.len(), 2);
+        assert_eq!(r[0].0, "flake.nix");
+        assert_eq!(r[0].1.kind, "format");
+        assert_eq!(r[1].0, "modules/audio.nix");
+        assert_eq!(r[1].1.kind, "parse-error");
+        assert_eq!(r[1].1.line, Some(12));
+        assert!(r[1].1.message.ends_with("unexpected ';'"));
+    }
+
+    #[test]
+    fn collect_refuses_escape_and_non_nix() {
+        let tmp = std::env::temp_dir().join("lint-collect");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(tmp.join("modules")).unwrap();
+        std::fs::write(tmp.join("flake.nix"), "{ }").unwrap();
+        std::fs::write(tmp.join("modules/a.nix"), "{ }").unwrap();
+        std::fs::write(tmp.join("README.md"), "").unwrap();
+        let base = tmp.canonicalize().unwrap();
+        assert_eq!(collect(&base, ".").unwrap(), ["flake.nix", "modules/a.nix"]);
+        assert_eq!(collect(&base, "modules/a.nix").unwrap(), ["modules/a.nix"]);
+        assert!(collect(&base, "README.md").is_err());
+        assert!(collect(&base, "../").is_err());
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn collect_skips_symlinks() {
+        let tmp = std::env::temp_dir().join("lint-collect-links");
+        let store = std::env::temp_dir().join("lint-collect-store");
+        let _ = std::fs::remove_dir_all(&tmp);
+        let _ = std::fs::remove_dir_all(&store);
+        std::fs::create_dir_all(&tmp).unwrap();
+        std::fs::create_dir_all(&store).unwrap();
+        std::fs::write(tmp.join("flake.nix"), "{ }").unwrap();
+        std::fs::write(store.join("default.nix"), "{ }").unwrap();
+        std::os::unix::fs::symlink(&store, tmp.join("result")).unwrap();
+        std::os::unix::fs::symlink(store.join("default.nix"), tmp.join("linked.nix")).unwrap();
+        let base = tmp.canonicalize().unwrap();
+        assert_eq!(collect(&base, ".").unwrap(), ["flake.nix"]);
+        let _ = std::fs::remove_dir_all(&tmp);
+        let _ = std::fs::remove_dir_all(&store);
+    }
+}
@@ -1,7 +1,8 @@
 //! oligarchy-system-mcp — read-only MCP server for the system aspect.
 //!
-//! Tools: `system_status`, `service_status`, `journal_tail`, `kernel_options`,
-//! `gpu_options`, `dry_build`, `flake_check`, `list_modules`, `read_module`.
+//! Tools: `system_status`, `health_summary`, `service_status`, `journal_tail`,
+//! `journal_query`, `kernel_options`, `gpu_options`, `dry_build`, `flake_check`,
+//! `flake_inputs`, `lint_flake`, `store_report`, `list_modules`, `read_module`.
 //! All shell-outs go through `oligarchy_mcp_core::runner::run`, which enforces
 //! the `SYSTEM` allowlist at runtime. See `docs/mcp-servers-roadmap.md` §4.1.
 
@@ -9,9 +10,20 @@ use oligarchy_mcp_core::audit;
 use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT, HEAVY_TIMEOUT};
 use oligarchy_mcp_core::runner_mcp;
 use oligarchy_mcp_core::sandbox;
+use oligarchy_mcp_core::validate::{self, UnitName, Validate};
 
 use rmcp::{ServerHandler, model::{ServerCapabilities, ServerInfo}, tool};
 
+mod advisory;
+mod diff;
+mod flake_inputs;
+mod health;
+mod journal;
+mod journal_query;
+mod lint;
+mod nix_scan;
+mod store;
+
 const ASPECT: &str = "system";
 
 #[derive(Debug, Clone, Default)]
@@ -26,9 +38,19 @@ impl Server {
             .unwrap_or_else(|e| format!("[error] {e}"))
     }
 
+    #[tool(description = "Post-rebuild health check for the current boot: failed system and user units, priority<=3 journal messages, OOM kills, kernel taint flags and the firmware/loader/kernel/initrd/userspace boot-time split. Structured JSON.")]
+    fn health_summary(&self) -> String {
+        audit::tool(ASPECT, "health_summary", "");
+        health::report()
+    }
+
     #[tool(description = "systemctl status for a unit (read-only). Tries the user manager, then system.")]
-    fn service_status(&self, #[tool(param)] unit: String) -> String {
-        audit::tool(ASPECT, "service_status", &unit);
+    fn service_status(&self, #[tool(param)] unit: UnitName) -> String {
+        audit::tool(ASPECT, "service_status", unit.as_str());
+        let unit = match validate::admit(ASPECT, "service_status", &unit) {
+            Ok(u) => u,
+            Err(denied) => return denied,
+        };
         runner::run(
             ASPECT,
             "systemctl",
@@ -47,10 +69,14 @@ impl Server {
     }
 
     #[tool(description = "Last N journal lines for a unit (read-only). lines is clamped to 1..500.")]
-    fn journal_tail(&self, #[tool(param)] unit: String, #[tool(param)] lines: u32) -> String {
+    fn journal_tail(&self, #[tool(param)] unit: UnitName, #[tool(param)] lines: u32) -> String {
         let n = lines.clamp(1, 500);
         let n_str = n.to_string();
-        audit::tool(ASPECT, "journal_tail", &format!("{unit} n={n}"));
+        audit::tool(ASPECT, "journal_tail", &format!("{} n={n}", unit.as_str()));
+        let unit = match validate::admit(ASPECT, "journal_tail", &unit) {
+            Ok(u) => u,
+            Err(denied) => return denied,
+        };
         runner::run(
             ASPECT,
             "journalctl",
@@ -68,6 +94,12 @@ impl Server {
         .unwrap_or_else(|e| format!("[error] {e}"))
     }
 
+    #[tool(description = "Structured journal search: units, since/until window, priority (level or range), boot offset, message grep and extra output fields. Returns decoded entries as JSON; clamped to 1000 entries and a 256 KiB budget.")]
+    fn journal_query(&self, #[tool(aggr)] query: journal_query::Query) -> String {
+        audit::tool(ASPECT, "journal_query", &query.summary());
+        journal_query::run(&query)
+    }
+
     #[tool(description = "Available custom.kernel.variant values.")]
     fn kernel_options(&self) -> String {
         audit::tool(ASPECT, "kernel_options", "");
@@ -111,6 +143,33 @@ impl Server {
         .unwrap_or_else(|e| format!("[error] {e}"))
     }
 
+    #[tool(description = "Flake input freshness from flake.lock: source, locked rev, commit date and age per direct input, plus commits/days behind the followed branch when a cached upstream snapshot exists (never fetches). advisory_db (a JSON file inside the flake) additionally checks package versions in the current system closure against that advisory list.")]
+    fn flake_inputs(&self, #[tool(param)] advisory_db: Option<String>) -> String {
+        audit::tool(ASPECT, "flake_inputs", advisory_db.as_deref().unwrap_or(""));
+        flake_inputs::report(advisory_db.as_deref())
+    }
+
+    #[tool(description = "nixfmt --check over the flake's .nix files (or a sandboxed subpath), with a unified diff preview per unformatted file; static_checks adds heuristic dead-let and unused-argument findings. Never writes any file.")]
+    fn lint_flake(&self, #[tool(param)] path: Option<String>, #[tool(param)] static_checks: bool) -> String {
+        audit::tool(
+            ASPECT,
+            "lint_flake",
+            &format!("{} static_checks={static_checks}", path.as_deref().unwrap_or(".")),
+        );
+        lint::report(path.as_deref(), static_checks)
+    }
+
+    #[tool(description = "Nix store health (read-only): store size, GC roots by owner (system/user profiles, result links), the biggest closures, dead-path bytes a GC would reclaim and hard-link optimisation savings. top (default 10) is clamped to 1..50; include_dead runs `nix-store --gc --print-dead` (slow). Heavy.")]
+    fn store_report(
+        &self,
+        #[tool(param)] top: Option<u32>,
+        #[tool(param)] include_dead: bool,
+    ) -> String {
+        let top = top.unwrap_or(10);
+        audit::tool(ASPECT, "store_report", &format!("top={top} include_dead={include_dead}"));
+        store::report(top, include_dead)
+    }
+
     #[tool(description = "List the .nix files in the Oligarchy flake repository.")]
     fn list_modules(&self) -> String {
         audit::tool(ASPECT, "list_modules", "");
@@ -136,6 +195,9 @@ impl Server {
     }
 }
 
+/// Collects `.nix` files under `cur`, relative to `base`. Symlinks are not
+/// followed: a `result` link would otherwise pull store paths outside the
+/// flake into the listing.
 fn walk_nix(base: &std::path::Path, cur: &std::path::Path, out: &mut Vec<String>) {
     if let Ok(entries) = std::fs::read_dir(cur) {
         for e in entries.flatten() {
@@ -143,9 +205,10 @@ fn walk_nix(base: &std::path::Path, cur: &std::path::Path, out: &mut Vec<String>
             if p.file_name().and_then(|n| n.to_str()) == Some(".git") {
                 continue;
             }
-            if p.is_dir() {
+            let Ok(ft) = e.file_type() else { continue };
+            if ft.is_dir() {
                 walk_nix(base, &p, out);
-            } else if p.extension().and_then(|x| x.to_str()) == Some("nix") {
+            } else if ft.is_file() && p.extension().and_then(|x| x.to_str()) == Some("nix") {
                 if let Ok(rel) = p.strip_prefix(base) {
                     out.push(rel.to_string_lossy().into_owned());
                 }
@@ -171,7 +234,7 @@ async fn main() -> anyhow::Result<()> {
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .init();
     tracing::info!(aspect = ASPECT, flake_dir = %sandbox::flake_dir().display(), "starting");
-    runner_mcp::serve(Server::default()).await
+    runner_mcp::serve(Server).await
 }
 
 #[cfg(test)]
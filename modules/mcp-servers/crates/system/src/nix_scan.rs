@@ -0,0 +1,357 @@
+//! Heuristic static checks for Nix source: dead `let` bindings and unused
+//! lambda arguments.
+//!
+//! This is a tokenizer, not a parser. Comments and string bodies are skipped
+//! (interpolations inside strings are scanned), and a name counts as used if
+//! the identifier appears anywhere else in the file, in any scope. That errs
+//! towards silence: shadowing or an attribute of the same name can hide a
+//! finding, but a reported binding really is referenced nowhere. Names
+//! starting with `_` are treated as intentionally unused, as `deadnix` does.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+const KEYWORDS: &[&str] = &[
+    "let", "in", "inherit", "rec", "with", "if", "then", "else", "assert", "or",
+];
+
+/// Characters allowed after `scheme:` in an unquoted Nix URI.
+const URI_CHARS: &str = "%/?:@&=+$,-_.!~*'";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub line: usize,
+    /// `dead-let` or `unused-arg`.
+    pub kind: &'static str,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    Punct(&'static str),
+    Other,
+}
+
+#[derive(Debug)]
+struct Token {
+    tok: Tok,
+    line: usize,
+}
+
+enum Ctx {
+    /// Code, with the `{` depth opened inside this context.
+    Code(u32),
+    Str,
+    IndStr,
+}
+
+fn tokenize(src: &str) -> Vec<Token> {
+    let c: Vec<char> = src.chars().collect();
+    let mut out = Vec::new();
+    let mut stack = vec![Ctx::Code(0)];
+    let mut line = 1;
+    let mut i = 0;
+    let push = |out: &mut Vec<Token>, tok, line| out.push(Token { tok, line });
+    while i < c.len() {
+        let ch = c[i];
+        let next = c.get(i + 1).copied();
+        if ch == '\n' {
+            line += 1;
+        }
+        let nested = stack.len() > 1;
+        match stack.last_mut().expect("context stack is never empty") {
+            Ctx::Str => {
+                match (ch, next) {
+                    ('\\', _) => i += 1,
+                    ('$', Some('$')) => i += 1,
+                    ('$', Some('{')) => {
+                        stack.push(Ctx::Code(0));
+                        push(&mut out, Tok::Punct("("), line);
+                        i += 1;
+                    }
+                    ('"', _) => {
+                        stack.pop();
+                    }
+                    _ => {}
+                }
+                if next == Some('\n') && ch == '\\' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            Ctx::IndStr => {
+                match (ch, next) {
+                    ('\'', Some('\'')) => match c.get(i + 2) {
+                        Some('$') | Some('\'') => i += 2,
+                        Some('\\') => i += 3,
+                        _ => {
+                            stack.pop();
+                            i += 1;
+                        }
+                    },
+                    ('$', Some('$')) => i += 1,
+                    ('$', Some('{')) => {
+                        stack.push(Ctx::Code(0));
+                        push(&mut out, Tok::Punct("("), line);
+                        i += 1;
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+            Ctx::Code(depth) => {
+                if ch.is_whitespace() {
+                    i += 1;
+                } else if ch == '#' {
+                    while i < c.len() && c[i] != '\n' {
+                        i += 1;
+                    }
+                } else if ch == '/' && next == Some('*') {
+                    i += 2;
+                    while i < c.len() && !(c[i] == '*' && c.get(i + 1) == Some(&'/')) {
+                        if c[i] == '\n' {
+                            line += 1;
+                        }
+                        i += 1;
+                    }
+                    i += 2;
+                } else if ch == '"' {
+                    stack.push(Ctx::Str);
+                    i += 1;
+                } else if ch == '\'' && next == Some('\'') {
+                    stack.push(Ctx::IndStr);
+                    i += 2;
+                } else if ch.is_ascii_alphabetic() || ch == '_' {
+                    let start = i;
+                    while i < c.len() && (c[i].is_ascii_alphanumeric() || "_'-".contains(c[i])) {
+                        i += 1;
+                    }
+                    let is_uri = c.get(i) == Some(&':')
+                        && c.get(i + 1).is_some_and(|&n| n.is_ascii_alphanumeric() || URI_CHARS.contains(n));
+                    if is_uri {
+                        while i < c.len() && (c[i].is_ascii_alphanumeric() || URI_CHARS.contains(c[i]) || c[i] == ':') {
+                            i += 1;
+                        }
+                        push(&mut out, Tok::Other, line);
+                    } else {
+                        push(&mut out, Tok::Ident(c[start..i].iter().collect()), line);
+                    }
+                } else {
+                    let two: String = c[i..(i + 2).min(c.len())].iter().collect();
+                    let three: String = c[i..(i + 3).min(c.len())].iter().collect();
+                    let (tok, len) = if three == "..." {
+                        (Tok::Punct("..."), 3)
+                    } else if ["==", "!=", "<=", ">=", "&&", "||", "->", "//", "++"].contains(&two.as_str()) {
+                        (Tok::Other, 2)
+                    } else {
+                        let p = match ch {
+                            '{' => {
+                                *depth += 1;
+                                Some("{")
+                            }
+                            '}' if *depth == 0 && nested => {
+                                // End of a `${…}` interpolation.
+                                stack.pop();
+                                Some(")")
+                            }
+                            '}' => {
+                                *depth = depth.saturating_sub(1);
+                                Some("}")
+                            }
+                            '(' => Some("("),
+                            ')' => Some(")"),
+                            '[' => Some("["),
+                            ']' => Some("]"),
+                            '=' => Some("="),
+                            ';' => Some(";"),
+                            ':' => Some(":"),
+                            ',' => Some(","),
+                            '?' => Some("?"),
+                            '@' => Some("@"),
+                            '.' => Some("."),
+                            _ => None,
+                        };
+                        (p.map(Tok::Punct).unwrap_or(Tok::Other), 1)
+                    };
+                    push(&mut out, tok, line);
+                    i += len;
+                }
+            }
+        }
+    }
+    out
+}
+
+pub fn check(src: &str) -> Vec<Finding> {
+    let toks = tokenize(src);
+    let name = |i: usize| match toks.get(i).map(|t| &t.tok) {
+        Some(Tok::Ident(s)) if !KEYWORDS.contains(&s.as_str()) => Some(s.as_str()),
+        _ => None,
+    };
+    let is = |i: usize, p: &str| matches!(toks.get(i).map(|t| &t.tok), Some(Tok::Punct(q)) if *q == p);
+    let is_kw = |i: usize, k: &str| matches!(toks.get(i).map(|t| &t.tok), Some(Tok::Ident(s)) if s == k);
+
+    let mut uses: HashMap<&str, usize> = HashMap::new();
+    for i in 0..toks.len() {
+        if let Some(n) = name(i) {
+            *uses.entry(n).or_default() += 1;
+        }
+    }
+    let unused = |n: &str| !n.starts_with('_') && uses.get(n) == Some(&1);
+
+    // Bracket depth before each token, and the opening `{` for each `}`.
+    let mut depth = Vec::with_capacity(toks.len());
+    let mut opener = vec![None; toks.len()];
+    let mut open = Vec::new();
+    let mut d = 0usize;
+    for (i, t) in toks.iter().enumerate() {
+        if let Tok::Punct(p) = t.tok {
+            if matches!(p, "}" | ")" | "]") {
+                d = d.saturating_sub(1);
+                if p == "}" {
+                    opener[i] = open.pop();
+                }
+            }
+        }
+        depth.push(d);
+        if let Tok::Punct(p) = t.tok {
+            if matches!(p, "{" | "(" | "[") {
+                d += 1;
+                if p == "{" {
+                    open.push(i);
+                }
+            }
+        }
+    }
+
+    let mut findings = Vec::new();
+    let mut report = |i: usize, kind, n: &str| {
+        findings.push(Finding { line: toks[i].line, kind, name: n.to_string() });
+    };
+
+    let mut lets: Vec<usize> = Vec::new();
+    for i in 0..toks.len() {
+        if is_kw(i, "let") && !is(i + 1, "{") {
+            lets.push(depth[i]);
+            continue;
+        }
+        if is_kw(i, "in") && lets.last() == Some(&depth[i]) {
+            lets.pop();
+            continue;
+        }
+        let at_binding = lets.last() == Some(&depth[i])
+            && i > 0
+            && (is(i - 1, ";") || is_kw(i - 1, "let"));
+        if at_binding {
+            if let Some(n) = name(i) {
+                if is(i + 1, "=") && unused(n) {
+                    report(i, "dead-let", n);
+                }
+            } else if is_kw(i, "inherit") {
+                let mut j = i + 1;
+                if is(j, "(") {
+                    while j < toks.len() && !(is(j, ")") && depth[j] == depth[i]) {
+                        j += 1;
+                    }
+                    j += 1;
+                }
+                while j < toks.len() && !is(j, ";") {
+                    if let Some(n) = name(j) {
+                        if unused(n) {
+                            report(j, "dead-let", n);
+                        }
+                    }
+                    j += 1;
+                }
+            }
+        }
+
+        // `x: …` and `args@{ … }:`.
+        if let Some(n) = name(i) {
+            if is(i + 1, ":") && unused(n) {
+                report(i, "unused-arg", n);
+            }
+        }
+        // `{ a, b ? d, ... }:`, `{ … }@args:` and `args@{ … }:`.
+        if is(i, "}") {
+            let is_pattern = is(i + 1, ":") || (is(i + 1, "@") && name(i + 2).is_some() && is(i + 3, ":"));
+            if let (true, Some(o)) = (is_pattern, opener[i]) {
+                if o >= 2 && is(o - 1, "@") {
+                    if let Some(n) = name(o - 2).filter(|n| unused(n)) {
+                        report(o - 2, "unused-arg", n);
+                    }
+                }
+                for j in o + 1..i {
+                    if depth[j] == depth[o] + 1 && (j == o + 1 || is(j - 1, ",")) {
+                        if let Some(n) = name(j).filter(|n| unused(n)) {
+                            report(j, "unused-arg", n);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    findings.sort_by_key(|f| f.line);
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(src: &str, kind: &str) -> Vec<String> {
+        check(src).into_iter().filter(|f| f.kind == kind).map(|f| f.name).collect()
+    }
+
+    #[test]
+    fn finds_dead_let_bindings() {
+        let src = r#"
+{ pkgs, ... }:
+let
+  used = pkgs.hello;
+  dead = "never read";
+  _ignored = 1;
+  inherit (pkgs) lib curl;
+  nested = let inner = 1; gone = 2; in inner;
+in
+{
+  environment.systemPackages = [ used nested ];
+  meta.text = "${lib.version}";
+}
+"#;
+        assert_eq!(names(src, "dead-let"), ["dead", "curl", "gone"]);
+        assert_eq!(check(src).iter().find(|f| f.name == "dead").unwrap().line, 5);
+    }
+
+    #[test]
+    fn finds_unused_args() {
+        let src = r#"
+{ config, lib, pkgs, ... }:
+{
+  options.x = lib.mkOption { };
+  config.y = map (n: 1) pkgs.list;
+  config.z = builtins.mapAttrs (name: value: value) { };
+  config.w = args@{ a, b ? a, ... }: b;
+}
+"#;
+        assert_eq!(names(src, "unused-arg"), ["n", "name", "args"]);
+    }
+
+    #[test]
+    fn skips_comments_strings_and_uris() {
+        let src = r#"
+let
+  # dead2 = 3;
+  /* dead3 = 4; */
+  url = https://example.org/x.tar.gz;
+  s = "dead4 = ${url}";
+  ind = ''
+    ''${ignored} ${s}
+  '';
+in ind
+"#;
+        assert!(check(src).is_empty(), "{:?}", check(src));
+    }
+}
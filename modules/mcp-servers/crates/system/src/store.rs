@@ -0,0 +1,363 @@
+//! `store_report` — Nix store size, GC roots, biggest closures, what a GC would
+//! reclaim and what store optimisation has already saved.
+//!
+//! Strictly read-only: `nix-store --gc` is only ever passed `--print-roots` or
+//! `--print-dead`, which report without deleting anything, and sizes come from
+//! `nix path-info`. Those two are the only `nix-store` argument lists the
+//! runner accepts (`allowlist::args_allowed` in core), for every caller in the
+//! system aspect. The optimisation figure is computed from the hard-link
+//! counts under `/nix/store/.links` (metadata only, no file contents).
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use oligarchy_mcp_core::runner::{self, HEAVY_TIMEOUT};
+use serde::Serialize;
+
+use crate::ASPECT;
+
+/// Store paths per `nix path-info` invocation, well under ARG_MAX.
+const PATH_INFO_BATCH: usize = 500;
+
+/// Upper bound on `.links` entries examined for the optimisation figure.
+const MAX_LINKS_SCANNED: usize = 2_000_000;
+
+#[derive(Debug, Serialize)]
+pub struct StoreReport {
+    pub store: Option<StoreSize>,
+    pub gc_roots: Vec<GcRoot>,
+    /// Roots per [`RootKind`], including runtime roots not listed above.
+    pub roots_by_kind: BTreeMap<RootKind, usize>,
+    pub biggest_closures: Vec<Closure>,
+    pub dead: Option<DeadPaths>,
+    pub optimise: Option<Optimise>,
+    pub unavailable: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StoreSize {
+    pub paths: usize,
+    pub nar_bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RootKind {
+    /// `/run/current-system`, `/run/booted-system`.
+    ActiveSystem,
+    /// A generation link of the system profile.
+    SystemProfile,
+    /// Per-user and home-manager profiles.
+    UserProfile,
+    /// A `result` link left behind by `nix build` / `nixos-rebuild build`.
+    ResultLink,
+    /// Held by a running process (`/proc/…`, or `{censored}` for non-root).
+    Runtime,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GcRoot {
+    pub link: String,
+    pub target: String,
+    pub kind: RootKind,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Closure {
+    pub path: String,
+    pub closure_bytes: u64,
+    /// The non-runtime roots that keep this path alive.
+    pub held_by: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeadPaths {
+    pub paths: usize,
+    /// NAR size of every dead path — what `nix-collect-garbage` would free.
+    pub reclaimable_bytes: u64,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Optimise {
+    /// `auto-optimise-store` from `/etc/nix/nix.conf`, if set there.
+    pub auto_optimise_store: Option<bool>,
+    pub linked_files: usize,
+    /// Bytes not stored twice because of hard-linking.
+    pub saved_bytes: u64,
+    pub truncated: bool,
+}
+
+pub fn report(top: u32, include_dead: bool) -> String {
+    let top = top.clamp(1, 50) as usize;
+    let mut unavailable = Vec::new();
+
+    let store = match run("nix", &["path-info", "--all", "--size"], HEAVY_TIMEOUT) {
+        Ok(out) => {
+            let sizes = parse_sizes(&out);
+            Some(StoreSize { paths: sizes.len(), nar_bytes: sizes.iter().map(|(_, s)| s).sum() })
+        }
+        Err(e) => {
+            unavailable.push(format!("nix path-info --all: {e}"));
+            None
+        }
+    };
+
+    let all_roots = match run("nix-store", &["--gc", "--print-roots"], HEAVY_TIMEOUT) {
+        Ok(out) => parse_roots(&out),
+        Err(e) => {
+            unavailable.push(format!("nix-store --gc --print-roots: {e}"));
+            Vec::new()
+        }
+    };
+    let mut roots_by_kind = BTreeMap::new();
+    for r in &all_roots {
+        *roots_by_kind.entry(r.kind).or_insert(0) += 1;
+    }
+    let gc_roots: Vec<GcRoot> = all_roots.into_iter().filter(|r| r.kind != RootKind::Runtime).collect();
+
+    let biggest_closures = match closures(&gc_roots, top) {
+        Ok(c) => c,
+        Err(e) => {
+            unavailable.push(format!("nix path-info --closure-size: {e}"));
+            Vec::new()
+        }
+    };
+
+    let dead = if include_dead {
+        match dead_paths() {
+            Ok(d) => Some(d),
+            Err(e) => {
+                unavailable.push(format!("nix-store --gc --print-dead: {e}"));
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let optimise = match scan_links(Path::new("/nix/store/.links"), MAX_LINKS_SCANNED) {
+        Ok(mut o) => {
+            o.auto_optimise_store = std::fs::read_to_string("/etc/nix/nix.conf")
+                .ok()
+                .and_then(|c| auto_optimise_setting(&c));
+            Some(o)
+        }
+        Err(e) => {
+            unavailable.push(format!("/nix/store/.links: {e}"));
+            None
+        }
+    };
+
+    oligarchy_mcp_core::report::json(&StoreReport {
+        store,
+        gc_roots,
+        roots_by_kind,
+        biggest_closures,
+        dead,
+        optimise,
+        unavailable,
+    })
+}
+
+fn run(prog: &str, args: &[&str], timeout: std::time::Duration) -> oligarchy_mcp_core::Result<String> {
+    runner::run_allow_empty(ASPECT, prog, args, timeout)
+}
+
+/// Closure sizes of every distinct root target, largest first.
+fn closures(roots: &[GcRoot], top: usize) -> oligarchy_mcp_core::Result<Vec<Closure>> {
+    let mut held_by: HashMap<&str, Vec<String>> = HashMap::new();
+    for r in roots {
+        held_by.entry(r.target.as_str()).or_default().push(r.link.clone());
+    }
+    let targets: Vec<&str> = held_by.keys().copied().collect();
+    let mut sizes = Vec::new();
+    for batch in targets.chunks(PATH_INFO_BATCH) {
+        let mut args = vec!["path-info", "--closure-size"];
+        args.extend_from_slice(batch);
+        // `--closure-size` walks each closure; 500 of them on a large store
+        // take well over a quick call's budget.
+        sizes.extend(parse_sizes(&run("nix", &args, HEAVY_TIMEOUT)?));
+    }
+    sizes.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+    sizes.truncate(top);
+    Ok(sizes
+        .into_iter()
+        .map(|(path, closure_bytes)| {
+            let mut links = held_by.remove(path.as_str()).unwrap_or_default();
+            links.sort();
+            Closure { path, closure_bytes, held_by: links }
+        })
+        .collect())
+}
+
+fn dead_paths() -> oligarchy_mcp_core::Result<DeadPaths> {
+    let out = run("nix-store", &["--gc", "--print-dead"], HEAVY_TIMEOUT)?;
+    let dead: Vec<&str> = out.lines().map(str::trim).filter(|l| l.starts_with("/nix/store/")).collect();
+    let mut reclaimable_bytes = 0;
+    for batch in dead.chunks(PATH_INFO_BATCH) {
+        let mut args = vec!["path-info", "--size"];
+        args.extend_from_slice(batch);
+        reclaimable_bytes += parse_sizes(&run("nix", &args, HEAVY_TIMEOUT)?)
+            .iter()
+            .map(|(_, s)| s)
+            .sum::<u64>();
+    }
+    Ok(DeadPaths { paths: dead.len(), reclaimable_bytes })
+}
+
+/// Parses `nix path-info --size` / `--closure-size` text output: a store path
+/// followed by a byte count. Error lines for invalid paths are skipped.
+pub fn parse_sizes(text: &str) -> Vec<(String, u64)> {
+    text.lines()
+        .filter_map(|l| {
+            let mut cols = l.split_whitespace();
+            let path = cols.next().filter(|p| p.starts_with("/nix/store/"))?;
+            let size = cols.last()?.parse().ok()?;
+            Some((path.to_string(), size))
+        })
+        .collect()
+}
+
+/// Parses `nix-store --gc --print-roots`: `<link> -> <store path>` per line.
+pub fn parse_roots(text: &str) -> Vec<GcRoot> {
+    text.lines()
+        .filter_map(|l| {
+            let (link, target) = l.split_once(" -> ")?;
+            let target = target.trim();
+            if !target.starts_with("/nix/store/") {
+                return None;
+            }
+            let link = link.trim().to_string();
+            Some(GcRoot { kind: classify_root(&link), link, target: target.to_string() })
+        })
+        .collect()
+}
+
+pub fn classify_root(link: &str) -> RootKind {
+    let name = link.rsplit('/').next().unwrap_or(link);
+    if link.starts_with("/proc/") || link.starts_with("{censored}") || link.starts_with("{temp:") {
+        RootKind::Runtime
+    } else if matches!(link, "/run/current-system" | "/run/booted-system") {
+        RootKind::ActiveSystem
+    } else if link.starts_with("/nix/var/nix/profiles/system") {
+        RootKind::SystemProfile
+    } else if link.starts_with("/nix/var/nix/profiles/per-user/")
+        || link.contains("/.local/state/nix/profiles/")
+        || link.contains("/.nix-profile")
+        || link.contains("home-manager")
+    {
+        RootKind::UserProfile
+    } else if name == "result" || name.starts_with("result-") {
+        RootKind::ResultLink
+    } else {
+        RootKind::Other
+    }
+}
+
+fn auto_optimise_setting(conf: &str) -> Option<bool> {
+    conf.lines().find_map(|l| {
+        let (k, v) = l.split_once('=')?;
+        (k.trim() == "auto-optimise-store").then(|| v.trim() == "true")
+    })
+}
+
+/// Each file under `.links` is one deduplicated content blob; every hard link
+/// beyond the `.links` entry itself and the first store copy is a copy that
+/// would otherwise occupy disk.
+fn scan_links(dir: &Path, cap: usize) -> std::io::Result<Optimise> {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut o = Optimise::default();
+    for entry in std::fs::read_dir(dir)? {
+        if o.linked_files >= cap {
+            o.truncated = true;
+            break;
+        }
+        let Ok(meta) = entry.and_then(|e| e.metadata()) else { continue };
+        o.linked_files += 1;
+        if meta.nlink() > 2 {
+            o.saved_bytes += meta.size() * (meta.nlink() - 2);
+        }
+    }
+    Ok(o)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_roots_and_classifies_them() {
+        let out = "\
+/nix/var/nix/profiles/system-142-link -> /nix/store/aaa-nixos-system-nixos-26.05
+/run/current-system -> /nix/store/aaa-nixos-system-nixos-26.05
+/home/user/Oligarchy/result -> /nix/store/bbb-nixos-system-nixos-26.05
+/home/user/.local/state/nix/profiles/home-manager-31-link -> /nix/store/ccc-home-manager-generation
+/proc/2211/maps -> /nix/store/ddd-glibc-2.40
+{censored} -> /nix/store/eee-firefox-140
+error: cannot read link";
+        let roots = parse_roots(out);
+        assert_eq!(roots.len(), 6);
+        let kinds: Vec<RootKind> = roots.iter().map(|r| r.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                RootKind::SystemProfile,
+                RootKind::ActiveSystem,
+                RootKind::ResultLink,
+                RootKind::UserProfile,
+                RootKind::Runtime,
+                RootKind::Runtime,
+            ]
+        );
+        assert_eq!(classify_root("/etc/nixos/result-dev"), RootKind::ResultLink);
+        assert_eq!(classify_root("/var/lib/something"), RootKind::Other);
+    }
+
+    #[test]
+    fn refuses_mutating_nix_store_args() {
+        for args in [&["--gc"][..], &["--delete", "/nix/store/aaa"], &["--realise", "/nix/store/aaa"], &["--gc", "--print-dead", "--max-freed", "1"], &[]] {
+            let err = run("nix-store", args, runner::QUICK_TIMEOUT).unwrap_err();
+            assert!(err.to_string().contains("not a permitted invocation"), "{args:?}: {err}");
+        }
+    }
+
+    #[test]
+    fn parses_path_info_sizes() {
+        let out = "\
+/nix/store/aaa-nixos-system-nixos-26.05\t 12345
+/nix/store/bbb-glibc-2.40   2000000
+error: path '/nix/store/zzz-gone' is not valid";
+        let sizes = parse_sizes(out);
+        assert_eq!(sizes.len(), 2);
+        assert_eq!(sizes[1], ("/nix/store/bbb-glibc-2.40".to_string(), 2_000_000));
+    }
+
+    #[test]
+    fn reads_auto_optimise_setting() {
+        assert_eq!(auto_optimise_setting("auto-optimise-store = true\n"), Some(true));
+        assert_eq!(auto_optimise_setting("max-jobs = 4\nauto-optimise-store = false"), Some(false));
+        assert_eq!(auto_optimise_setting("max-jobs = 4"), None);
+    }
+
+    #[test]
+    fn counts_hard_link_savings() {
+        let tmp = std::env::temp_dir().join("oligarchy-store-links-test");
+        let _ = std::fs::remove_dir_all(&tmp);
+        let links = tmp.join(".links");
+        std::fs::create_dir_all(&links).unwrap();
+        std::fs::write(links.join("blob"), [0u8; 100]).unwrap();
+        std::fs::write(links.join("single"), [0u8; 7]).unwrap();
+        // Three more links to `blob`: four links in all, two of them savings.
+        std::fs::hard_link(links.join("blob"), tmp.join("a")).unwrap();
+        std::fs::hard_link(links.join("blob"), tmp.join("b")).unwrap();
+        std::fs::hard_link(links.join("blob"), tmp.join("c")).unwrap();
+        let o = scan_links(&links, 10).unwrap();
+        assert_eq!(o.linked_files, 2);
+        assert_eq!(o.saved_bytes, 200);
+        assert!(!o.truncated);
+        assert!(scan_links(&links, 1).unwrap().truncated);
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+}
@@ -56,7 +56,7 @@ fn run() -> ! {
              \n  In `.mcp.json`, register one entry per aspect, e.g.:\n  \
              {{\"mcpServers\": {{\"oligarchy-system\": {{\"command\": \
              \"{0}\", \"args\": [\"system\"]}}}}}}",
-            args.get(0).map(|s| s.as_str()).unwrap_or("oligarchy-mcp")
+            args.first().map(|s| s.as_str()).unwrap_or("oligarchy-mcp")
         );
         std::process::exit(2);
     }
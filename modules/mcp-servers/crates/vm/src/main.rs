@@ -5,6 +5,7 @@ use oligarchy_mcp_core::audit;
 use oligarchy_mcp_core::runner::{self, QUICK_TIMEOUT};
 use oligarchy_mcp_core::runner_mcp;
 use oligarchy_mcp_core::sandbox;
+use oligarchy_mcp_core::validate::{self, Validate, VmName};
 
 use rmcp::{ServerHandler, model::{ServerCapabilities, ServerInfo}, tool};
 
@@ -23,8 +24,12 @@ impl Server {
     }
 
     #[tool(description = "vm-manager status for a single VM (read-only).")]
-    fn vm_status(&self, #[tool(param)] name: String) -> String {
-        audit::tool(ASPECT, "vm_status", &name);
+    fn vm_status(&self, #[tool(param)] name: VmName) -> String {
+        audit::tool(ASPECT, "vm_status", name.as_str());
+        let name = match validate::admit(ASPECT, "vm_status", &name) {
+            Ok(n) => n,
+            Err(denied) => return denied,
+        };
         runner::run(ASPECT, "vm-manager", &["status", &name], QUICK_TIMEOUT)
             .unwrap_or_else(|e| format!("[error] {e}"))
     }
@@ -73,7 +78,7 @@ impl ServerHandler for Server {
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt().init();
     tracing::info!(aspect = ASPECT, "starting");
-    runner_mcp::serve(Server::default()).await
+    runner_mcp::serve(Server).await
 }
 
 #[cfg(test)]
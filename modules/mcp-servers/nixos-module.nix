@@ -100,6 +100,31 @@ in
       '';
     };
 
+    hydrameshRecorder = {
+      enable = mkOption {
+        type = types.bool;
+        default = false;
+        description = ''
+          Sample `hydramesh metrics` and `dcf status` on a timer into the
+          hydramesh aspect's state dir, for the `mesh_history` tool. A
+          per-user timer running `oligarchy-hydramesh-mcp --record`; it
+          appends one JSON line per source per run and never transmits.
+        '';
+      };
+
+      interval = mkOption {
+        type = types.str;
+        default = "1min";
+        description = "Sampling interval (systemd time span, used as OnUnitActiveSec).";
+      };
+
+      retentionDays = mkOption {
+        type = types.ints.positive;
+        default = 30;
+        description = "Day files older than this are deleted by the recorder.";
+      };
+    };
+
     aspects = mkOption {
       type = types.attrsOf (types.submodule {
         options.enable = mkOption {
@@ -124,6 +149,30 @@ in
       };
     })
 
+    # Opt-in metrics recorder for `mesh_history`. A user unit so samples land
+    # in the same per-user state dir the MCP server reads.
+    (mkIf (cfg.enable && cfg.hydrameshRecorder.enable && aspectEnabled "hydramesh") {
+      systemd.user.services.oligarchy-hydramesh-recorder = {
+        description = "Oligarchy hydramesh metrics sample";
+        serviceConfig = {
+          Type = "oneshot";
+          ExecStart = "${aspectPkg "hydramesh"}/bin/oligarchy-hydramesh-mcp --record --retention-days ${toString cfg.hydrameshRecorder.retentionDays}";
+          Environment = [ "OLIGARCHY_MCP_STATE_DIR=${cfg.stateDir}" ];
+          NoNewPrivileges = true;
+          PrivateTmp = true;
+        };
+        path = [ "/run/current-system/sw" ];
+      };
+      systemd.user.timers.oligarchy-hydramesh-recorder = {
+        description = "Sample hydramesh metrics every ${cfg.hydrameshRecorder.interval}";
+        wantedBy = [ "timers.target" ];
+        timerConfig = {
+          OnActiveSec = "1min";
+          OnUnitActiveSec = cfg.hydrameshRecorder.interval;
+        };
+      };
+    })
+
     # Optional hardened systemd unit for long-running agent hosts. Off by
     # default; the umbrella is then spawned over stdio by Claude Code /
     # Blipply. Because the umbrella execs into the per-aspect binary on